@@ -0,0 +1,663 @@
+;; Gerado por mepa-rs a partir de 44 instruções MEPA
+(module
+  (import "mepa" "leit" (func $leit (param i32) (result i32)))
+  (import "mepa" "impr" (func $impr (param i32)))
+  (import "mepa" "erro" (func $erro (param i32 i32 i32)))
+  (memory (export "memoria") 1)
+  (global $s (export "s") (mut i32) (i32.const -1))
+  (global $m_len (mut i32) (i32.const 0))
+
+  ;; índice de M[s - profundidade], se existir
+  (func $pilha (param $profundidade i32) (param $i i32) (result i32)
+    global.get $s
+    local.get $profundidade
+    i32.sub
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      local.get $i
+      i32.const 0
+      call $erro
+      unreachable
+    end
+    global.get $s
+    local.get $profundidade
+    i32.sub
+  )
+
+  (func $endereco (param $addr i32) (param $i i32) (result i32)
+    local.get $addr
+    i32.const 0
+    i32.lt_s
+    local.get $addr
+    global.get $m_len
+    i32.ge_s
+    i32.or
+    if
+      i32.const 2
+      local.get $i
+      local.get $addr
+      call $erro
+      unreachable
+    end
+    local.get $addr
+  )
+
+  (func $carrega (param $addr i32) (result i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    i32.load
+  )
+
+  (func $guarda (param $addr i32) (param $valor i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    local.get $valor
+    i32.store
+  )
+
+  (func $aloc (param $quantidade i32)
+    (local $paginas i32)
+    global.get $s
+    local.get $quantidade
+    i32.add
+    global.set $s
+    global.get $s
+    i32.const 1
+    i32.add
+    global.get $m_len
+    i32.gt_s
+    if
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $m_len
+      ;; páginas de 64 KiB necessárias; as novas já vêm zeradas
+      global.get $m_len
+      i32.const 2
+      i32.shl
+      i32.const 65535
+      i32.add
+      i32.const 16
+      i32.shr_u
+      local.tee $paginas
+      memory.size
+      i32.gt_u
+      if
+        local.get $paginas
+        memory.size
+        i32.sub
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          i32.const 7
+          i32.const -1
+          i32.const 0
+          call $erro
+          unreachable
+        end
+      end
+    end
+  )
+
+  (func $empilha (param $valor i32)
+    i32.const 1
+    call $aloc
+    global.get $s
+    local.get $valor
+    call $guarda
+  )
+
+  (func $desempilha (param $quantidade i32)
+    global.get $s
+    local.get $quantidade
+    i32.sub
+    global.set $s
+  )
+
+  ;; divisão euclidiana: o resto nunca é negativo
+  (func $divi (param $a i32) (param $b i32) (result i32)
+    (local $q i32)
+    local.get $a
+    i32.const -2147483648
+    i32.eq
+    local.get $b
+    i32.const -1
+    i32.eq
+    i32.and
+    if
+      i32.const -2147483648
+      return
+    end
+    local.get $a
+    local.get $b
+    i32.div_s
+    local.set $q
+    local.get $a
+    local.get $b
+    i32.rem_s
+    i32.const 0
+    i32.lt_s
+    if
+      local.get $q
+      i32.const -1
+      i32.const 1
+      local.get $b
+      i32.const 0
+      i32.gt_s
+      select
+      i32.add
+      local.set $q
+    end
+    local.get $q
+  )
+
+  (func $conj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    i32.const 0
+    i32.ne
+    local.get $b
+    i32.const 0
+    i32.ne
+    i32.and
+  )
+
+  (func $disj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.or
+    i32.const 0
+    i32.ne
+  )
+  (global $d0 (mut i32) (i32.const -1))
+  (global $d1 (mut i32) (i32.const -1))
+
+  (func $main (export "main")
+    (local $i i32) (local $x i32) (local $y i32) (local $t i32)
+    block $fim
+    loop $dispatch
+    block $encerrado
+    block $i43
+    block $i42
+    block $i41
+    block $i40
+    block $i39
+    block $i38
+    block $i37
+    block $i36
+    block $i35
+    block $i34
+    block $i33
+    block $i32
+    block $i31
+    block $i30
+    block $i29
+    block $i28
+    block $i27
+    block $i26
+    block $i25
+    block $i24
+    block $i23
+    block $i22
+    block $i21
+    block $i20
+    block $i19
+    block $i18
+    block $i17
+    block $i16
+    block $i15
+    block $i14
+    block $i13
+    block $i12
+    block $i11
+    block $i10
+    block $i9
+    block $i8
+    block $i7
+    block $i6
+    block $i5
+    block $i4
+    block $i3
+    block $i2
+    block $i1
+    block $i0
+      local.get $i
+      br_table $i0 $i1 $i2 $i3 $i4 $i5 $i6 $i7 $i8 $i9 $i10 $i11 $i12 $i13 $i14 $i15 $i16 $i17 $i18 $i19 $i20 $i21 $i22 $i23 $i24 $i25 $i26 $i27 $i28 $i29 $i30 $i31 $i32 $i33 $i34 $i35 $i36 $i37 $i38 $i39 $i40 $i41 $i42 $i43 $encerrado
+    end
+      ;; 0 INPP
+      i32.const -1
+      global.set $s
+      i32.const 0
+      global.set $d0
+    end
+      ;; 1 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 2 DSVS 36
+      i32.const 36
+      local.set $i
+      br $dispatch
+    end
+      ;; 3 L2: ENPR 1
+      global.get $d1
+      call $empilha
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $d1
+    end
+      ;; 4 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 5 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 5
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 6 CRCT 2
+      i32.const 2
+      call $empilha
+    end
+      ;; 7 CMME
+      i32.const 1
+      i32.const 7
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 7
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.lt_s
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 8 DSVF 14
+      i32.const 0
+      i32.const 8
+      call $pilha
+      call $carrega
+      i32.const 1
+      call $desempilha
+      i32.eqz
+      if
+      i32.const 14
+      local.set $i
+      br $dispatch
+      end
+    end
+      ;; 9 CRVI 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 9
+      call $endereco
+      call $carrega
+      i32.const 9
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 10 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 10
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 11 SOMA
+      i32.const 1
+      i32.const 11
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 11
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 12 ARMI 1 -3
+      i32.const 0
+      i32.const 12
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 12
+      call $endereco
+      call $carrega
+      i32.const 12
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 13 DSVS 29
+      i32.const 29
+      local.set $i
+      br $dispatch
+    end
+      ;; 14 L3: NADA
+      nop
+    end
+      ;; 15 CRVI 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 15
+      call $endereco
+      call $carrega
+      i32.const 15
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 16 ARMZ 1 0
+      i32.const 0
+      i32.const 16
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 16
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 17 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 17
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 18 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 19 SUBT
+      i32.const 1
+      i32.const 19
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 19
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.sub
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 20 CREN 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      call $empilha
+    end
+      ;; 21 CHPR 3
+      i32.const 22
+      call $empilha
+      i32.const 3
+      local.set $i
+      br $dispatch
+    end
+      ;; 22 CRVL 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 22
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 23 ARMI 1 -3
+      i32.const 0
+      i32.const 23
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 23
+      call $endereco
+      call $carrega
+      i32.const 23
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 24 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 24
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 25 CRCT 2
+      i32.const 2
+      call $empilha
+    end
+      ;; 26 SUBT
+      i32.const 1
+      i32.const 26
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 26
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.sub
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 27 CRVL 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 27
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 28 CHPR 3
+      i32.const 29
+      call $empilha
+      i32.const 3
+      local.set $i
+      br $dispatch
+    end
+      ;; 29 L4: NADA
+      nop
+    end
+      ;; 30 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 30
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 31 IMPR
+      i32.const 0
+      i32.const 31
+      call $pilha
+      call $carrega
+      call $impr
+      i32.const 1
+      call $desempilha
+    end
+      ;; 32 CRVI 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 32
+      call $endereco
+      call $carrega
+      i32.const 32
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 33 IMPR
+      i32.const 0
+      i32.const 33
+      call $pilha
+      call $carrega
+      call $impr
+      i32.const 1
+      call $desempilha
+    end
+      ;; 34 DMEM 1
+      i32.const 0
+      i32.const 34
+      call $pilha
+      drop
+      i32.const 1
+      call $desempilha
+    end
+      ;; 35 RTPR 1 2
+      i32.const 1
+      i32.const 35
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 35
+      call $pilha
+      local.set $y
+      i32.const 3
+      i32.const 35
+      call $pilha
+      drop
+      local.get $x
+      call $carrega
+      local.tee $t
+      i32.const 44
+      i32.ge_u
+      if
+      i32.const 5
+      i32.const 35
+      local.get $t
+      call $erro
+      unreachable
+      end
+      local.get $y
+      call $carrega
+      global.set $d1
+      i32.const 4
+      call $desempilha
+      local.get $t
+      local.set $i
+      br $dispatch
+    end
+      ;; 36 L1: NADA
+      nop
+    end
+      ;; 37 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 38 ARMZ 0 0
+      i32.const 0
+      i32.const 38
+      call $pilha
+      local.set $t
+      global.get $d0
+      i32.const 0
+      i32.add
+      i32.const 38
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 39 CRCT 3
+      i32.const 3
+      call $empilha
+    end
+      ;; 40 CREN 0 0
+      global.get $d0
+      i32.const 0
+      i32.add
+      call $empilha
+    end
+      ;; 41 CHPR 3
+      i32.const 42
+      call $empilha
+      i32.const 3
+      local.set $i
+      br $dispatch
+    end
+      ;; 42 DMEM 1
+      i32.const 0
+      i32.const 42
+      call $pilha
+      drop
+      i32.const 1
+      call $desempilha
+    end
+      ;; 43 PARA
+      br $fim
+    end
+      i32.const 6
+      local.get $i
+      i32.const 0
+      call $erro
+      unreachable
+    end
+    end
+  )
+)
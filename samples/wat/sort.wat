@@ -0,0 +1,1880 @@
+;; Gerado por mepa-rs a partir de 135 instruções MEPA
+(module
+  (import "mepa" "leit" (func $leit (param i32) (result i32)))
+  (import "mepa" "impr" (func $impr (param i32)))
+  (import "mepa" "erro" (func $erro (param i32 i32 i32)))
+  (memory (export "memoria") 1)
+  (global $s (export "s") (mut i32) (i32.const -1))
+  (global $m_len (mut i32) (i32.const 0))
+
+  ;; índice de M[s - profundidade], se existir
+  (func $pilha (param $profundidade i32) (param $i i32) (result i32)
+    global.get $s
+    local.get $profundidade
+    i32.sub
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      local.get $i
+      i32.const 0
+      call $erro
+      unreachable
+    end
+    global.get $s
+    local.get $profundidade
+    i32.sub
+  )
+
+  (func $endereco (param $addr i32) (param $i i32) (result i32)
+    local.get $addr
+    i32.const 0
+    i32.lt_s
+    local.get $addr
+    global.get $m_len
+    i32.ge_s
+    i32.or
+    if
+      i32.const 2
+      local.get $i
+      local.get $addr
+      call $erro
+      unreachable
+    end
+    local.get $addr
+  )
+
+  (func $carrega (param $addr i32) (result i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    i32.load
+  )
+
+  (func $guarda (param $addr i32) (param $valor i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    local.get $valor
+    i32.store
+  )
+
+  (func $aloc (param $quantidade i32)
+    (local $paginas i32)
+    global.get $s
+    local.get $quantidade
+    i32.add
+    global.set $s
+    global.get $s
+    i32.const 1
+    i32.add
+    global.get $m_len
+    i32.gt_s
+    if
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $m_len
+      ;; páginas de 64 KiB necessárias; as novas já vêm zeradas
+      global.get $m_len
+      i32.const 2
+      i32.shl
+      i32.const 65535
+      i32.add
+      i32.const 16
+      i32.shr_u
+      local.tee $paginas
+      memory.size
+      i32.gt_u
+      if
+        local.get $paginas
+        memory.size
+        i32.sub
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          i32.const 7
+          i32.const -1
+          i32.const 0
+          call $erro
+          unreachable
+        end
+      end
+    end
+  )
+
+  (func $empilha (param $valor i32)
+    i32.const 1
+    call $aloc
+    global.get $s
+    local.get $valor
+    call $guarda
+  )
+
+  (func $desempilha (param $quantidade i32)
+    global.get $s
+    local.get $quantidade
+    i32.sub
+    global.set $s
+  )
+
+  ;; divisão euclidiana: o resto nunca é negativo
+  (func $divi (param $a i32) (param $b i32) (result i32)
+    (local $q i32)
+    local.get $a
+    i32.const -2147483648
+    i32.eq
+    local.get $b
+    i32.const -1
+    i32.eq
+    i32.and
+    if
+      i32.const -2147483648
+      return
+    end
+    local.get $a
+    local.get $b
+    i32.div_s
+    local.set $q
+    local.get $a
+    local.get $b
+    i32.rem_s
+    i32.const 0
+    i32.lt_s
+    if
+      local.get $q
+      i32.const -1
+      i32.const 1
+      local.get $b
+      i32.const 0
+      i32.gt_s
+      select
+      i32.add
+      local.set $q
+    end
+    local.get $q
+  )
+
+  (func $conj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    i32.const 0
+    i32.ne
+    local.get $b
+    i32.const 0
+    i32.ne
+    i32.and
+  )
+
+  (func $disj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.or
+    i32.const 0
+    i32.ne
+  )
+  (global $d0 (mut i32) (i32.const -1))
+  (global $d1 (mut i32) (i32.const -1))
+
+  (func $main (export "main")
+    (local $i i32) (local $x i32) (local $y i32) (local $t i32)
+    block $fim
+    loop $dispatch
+    block $encerrado
+    block $i134
+    block $i133
+    block $i132
+    block $i131
+    block $i130
+    block $i129
+    block $i128
+    block $i127
+    block $i126
+    block $i125
+    block $i124
+    block $i123
+    block $i122
+    block $i121
+    block $i120
+    block $i119
+    block $i118
+    block $i117
+    block $i116
+    block $i115
+    block $i114
+    block $i113
+    block $i112
+    block $i111
+    block $i110
+    block $i109
+    block $i108
+    block $i107
+    block $i106
+    block $i105
+    block $i104
+    block $i103
+    block $i102
+    block $i101
+    block $i100
+    block $i99
+    block $i98
+    block $i97
+    block $i96
+    block $i95
+    block $i94
+    block $i93
+    block $i92
+    block $i91
+    block $i90
+    block $i89
+    block $i88
+    block $i87
+    block $i86
+    block $i85
+    block $i84
+    block $i83
+    block $i82
+    block $i81
+    block $i80
+    block $i79
+    block $i78
+    block $i77
+    block $i76
+    block $i75
+    block $i74
+    block $i73
+    block $i72
+    block $i71
+    block $i70
+    block $i69
+    block $i68
+    block $i67
+    block $i66
+    block $i65
+    block $i64
+    block $i63
+    block $i62
+    block $i61
+    block $i60
+    block $i59
+    block $i58
+    block $i57
+    block $i56
+    block $i55
+    block $i54
+    block $i53
+    block $i52
+    block $i51
+    block $i50
+    block $i49
+    block $i48
+    block $i47
+    block $i46
+    block $i45
+    block $i44
+    block $i43
+    block $i42
+    block $i41
+    block $i40
+    block $i39
+    block $i38
+    block $i37
+    block $i36
+    block $i35
+    block $i34
+    block $i33
+    block $i32
+    block $i31
+    block $i30
+    block $i29
+    block $i28
+    block $i27
+    block $i26
+    block $i25
+    block $i24
+    block $i23
+    block $i22
+    block $i21
+    block $i20
+    block $i19
+    block $i18
+    block $i17
+    block $i16
+    block $i15
+    block $i14
+    block $i13
+    block $i12
+    block $i11
+    block $i10
+    block $i9
+    block $i8
+    block $i7
+    block $i6
+    block $i5
+    block $i4
+    block $i3
+    block $i2
+    block $i1
+    block $i0
+      local.get $i
+      br_table $i0 $i1 $i2 $i3 $i4 $i5 $i6 $i7 $i8 $i9 $i10 $i11 $i12 $i13 $i14 $i15 $i16 $i17 $i18 $i19 $i20 $i21 $i22 $i23 $i24 $i25 $i26 $i27 $i28 $i29 $i30 $i31 $i32 $i33 $i34 $i35 $i36 $i37 $i38 $i39 $i40 $i41 $i42 $i43 $i44 $i45 $i46 $i47 $i48 $i49 $i50 $i51 $i52 $i53 $i54 $i55 $i56 $i57 $i58 $i59 $i60 $i61 $i62 $i63 $i64 $i65 $i66 $i67 $i68 $i69 $i70 $i71 $i72 $i73 $i74 $i75 $i76 $i77 $i78 $i79 $i80 $i81 $i82 $i83 $i84 $i85 $i86 $i87 $i88 $i89 $i90 $i91 $i92 $i93 $i94 $i95 $i96 $i97 $i98 $i99 $i100 $i101 $i102 $i103 $i104 $i105 $i106 $i107 $i108 $i109 $i110 $i111 $i112 $i113 $i114 $i115 $i116 $i117 $i118 $i119 $i120 $i121 $i122 $i123 $i124 $i125 $i126 $i127 $i128 $i129 $i130 $i131 $i132 $i133 $i134 $encerrado
+    end
+      ;; 0 INPP
+      i32.const -1
+      global.set $s
+      i32.const 0
+      global.set $d0
+    end
+      ;; 1 AMEM 2
+      i32.const 2
+      call $aloc
+    end
+      ;; 2 DSVS 131
+      i32.const 131
+      local.set $i
+      br $dispatch
+    end
+      ;; 3 ENPR 1
+      global.get $d1
+      call $empilha
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $d1
+    end
+      ;; 4 AMEM 5
+      i32.const 5
+      call $aloc
+    end
+      ;; 5 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 6 ARMZ 1 2
+      i32.const 0
+      i32.const 6
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 6
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 7 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 7
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 8 CRVL 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 8
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 9 CMME
+      i32.const 1
+      i32.const 9
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 9
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.lt_s
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 10 DSVF 63
+      i32.const 0
+      i32.const 10
+      call $pilha
+      call $carrega
+      i32.const 1
+      call $desempilha
+      i32.eqz
+      if
+      i32.const 63
+      local.set $i
+      br $dispatch
+      end
+    end
+      ;; 11 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 11
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 12 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 13 SOMA
+      i32.const 1
+      i32.const 13
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 13
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 14 ARMZ 1 3
+      i32.const 0
+      i32.const 14
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 14
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 15 CRVL 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 15
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 16 CRVL 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 16
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 17 CMME
+      i32.const 1
+      i32.const 17
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 17
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.lt_s
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 18 DSVF 58
+      i32.const 0
+      i32.const 18
+      call $pilha
+      call $carrega
+      i32.const 1
+      call $desempilha
+      i32.eqz
+      if
+      i32.const 58
+      local.set $i
+      br $dispatch
+      end
+    end
+      ;; 19 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 19
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 20 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 20
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 21 SOMA
+      i32.const 1
+      i32.const 21
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 21
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 22 ARMZ 1 0
+      i32.const 0
+      i32.const 22
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 22
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 23 CRVI 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 23
+      call $endereco
+      call $carrega
+      i32.const 23
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 24 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 24
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 25 CRVL 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 25
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 26 SOMA
+      i32.const 1
+      i32.const 26
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 26
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 27 ARMZ 1 0
+      i32.const 0
+      i32.const 27
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 27
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 28 CRVI 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 28
+      call $endereco
+      call $carrega
+      i32.const 28
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 29 CMMA
+      i32.const 1
+      i32.const 29
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 29
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.gt_s
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 30 DSVF 53
+      i32.const 0
+      i32.const 30
+      call $pilha
+      call $carrega
+      i32.const 1
+      call $desempilha
+      i32.eqz
+      if
+      i32.const 53
+      local.set $i
+      br $dispatch
+      end
+    end
+      ;; 31 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 31
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 32 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 32
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 33 SOMA
+      i32.const 1
+      i32.const 33
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 33
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 34 ARMZ 1 0
+      i32.const 0
+      i32.const 34
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 34
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 35 CRVI 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 35
+      call $endereco
+      call $carrega
+      i32.const 35
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 36 ARMZ 1 4
+      i32.const 0
+      i32.const 36
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 4
+      i32.add
+      i32.const 36
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 37 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 37
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 38 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 38
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 39 SOMA
+      i32.const 1
+      i32.const 39
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 39
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 40 ARMZ 1 1
+      i32.const 0
+      i32.const 40
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 40
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 41 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 41
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 42 CRVL 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 42
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 43 SOMA
+      i32.const 1
+      i32.const 43
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 43
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 44 ARMZ 1 0
+      i32.const 0
+      i32.const 44
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 44
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 45 CRVI 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 45
+      call $endereco
+      call $carrega
+      i32.const 45
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 46 ARMI 1 1
+      i32.const 0
+      i32.const 46
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 46
+      call $endereco
+      call $carrega
+      i32.const 46
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 47 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 47
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 48 CRVL 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 48
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 49 SOMA
+      i32.const 1
+      i32.const 49
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 49
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 50 ARMZ 1 1
+      i32.const 0
+      i32.const 50
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 50
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 51 CRVL 1 4
+      global.get $d1
+      i32.const 4
+      i32.add
+      i32.const 51
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 52 ARMI 1 1
+      i32.const 0
+      i32.const 52
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 52
+      call $endereco
+      call $carrega
+      i32.const 52
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 53 CRVL 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 53
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 54 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 55 SOMA
+      i32.const 1
+      i32.const 55
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 55
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 56 ARMZ 1 3
+      i32.const 0
+      i32.const 56
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 3
+      i32.add
+      i32.const 56
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 57 DSVS 15
+      i32.const 15
+      local.set $i
+      br $dispatch
+    end
+      ;; 58 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 58
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 59 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 60 SOMA
+      i32.const 1
+      i32.const 60
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 60
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 61 ARMZ 1 2
+      i32.const 0
+      i32.const 61
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 61
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 62 DSVS 7
+      i32.const 7
+      local.set $i
+      br $dispatch
+    end
+      ;; 63 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 64 ARMZ 1 -5
+      i32.const 0
+      i32.const 64
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const -5
+      i32.add
+      i32.const 64
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 65 DMEM 5
+      i32.const 4
+      i32.const 65
+      call $pilha
+      drop
+      i32.const 5
+      call $desempilha
+    end
+      ;; 66 RTPR 1 2
+      i32.const 1
+      i32.const 66
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 66
+      call $pilha
+      local.set $y
+      i32.const 3
+      i32.const 66
+      call $pilha
+      drop
+      local.get $x
+      call $carrega
+      local.tee $t
+      i32.const 135
+      i32.ge_u
+      if
+      i32.const 5
+      i32.const 66
+      local.get $t
+      call $erro
+      unreachable
+      end
+      local.get $y
+      call $carrega
+      global.set $d1
+      i32.const 4
+      call $desempilha
+      local.get $t
+      local.set $i
+      br $dispatch
+    end
+      ;; 67 ENPR 1
+      global.get $d1
+      call $empilha
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $d1
+    end
+      ;; 68 AMEM 3
+      i32.const 3
+      call $aloc
+    end
+      ;; 69 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 70 ARMZ 1 2
+      i32.const 0
+      i32.const 70
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 70
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 71 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 71
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 72 CRVL 1 -3
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 72
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 73 CMME
+      i32.const 1
+      i32.const 73
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 73
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.lt_s
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 74 DSVF 86
+      i32.const 0
+      i32.const 74
+      call $pilha
+      call $carrega
+      i32.const 1
+      call $desempilha
+      i32.eqz
+      if
+      i32.const 86
+      local.set $i
+      br $dispatch
+      end
+    end
+      ;; 75 CRVL 1 -4
+      global.get $d1
+      i32.const -4
+      i32.add
+      i32.const 75
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 76 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 76
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 77 SOMA
+      i32.const 1
+      i32.const 77
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 77
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 78 ARMZ 1 0
+      i32.const 0
+      i32.const 78
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 78
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 79 CRVI 1 0
+      global.get $d1
+      i32.const 0
+      i32.add
+      i32.const 79
+      call $endereco
+      call $carrega
+      i32.const 79
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 80 IMPR
+      i32.const 0
+      i32.const 80
+      call $pilha
+      call $carrega
+      call $impr
+      i32.const 1
+      call $desempilha
+    end
+      ;; 81 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 81
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 82 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 83 SOMA
+      i32.const 1
+      i32.const 83
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 83
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 84 ARMZ 1 2
+      i32.const 0
+      i32.const 84
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 84
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 85 DSVS 71
+      i32.const 71
+      local.set $i
+      br $dispatch
+    end
+      ;; 86 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 87 ARMZ 1 -5
+      i32.const 0
+      i32.const 87
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const -5
+      i32.add
+      i32.const 87
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 88 DMEM 3
+      i32.const 2
+      i32.const 88
+      call $pilha
+      drop
+      i32.const 3
+      call $desempilha
+    end
+      ;; 89 RTPR 1 2
+      i32.const 1
+      i32.const 89
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 89
+      call $pilha
+      local.set $y
+      i32.const 3
+      i32.const 89
+      call $pilha
+      drop
+      local.get $x
+      call $carrega
+      local.tee $t
+      i32.const 135
+      i32.ge_u
+      if
+      i32.const 5
+      i32.const 89
+      local.get $t
+      call $erro
+      unreachable
+      end
+      local.get $y
+      call $carrega
+      global.set $d1
+      i32.const 4
+      call $desempilha
+      local.get $t
+      local.set $i
+      br $dispatch
+    end
+      ;; 90 ENPR 1
+      global.get $d1
+      call $empilha
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $d1
+    end
+      ;; 91 AMEM 7
+      i32.const 7
+      call $aloc
+    end
+      ;; 92 CREN 1 3
+      global.get $d1
+      i32.const 3
+      i32.add
+      call $empilha
+    end
+      ;; 93 ARMZ 1 2
+      i32.const 0
+      i32.const 93
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 93
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 94 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 94
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 95 CRCT 0
+      i32.const 0
+      call $empilha
+    end
+      ;; 96 SOMA
+      i32.const 1
+      i32.const 96
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 96
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 97 ARMZ 1 1
+      i32.const 0
+      i32.const 97
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 97
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 98 CRCT 2
+      i32.const 2
+      call $empilha
+    end
+      ;; 99 ARMI 1 1
+      i32.const 0
+      i32.const 99
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 99
+      call $endereco
+      call $carrega
+      i32.const 99
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 100 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 100
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 101 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 102 SOMA
+      i32.const 1
+      i32.const 102
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 102
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 103 ARMZ 1 1
+      i32.const 0
+      i32.const 103
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 103
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 104 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 105 ARMI 1 1
+      i32.const 0
+      i32.const 105
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 105
+      call $endereco
+      call $carrega
+      i32.const 105
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 106 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 106
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 107 CRCT 2
+      i32.const 2
+      call $empilha
+    end
+      ;; 108 SOMA
+      i32.const 1
+      i32.const 108
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 108
+      call $pilha
+      local.set $y
+      local.get $x
+      local.get $x
+      call $carrega
+      local.get $y
+      call $carrega
+      i32.add
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 109 ARMZ 1 1
+      i32.const 0
+      i32.const 109
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 109
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 110 CRCT 3
+      i32.const 3
+      call $empilha
+    end
+      ;; 111 ARMI 1 1
+      i32.const 0
+      i32.const 111
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 1
+      i32.add
+      i32.const 111
+      call $endereco
+      call $carrega
+      i32.const 111
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 112 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 113 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 113
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 114 CRCT 3
+      i32.const 3
+      call $empilha
+    end
+      ;; 115 CHPR 67
+      i32.const 116
+      call $empilha
+      i32.const 67
+      local.set $i
+      br $dispatch
+    end
+      ;; 116 ARMZ 1 6
+      i32.const 0
+      i32.const 116
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 6
+      i32.add
+      i32.const 116
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 117 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 118 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 118
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 119 CRCT 3
+      i32.const 3
+      call $empilha
+    end
+      ;; 120 CHPR 3
+      i32.const 121
+      call $empilha
+      i32.const 3
+      local.set $i
+      br $dispatch
+    end
+      ;; 121 ARMZ 1 6
+      i32.const 0
+      i32.const 121
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 6
+      i32.add
+      i32.const 121
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 122 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 123 CRVL 1 2
+      global.get $d1
+      i32.const 2
+      i32.add
+      i32.const 123
+      call $endereco
+      call $carrega
+      call $empilha
+    end
+      ;; 124 CRCT 3
+      i32.const 3
+      call $empilha
+    end
+      ;; 125 CHPR 67
+      i32.const 126
+      call $empilha
+      i32.const 67
+      local.set $i
+      br $dispatch
+    end
+      ;; 126 ARMZ 1 6
+      i32.const 0
+      i32.const 126
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const 6
+      i32.add
+      i32.const 126
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 127 CRCT 1
+      i32.const 1
+      call $empilha
+    end
+      ;; 128 ARMZ 1 -3
+      i32.const 0
+      i32.const 128
+      call $pilha
+      local.set $t
+      global.get $d1
+      i32.const -3
+      i32.add
+      i32.const 128
+      call $endereco
+      local.get $t
+      call $carrega
+      call $guarda
+      i32.const 1
+      call $desempilha
+    end
+      ;; 129 DMEM 7
+      i32.const 6
+      i32.const 129
+      call $pilha
+      drop
+      i32.const 7
+      call $desempilha
+    end
+      ;; 130 RTPR 1 0
+      i32.const 1
+      i32.const 130
+      call $pilha
+      local.set $x
+      i32.const 0
+      i32.const 130
+      call $pilha
+      local.set $y
+      i32.const 1
+      i32.const 130
+      call $pilha
+      drop
+      local.get $x
+      call $carrega
+      local.tee $t
+      i32.const 135
+      i32.ge_u
+      if
+      i32.const 5
+      i32.const 130
+      local.get $t
+      call $erro
+      unreachable
+      end
+      local.get $y
+      call $carrega
+      global.set $d1
+      i32.const 2
+      call $desempilha
+      local.get $t
+      local.set $i
+      br $dispatch
+    end
+      ;; 131 AMEM 1
+      i32.const 1
+      call $aloc
+    end
+      ;; 132 CHPR 90
+      i32.const 133
+      call $empilha
+      i32.const 90
+      local.set $i
+      br $dispatch
+    end
+      ;; 133 DMEM 3
+      i32.const 2
+      i32.const 133
+      call $pilha
+      drop
+      i32.const 3
+      call $desempilha
+    end
+      ;; 134 PARA
+      br $fim
+    end
+      i32.const 6
+      local.get $i
+      i32.const 0
+      call $erro
+      unreachable
+    end
+    end
+  )
+)
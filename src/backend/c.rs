@@ -0,0 +1,336 @@
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::MepaResult;
+use crate::mepa::instruction::Instruction;
+use crate::mepa::label::Label;
+use std::fmt::Write;
+
+// Estado da máquina e verificações, com as mesmas mensagens de erro da BasicMachine.
+// A aritmética passa por uint32_t para dar a volta em overflow como no interpretador,
+// sem comportamento indefinido.
+const RUNTIME: &str = r#"#include <inttypes.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+/* nem todo programa usa todos os auxiliares */
+#if defined(__GNUC__)
+#define AUXILIAR static __attribute__((unused))
+#else
+#define AUXILIAR static
+#endif
+
+static int32_t *M = NULL;
+static int32_t m_len = 0;
+static int32_t m_cap = 0;
+static int32_t D[NIVEIS];
+static int32_t s = -1;
+
+static void falha(const char *formato, ...) {
+    va_list args;
+    va_start(args, formato);
+    vfprintf(stderr, formato, args);
+    va_end(args);
+    fputc('\n', stderr);
+    exit(1);
+}
+
+/* índice de M[s - profundidade], se existir */
+AUXILIAR int32_t pilha(int32_t profundidade, int32_t i, const char *instr) {
+    if (s - profundidade < 0) {
+        falha("Erro de execução na instrução %" PRId32 " (%s): pilha vazia", i, instr);
+    }
+    return s - profundidade;
+}
+
+AUXILIAR int32_t nivel(int32_t k, int32_t i, const char *instr) {
+    if (k < 0 || k >= NIVEIS) {
+        falha("Erro de execução na instrução %" PRId32 " (%s): nível léxico inválido %" PRId32,
+              i, instr, k);
+    }
+    return k;
+}
+
+AUXILIAR int32_t endereco(int32_t addr, int32_t i, const char *instr) {
+    if (addr < 0 || addr >= m_len) {
+        falha("Erro de execução na instrução %" PRId32 " (%s): endereço de memória inválido %" PRId32,
+              i, instr, addr);
+    }
+    return addr;
+}
+
+AUXILIAR int32_t soma(int32_t a, int32_t b) { return (int32_t)((uint32_t)a + (uint32_t)b); }
+AUXILIAR int32_t subt(int32_t a, int32_t b) { return (int32_t)((uint32_t)a - (uint32_t)b); }
+AUXILIAR int32_t mult(int32_t a, int32_t b) { return (int32_t)((uint32_t)a * (uint32_t)b); }
+AUXILIAR int32_t invr(int32_t a) { return (int32_t)(0u - (uint32_t)a); }
+
+/* divisão euclidiana: o resto nunca é negativo */
+AUXILIAR int32_t divi(int32_t a, int32_t b) {
+    int32_t q, r;
+    if (a == INT32_MIN && b == -1) {
+        return INT32_MIN;
+    }
+    q = a / b;
+    r = a % b;
+    if (r < 0) {
+        q = b > 0 ? q - 1 : q + 1;
+    }
+    return q;
+}
+
+/* endereço D[m] + n */
+AUXILIAR int32_t variavel(int32_t m, int32_t n, int32_t i, const char *instr) {
+    return endereco(soma(D[nivel(m, i, instr)], n), i, instr);
+}
+
+AUXILIAR void aloc(int32_t quantidade) {
+    s += quantidade;
+    if (s + 1 > m_len) {
+        int32_t novo = s + 1;
+        if (novo > m_cap) {
+            int32_t cap = m_cap == 0 ? 1024 : m_cap;
+            while (cap < novo) {
+                cap *= 2;
+            }
+            M = realloc(M, (size_t)cap * sizeof(int32_t));
+            if (M == NULL) {
+                falha("Erro de execução: memória insuficiente");
+            }
+            m_cap = cap;
+        }
+        while (m_len < novo) {
+            M[m_len++] = 0;
+        }
+    }
+}
+
+AUXILIAR int32_t leit(int32_t i) {
+    int32_t valor;
+    if (scanf("%" SCNd32, &valor) != 1) {
+        falha("Erro de execução na instrução %" PRId32 " (LEIT): a entrada acabou", i);
+    }
+    return valor;
+}
+"#;
+
+/// Traduz o programa para um único arquivo C99, sem dependências além da biblioteca padrão.
+///
+/// Cada instrução vira um `case` de um `switch` sobre `i`; instruções em sequência caem
+/// umas nas outras e só os desvios voltam ao `switch`. `LEIT` lê inteiros da entrada
+/// padrão e `IMPR` escreve um por linha na saída padrão. Erros de execução são escritos
+/// na saída de erro com as mesmas mensagens do interpretador, e o programa termina com 1.
+///
+/// A aritmética é sempre a padrão do interpretador: dá a volta em overflow e `DIVI` é
+/// euclidiana, sem considerar `--arithmetic` ou `--division`.
+pub fn emit_c(code: MepaCode) -> MepaResult<String> {
+    let code = code.assemble()?;
+    let len = code.len();
+    let mut c = String::new();
+    let _ = writeln!(
+        c,
+        "/* Gerado por mepa-rs a partir de {} instruções MEPA */",
+        len
+    );
+    // o tamanho de D depende do nível léxico mais profundo do programa
+    let _ = writeln!(c, "#define NIVEIS {}", code.lexical_levels());
+    c.push_str(RUNTIME);
+    let _ = writeln!(c, "\n#define INSTRUCOES {}\n", len);
+    c.push_str("int main(void) {\n    int32_t i = 0, k;\n");
+    c.push_str("    for (k = 0; k < NIVEIS; k++) {\n        D[k] = -1;\n    }\n");
+    c.push_str("    for (;;) {\n        switch (i) {\n");
+
+    for (index, (label, instruction)) in code.iter().enumerate() {
+        let text = instruction.to_string();
+        let label = match label {
+            Some(Label::Simbolic(name)) => format!("{}: ", name),
+            _ => String::new(),
+        };
+        let _ = writeln!(c, "        case {}: /* {}{} */", index, label, text);
+        let at = format!("{}, \"{}\"", index, text);
+        let jump = |target: usize| {
+            if target == index + 1 {
+                String::new()
+            } else {
+                format!(" i = {}; continue;", target)
+            }
+        };
+        let body = match instruction {
+            Instruction::CRCT(k) => format!("aloc(1); M[s] = {};", k),
+            Instruction::CRVL(m, n) => format!(
+                "{{ int32_t v = M[variavel({}, {}, {})]; aloc(1); M[s] = v; }}",
+                m, n, at
+            ),
+            Instruction::CREN(m, n) => format!(
+                "{{ int32_t v = soma(D[nivel({}, {})], {}); aloc(1); M[s] = v; }}",
+                m, at, n
+            ),
+            Instruction::ARMZ(m, n) => format!(
+                "{{ int32_t t = pilha(0, {a}); M[variavel({}, {}, {a})] = M[t]; s--; }}",
+                m,
+                n,
+                a = at
+            ),
+            Instruction::CRVI(m, n) => format!(
+                "{{ int32_t v = M[endereco(M[variavel({}, {}, {a})], {a})]; aloc(1); M[s] = v; }}",
+                m,
+                n,
+                a = at
+            ),
+            Instruction::ARMI(m, n) => format!(
+                "{{ int32_t t = pilha(0, {a}); M[endereco(M[variavel({}, {}, {a})], {a})] = M[t]; s--; }}",
+                m,
+                n,
+                a = at
+            ),
+            Instruction::SOMA => binary(&at, "soma(a, b)"),
+            Instruction::SUBT => binary(&at, "subt(a, b)"),
+            Instruction::MULT => binary(&at, "mult(a, b)"),
+            Instruction::DIVI => format!(
+                "if (M[pilha(0, {a})] == 0) {{ falha(\"Erro de execução na instrução {} ({}): divisão por zero\"); }} {}",
+                index,
+                text,
+                binary(&at, "divi(a, b)"),
+                a = at
+            ),
+            Instruction::INVR => format!("{{ int32_t a = pilha(0, {}); M[a] = invr(M[a]); }}", at),
+            Instruction::CONJ => binary(&at, "a != 0 && b != 0"),
+            Instruction::DISJ => binary(&at, "a != 0 || b != 0"),
+            Instruction::NEGA => format!("{{ int32_t a = pilha(0, {}); M[a] = M[a] == 0; }}", at),
+            Instruction::CMME => binary(&at, "a < b"),
+            Instruction::CMMA => binary(&at, "a > b"),
+            Instruction::CMIG => binary(&at, "a == b"),
+            Instruction::CMDG => binary(&at, "a != b"),
+            Instruction::CMEG => binary(&at, "a <= b"),
+            Instruction::CMAG => binary(&at, "a >= b"),
+            Instruction::DSVS(p) => format!("i = {}; continue;", p.unwrap()),
+            Instruction::DSVF(p) => format!(
+                "{{ int32_t cond = M[pilha(0, {})]; s--; if (cond == 0) {{ i = {}; continue; }} }}",
+                at,
+                p.unwrap()
+            ),
+            Instruction::NADA => String::new(),
+            Instruction::PARA => "goto fim;".to_owned(),
+            Instruction::LEIT => format!("{{ int32_t v = leit({}); aloc(1); M[s] = v; }}", index),
+            Instruction::IMPR => format!(
+                "printf(\"%\" PRId32 \"\\n\", M[pilha(0, {})]); s--;",
+                at
+            ),
+            Instruction::AMEM(n) => format!("aloc({});", n),
+            // s pode chegar a -1 (pilha vazia), mas não abaixo disso
+            Instruction::DMEM(n) => format!("pilha({}, {}); s -= {};", n - 1, at, n),
+            Instruction::INPP => format!("s = -1; D[0] = 0;{}", jump(1)),
+            Instruction::CHPR(p) => format!("aloc(1); M[s] = {}; i = {}; continue;", index + 1, p.unwrap()),
+            Instruction::ENPR(k) => format!(
+                "{{ int32_t k = nivel({}, {}); aloc(1); M[s] = D[k]; D[k] = s + 1; }}",
+                k, at
+            ),
+            Instruction::RTPR(k, n) => format!(
+                "{{ int32_t k = nivel({k}, {a}); int32_t ret = pilha(1, {a}); int32_t t = pilha(0, {a}); \
+                 pilha({}, {a}); if (M[ret] < 0 || M[ret] >= INSTRUCOES) \
+                 falha(\"Erro de execução na instrução {} ({}): retorno para endereço inválido %\" PRId32, M[ret]); \
+                 D[k] = M[t]; i = M[ret]; s -= {}; continue; }}",
+                n + 1,
+                index,
+                text,
+                n + 2,
+                k = k,
+                a = at
+            ),
+        };
+        if !body.is_empty() {
+            let _ = writeln!(c, "            {}", body);
+        }
+        // avisa o compilador C de que cair no próximo case é intencional
+        if !body.ends_with("continue;") && !body.ends_with("goto fim;") {
+            c.push_str("            /* fall through */\n");
+        }
+    }
+
+    c.push_str("        default:\n");
+    c.push_str("            falha(\"Erro de execução: Programa encerrado\");\n");
+    c.push_str("        }\n    }\nfim:\n    free(M);\n    return 0;\n}\n");
+    Ok(c)
+}
+
+// M[s - 1] = op(M[s - 1], M[s]), com `a` e `b` sendo os dois valores
+fn binary(at: &str, op: &str) -> String {
+    format!(
+        "{{ int32_t x = pilha(1, {a}); int32_t y = pilha(0, {a}); int32_t a = M[x], b = M[y]; M[x] = {}; s--; }}",
+        op,
+        a = at
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::ExecutionLimits;
+    use crate::utils::samples::{programs, run_full, INPUT};
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+    use std::{env, fs};
+
+    // compilador C do sistema, se houver
+    fn c_compiler() -> Option<String> {
+        let cc = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+        let available = Command::new(&cc)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        available.then_some(cc)
+    }
+
+    #[test]
+    fn compiled_programs_match_full_machine() {
+        let Some(cc) = c_compiler() else {
+            eprintln!("nenhum compilador C encontrado; teste ignorado");
+            return;
+        };
+        let dir = env::temp_dir().join(format!("mepa-rs-emit-c-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input: Vec<String> = INPUT.iter().map(|n| n.to_string()).collect();
+
+        for (index, (name, code)) in programs().into_iter().enumerate() {
+            let (result, expected, _) = run_full(code.clone(), ExecutionLimits::default());
+
+            let source = dir.join(format!("programa{}.c", index));
+            let binary = dir.join(format!("programa{}", index));
+            fs::write(&source, emit_c(code).unwrap()).unwrap();
+            let status = Command::new(&cc)
+                .args(["-std=c99", "-O1", "-o"])
+                .arg(&binary)
+                .arg(&source)
+                .status()
+                .unwrap();
+            assert!(status.success(), "{}: falha ao compilar o C gerado", name);
+
+            let mut child = Command::new(&binary)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap();
+            // o programa pode terminar sem ler toda a entrada, fechando o pipe
+            let _ = child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(input.join(" ").as_bytes());
+            let run = child.wait_with_output().unwrap();
+            let output: Vec<i32> = String::from_utf8(run.stdout)
+                .unwrap()
+                .lines()
+                .map(|line| line.trim().parse().unwrap())
+                .collect();
+            assert_eq!(output, expected, "{}", name);
+            assert_eq!(run.status.success(), result.is_ok(), "{}", name);
+            if let Err(message) = result {
+                let stderr = String::from_utf8(run.stderr).unwrap();
+                assert_eq!(stderr.trim(), message, "{}", name);
+            }
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,7 @@
+//! Tradução do código MEPA para outras linguagens, para rodar sem o interpretador
+
+pub mod c;
+pub mod wat;
+
+pub use c::emit_c;
+pub use wat::emit_wat;
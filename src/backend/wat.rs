@@ -0,0 +1,562 @@
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::{MepaError, MepaResult};
+use crate::mepa::instruction::Instruction;
+use crate::mepa::label::Label;
+use std::fmt::Write;
+
+/// Códigos passados para a função importada `erro`, junto com o endereço da instrução
+/// e um valor que depende do erro
+pub const PILHA_VAZIA: i32 = 1;
+/// O valor é o endereço acessado
+pub const ENDERECO_INVALIDO: i32 = 2;
+pub const DIVISAO_POR_ZERO: i32 = 3;
+/// O valor é o nível léxico usado
+pub const NIVEL_INVALIDO: i32 = 4;
+/// O valor é o destino do retorno
+pub const RETORNO_INVALIDO: i32 = 5;
+/// A execução passou da última instrução sem encontrar `PARA`
+pub const PROGRAMA_ENCERRADO: i32 = 6;
+/// `memory.grow` falhou
+pub const MEMORIA_INSUFICIENTE: i32 = 7;
+
+// Estado da máquina e auxiliares. M fica na memória linear (célula k nos bytes 4k..4k+4),
+// D em globais `$d0`, `$d1`, ..., geradas para cada programa, pois o nível de cada
+// instrução é conhecido na tradução.
+// A aritmética de i32 do wasm já dá a volta em overflow, como o interpretador.
+const RUNTIME: &str = r#"  (import "mepa" "leit" (func $leit (param i32) (result i32)))
+  (import "mepa" "impr" (func $impr (param i32)))
+  (import "mepa" "erro" (func $erro (param i32 i32 i32)))
+  (memory (export "memoria") 1)
+  (global $s (export "s") (mut i32) (i32.const -1))
+  (global $m_len (mut i32) (i32.const 0))
+
+  ;; índice de M[s - profundidade], se existir
+  (func $pilha (param $profundidade i32) (param $i i32) (result i32)
+    global.get $s
+    local.get $profundidade
+    i32.sub
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      local.get $i
+      i32.const 0
+      call $erro
+      unreachable
+    end
+    global.get $s
+    local.get $profundidade
+    i32.sub
+  )
+
+  (func $endereco (param $addr i32) (param $i i32) (result i32)
+    local.get $addr
+    i32.const 0
+    i32.lt_s
+    local.get $addr
+    global.get $m_len
+    i32.ge_s
+    i32.or
+    if
+      i32.const 2
+      local.get $i
+      local.get $addr
+      call $erro
+      unreachable
+    end
+    local.get $addr
+  )
+
+  (func $carrega (param $addr i32) (result i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    i32.load
+  )
+
+  (func $guarda (param $addr i32) (param $valor i32)
+    local.get $addr
+    i32.const 2
+    i32.shl
+    local.get $valor
+    i32.store
+  )
+
+  (func $aloc (param $quantidade i32)
+    (local $paginas i32)
+    global.get $s
+    local.get $quantidade
+    i32.add
+    global.set $s
+    global.get $s
+    i32.const 1
+    i32.add
+    global.get $m_len
+    i32.gt_s
+    if
+      global.get $s
+      i32.const 1
+      i32.add
+      global.set $m_len
+      ;; páginas de 64 KiB necessárias; as novas já vêm zeradas
+      global.get $m_len
+      i32.const 2
+      i32.shl
+      i32.const 65535
+      i32.add
+      i32.const 16
+      i32.shr_u
+      local.tee $paginas
+      memory.size
+      i32.gt_u
+      if
+        local.get $paginas
+        memory.size
+        i32.sub
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          i32.const 7
+          i32.const -1
+          i32.const 0
+          call $erro
+          unreachable
+        end
+      end
+    end
+  )
+
+  (func $empilha (param $valor i32)
+    i32.const 1
+    call $aloc
+    global.get $s
+    local.get $valor
+    call $guarda
+  )
+
+  (func $desempilha (param $quantidade i32)
+    global.get $s
+    local.get $quantidade
+    i32.sub
+    global.set $s
+  )
+
+  ;; divisão euclidiana: o resto nunca é negativo
+  (func $divi (param $a i32) (param $b i32) (result i32)
+    (local $q i32)
+    local.get $a
+    i32.const -2147483648
+    i32.eq
+    local.get $b
+    i32.const -1
+    i32.eq
+    i32.and
+    if
+      i32.const -2147483648
+      return
+    end
+    local.get $a
+    local.get $b
+    i32.div_s
+    local.set $q
+    local.get $a
+    local.get $b
+    i32.rem_s
+    i32.const 0
+    i32.lt_s
+    if
+      local.get $q
+      i32.const -1
+      i32.const 1
+      local.get $b
+      i32.const 0
+      i32.gt_s
+      select
+      i32.add
+      local.set $q
+    end
+    local.get $q
+  )
+
+  (func $conj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    i32.const 0
+    i32.ne
+    local.get $b
+    i32.const 0
+    i32.ne
+    i32.and
+  )
+
+  (func $disj (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.or
+    i32.const 0
+    i32.ne
+  )
+"#;
+
+/// Traduz o programa para um módulo WebAssembly em formato texto (WAT).
+///
+/// O módulo importa de `"mepa"` as funções `leit(instrução) -> i32`, `impr(valor)` e
+/// `erro(código, instrução, valor)`, exporta a memória `memoria` com `M` e a função `main`.
+/// `leit` recebe o endereço do `LEIT`, para que o hospedeiro possa reportar a falta de
+/// entrada; `erro` recebe um dos códigos deste módulo e não deve retornar.
+///
+/// Cada instrução vira um bloco e um `br_table` sobre `i` faz os desvios, então o texto
+/// gerado depende só do programa e pode ser comparado diretamente.
+///
+/// Como em [`emit_c`](super::emit_c), a aritmética dá a volta em overflow e `DIVI` é
+/// euclidiana, qualquer que seja o `--arithmetic` ou `--division` da linha de comando.
+pub fn emit_wat(code: MepaCode) -> MepaResult<String> {
+    let code = code.assemble()?;
+    let len = code.len();
+    let mut wat = String::new();
+    let _ = writeln!(
+        wat,
+        ";; Gerado por mepa-rs a partir de {} instruções MEPA",
+        len
+    );
+    wat.push_str("(module\n");
+    wat.push_str(RUNTIME);
+    let levels = code.lexical_levels();
+    for k in 0..levels {
+        let _ = writeln!(wat, "  (global $d{} (mut i32) (i32.const -1))", k);
+    }
+    wat.push_str("\n  (func $main (export \"main\")\n");
+    wat.push_str("    (local $i i32) (local $x i32) (local $y i32) (local $t i32)\n");
+    wat.push_str("    block $fim\n    loop $dispatch\n    block $encerrado\n");
+    for index in (0..len).rev() {
+        let _ = writeln!(wat, "    block $i{}", index);
+    }
+    let targets: Vec<String> = (0..len).map(|index| format!("$i{}", index)).collect();
+    let _ = writeln!(wat, "      local.get $i");
+    let _ = writeln!(wat, "      br_table {} $encerrado", targets.join(" "));
+
+    for (index, (label, instruction)) in code.iter().enumerate() {
+        let label = match label {
+            Some(Label::Simbolic(name)) => format!("{}: ", name),
+            _ => String::new(),
+        };
+        let _ = writeln!(wat, "    end");
+        let _ = writeln!(wat, "      ;; {} {}{}", index, label, instruction);
+        for op in translate(index, instruction, len, levels) {
+            let _ = writeln!(wat, "      {}", op);
+        }
+    }
+
+    wat.push_str("    end\n");
+    let _ = writeln!(wat, "      i32.const {}", PROGRAMA_ENCERRADO);
+    let _ = writeln!(wat, "      local.get $i");
+    wat.push_str("      i32.const 0\n      call $erro\n      unreachable\n");
+    wat.push_str("    end\n    end\n  )\n)\n");
+    Ok(wat)
+}
+
+/// Converte uma chamada à função importada `erro` no erro equivalente do interpretador
+pub fn trap_error(code: &MepaCode, trap: i32, instruction_index: i32, value: i32) -> MepaError {
+    let index = instruction_index as usize;
+    let instruction = match code.get(index) {
+        Some((_, instruction)) => instruction.clone(),
+        None => return MepaError::Runtime("Programa encerrado".to_owned()),
+    };
+    match trap {
+        PILHA_VAZIA => MepaError::StackUnderflow {
+            instruction_index: index,
+            instruction,
+        },
+        ENDERECO_INVALIDO => MepaError::InvalidAddress {
+            addr: value,
+            instruction_index: index,
+            instruction,
+        },
+        DIVISAO_POR_ZERO => MepaError::DivisionByZero {
+            instruction_index: index,
+            instruction,
+        },
+        NIVEL_INVALIDO => MepaError::InvalidLevel {
+            level: value,
+            instruction_index: index,
+            instruction,
+        },
+        RETORNO_INVALIDO => MepaError::InvalidJump {
+            target: value,
+            instruction_index: index,
+            instruction,
+        },
+        MEMORIA_INSUFICIENTE => MepaError::Runtime("memória insuficiente".to_owned()),
+        _ => MepaError::Runtime("Programa encerrado".to_owned()),
+    }
+}
+
+// Código de uma instrução, com o mesmo comportamento e a mesma ordem de verificações
+// da BasicMachine
+fn translate(index: usize, instruction: &Instruction, len: usize, levels: usize) -> Vec<String> {
+    let mut ops: Vec<String> = Vec::new();
+    // empilha o endereço de M[s - profundidade]
+    let stack = |ops: &mut Vec<String>, depth: i32| {
+        ops.push(format!("i32.const {}", depth));
+        ops.push(format!("i32.const {}", index));
+        ops.push("call $pilha".to_owned());
+    };
+    let trap = |ops: &mut Vec<String>, code: i32, value: i32| {
+        ops.push(format!("i32.const {}", code));
+        ops.push(format!("i32.const {}", index));
+        ops.push(format!("i32.const {}", value));
+        ops.push("call $erro".to_owned());
+        ops.push("unreachable".to_owned());
+    };
+    // global de D[k]; um nível inexistente vira o erro correspondente
+    let level = |ops: &mut Vec<String>, k: i32| {
+        if k >= 0 && (k as usize) < levels {
+            Some(format!("$d{}", k))
+        } else {
+            trap(ops, NIVEL_INVALIDO, k);
+            None
+        }
+    };
+    // empilha o endereço D[m] + n
+    let variable = |ops: &mut Vec<String>, m: i32, n: i32| {
+        let d = level(ops, m)?;
+        ops.push(format!("global.get {}", d));
+        ops.push(format!("i32.const {}", n));
+        ops.push("i32.add".to_owned());
+        ops.push(format!("i32.const {}", index));
+        ops.push("call $endereco".to_owned());
+        Some(())
+    };
+    let jump = |ops: &mut Vec<String>, target: usize| {
+        if target != index + 1 {
+            ops.push(format!("i32.const {}", target));
+            ops.push("local.set $i".to_owned());
+            ops.push("br $dispatch".to_owned());
+        }
+    };
+    // M[s - 1] = op(M[s - 1], M[s])
+    let binary = |ops: &mut Vec<String>, op: &str| {
+        stack(ops, 1);
+        ops.push("local.set $x".to_owned());
+        stack(ops, 0);
+        ops.push("local.set $y".to_owned());
+        ops.push("local.get $x".to_owned());
+        ops.push("local.get $x".to_owned());
+        ops.push("call $carrega".to_owned());
+        ops.push("local.get $y".to_owned());
+        ops.push("call $carrega".to_owned());
+        ops.push(op.to_owned());
+        ops.push("call $guarda".to_owned());
+        ops.push("i32.const 1".to_owned());
+        ops.push("call $desempilha".to_owned());
+    };
+
+    match instruction {
+        Instruction::CRCT(k) => {
+            ops.push(format!("i32.const {}", k));
+            ops.push("call $empilha".to_owned());
+        }
+        Instruction::CRVL(m, n) => {
+            if variable(&mut ops, *m, *n).is_some() {
+                ops.push("call $carrega".to_owned());
+                ops.push("call $empilha".to_owned());
+            }
+        }
+        Instruction::CREN(m, n) => {
+            if let Some(d) = level(&mut ops, *m) {
+                ops.push(format!("global.get {}", d));
+                ops.push(format!("i32.const {}", n));
+                ops.push("i32.add".to_owned());
+                ops.push("call $empilha".to_owned());
+            }
+        }
+        Instruction::ARMZ(m, n) | Instruction::ARMI(m, n) => {
+            stack(&mut ops, 0);
+            ops.push("local.set $t".to_owned());
+            if variable(&mut ops, *m, *n).is_some() {
+                if let Instruction::ARMI(_, _) = instruction {
+                    ops.push("call $carrega".to_owned());
+                    ops.push(format!("i32.const {}", index));
+                    ops.push("call $endereco".to_owned());
+                }
+                ops.push("local.get $t".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push("call $guarda".to_owned());
+                ops.push("i32.const 1".to_owned());
+                ops.push("call $desempilha".to_owned());
+            }
+        }
+        Instruction::CRVI(m, n) => {
+            if variable(&mut ops, *m, *n).is_some() {
+                ops.push("call $carrega".to_owned());
+                ops.push(format!("i32.const {}", index));
+                ops.push("call $endereco".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push("call $empilha".to_owned());
+            }
+        }
+        Instruction::SOMA => binary(&mut ops, "i32.add"),
+        Instruction::SUBT => binary(&mut ops, "i32.sub"),
+        Instruction::MULT => binary(&mut ops, "i32.mul"),
+        Instruction::DIVI => {
+            stack(&mut ops, 0);
+            ops.push("call $carrega".to_owned());
+            ops.push("i32.eqz".to_owned());
+            ops.push("if".to_owned());
+            trap(&mut ops, DIVISAO_POR_ZERO, 0);
+            ops.push("end".to_owned());
+            binary(&mut ops, "call $divi");
+        }
+        Instruction::INVR | Instruction::NEGA => {
+            stack(&mut ops, 0);
+            ops.push("local.tee $x".to_owned());
+            if let Instruction::INVR = instruction {
+                ops.push("i32.const 0".to_owned());
+                ops.push("local.get $x".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push("i32.sub".to_owned());
+            } else {
+                ops.push("local.get $x".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push("i32.eqz".to_owned());
+            }
+            ops.push("call $guarda".to_owned());
+        }
+        Instruction::CONJ => binary(&mut ops, "call $conj"),
+        Instruction::DISJ => binary(&mut ops, "call $disj"),
+        Instruction::CMME => binary(&mut ops, "i32.lt_s"),
+        Instruction::CMMA => binary(&mut ops, "i32.gt_s"),
+        Instruction::CMIG => binary(&mut ops, "i32.eq"),
+        Instruction::CMDG => binary(&mut ops, "i32.ne"),
+        Instruction::CMEG => binary(&mut ops, "i32.le_s"),
+        Instruction::CMAG => binary(&mut ops, "i32.ge_s"),
+        Instruction::DSVS(p) => {
+            ops.push(format!("i32.const {}", p.unwrap()));
+            ops.push("local.set $i".to_owned());
+            ops.push("br $dispatch".to_owned());
+        }
+        Instruction::DSVF(p) => {
+            stack(&mut ops, 0);
+            ops.push("call $carrega".to_owned());
+            ops.push("i32.const 1".to_owned());
+            ops.push("call $desempilha".to_owned());
+            ops.push("i32.eqz".to_owned());
+            ops.push("if".to_owned());
+            ops.push(format!("i32.const {}", p.unwrap()));
+            ops.push("local.set $i".to_owned());
+            ops.push("br $dispatch".to_owned());
+            ops.push("end".to_owned());
+        }
+        Instruction::NADA => ops.push("nop".to_owned()),
+        Instruction::PARA => ops.push("br $fim".to_owned()),
+        Instruction::LEIT => {
+            ops.push(format!("i32.const {}", index));
+            ops.push("call $leit".to_owned());
+            ops.push("call $empilha".to_owned());
+        }
+        Instruction::IMPR => {
+            stack(&mut ops, 0);
+            ops.push("call $carrega".to_owned());
+            ops.push("call $impr".to_owned());
+            ops.push("i32.const 1".to_owned());
+            ops.push("call $desempilha".to_owned());
+        }
+        Instruction::AMEM(n) => {
+            ops.push(format!("i32.const {}", n));
+            ops.push("call $aloc".to_owned());
+        }
+        Instruction::DMEM(n) => {
+            // s pode chegar a -1 (pilha vazia), mas não abaixo disso
+            stack(&mut ops, n - 1);
+            ops.push("drop".to_owned());
+            ops.push(format!("i32.const {}", n));
+            ops.push("call $desempilha".to_owned());
+        }
+        Instruction::INPP => {
+            ops.push("i32.const -1".to_owned());
+            ops.push("global.set $s".to_owned());
+            ops.push("i32.const 0".to_owned());
+            ops.push("global.set $d0".to_owned());
+            jump(&mut ops, 1);
+        }
+        Instruction::CHPR(p) => {
+            ops.push(format!("i32.const {}", index + 1));
+            ops.push("call $empilha".to_owned());
+            ops.push(format!("i32.const {}", p.unwrap()));
+            ops.push("local.set $i".to_owned());
+            ops.push("br $dispatch".to_owned());
+        }
+        Instruction::ENPR(k) => {
+            if let Some(d) = level(&mut ops, *k) {
+                ops.push(format!("global.get {}", d));
+                ops.push("call $empilha".to_owned());
+                ops.push("global.get $s".to_owned());
+                ops.push("i32.const 1".to_owned());
+                ops.push("i32.add".to_owned());
+                ops.push(format!("global.set {}", d));
+            }
+        }
+        Instruction::RTPR(k, n) => {
+            if let Some(d) = level(&mut ops, *k) {
+                stack(&mut ops, 1);
+                ops.push("local.set $x".to_owned());
+                stack(&mut ops, 0);
+                ops.push("local.set $y".to_owned());
+                stack(&mut ops, n + 1);
+                ops.push("drop".to_owned());
+                // destino do retorno
+                ops.push("local.get $x".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push("local.tee $t".to_owned());
+                ops.push(format!("i32.const {}", len));
+                ops.push("i32.ge_u".to_owned());
+                ops.push("if".to_owned());
+                ops.push(format!("i32.const {}", RETORNO_INVALIDO));
+                ops.push(format!("i32.const {}", index));
+                ops.push("local.get $t".to_owned());
+                ops.push("call $erro".to_owned());
+                ops.push("unreachable".to_owned());
+                ops.push("end".to_owned());
+                ops.push("local.get $y".to_owned());
+                ops.push("call $carrega".to_owned());
+                ops.push(format!("global.set {}", d));
+                ops.push(format!("i32.const {}", n + 2));
+                ops.push("call $desempilha".to_owned());
+                ops.push("local.get $t".to_owned());
+                ops.push("local.set $i".to_owned());
+                ops.push("br $dispatch".to_owned());
+            }
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::samples::programs;
+    use std::fs;
+
+    // programas de samples/mepa com a tradução esperada em samples/wat
+    const GOLDEN: [&str; 3] = ["c1", "recursao", "sort"];
+
+    #[test]
+    fn output_matches_golden_files() {
+        for name in GOLDEN {
+            let code = MepaCode::from_file(format!("samples/mepa/{}.mepa", name)).unwrap();
+            let expected = fs::read_to_string(format!("samples/wat/{}.wat", name)).unwrap();
+            assert_eq!(emit_wat(code).unwrap(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn output_is_valid_webassembly() {
+        for (name, code) in programs() {
+            let wat = emit_wat(code).unwrap();
+            let binary = wat::parse_str(&wat).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            wasmparser::Validator::new()
+                .validate_all(&binary)
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::fs;
 use std::io;
 use std::path::PathBuf;
 
@@ -8,13 +9,16 @@ use crate::{ensure_is_token, is_token, mepa::instruction::Instruction};
 use super::error::CompileError;
 use super::lexic::{Lexic, Token};
 use super::simbol_table::{SimbolTable, VarType, Variable};
+use super::source_map::{FunctionInfo, SourceMap, VariableInfo};
 use crate::mepa::code::MepaCode;
+use crate::mepa::syntax::MepaSyntax;
 
 struct Compiler {
     tokens: Lexic,
     simbols: SimbolTable,
     generated_code: MepaCode,
     current_function: Option<String>,
+    source_map: SourceMap,
 }
 impl Compiler {
     pub fn new(file_path: &PathBuf) -> Result<Compiler, CompileError> {
@@ -23,6 +27,10 @@ impl Compiler {
             simbols: SimbolTable::new(),
             generated_code: MepaCode::with_capacity(256),
             current_function: None,
+            source_map: SourceMap {
+                source: Some(file_path.display().to_string()),
+                ..SourceMap::default()
+            },
         })
     }
 
@@ -32,11 +40,45 @@ impl Compiler {
             simbols: SimbolTable::new(),
             generated_code: MepaCode::with_capacity(256),
             current_function: None,
+            source_map: SourceMap::default(),
         })
     }
 
+    // Insere a instrução, anotando a linha do fonte que a gerou
+    fn emit(&mut self, line: (Option<Label>, Instruction)) {
+        self.generated_code.insert(line);
+        self.source_map.lines.push(self.tokens.last_line());
+    }
+
+    // Declara a variável na tabela de símbolos e no mapa do fonte
+    fn declare(
+        &mut self,
+        name: String,
+        var_type: VarType,
+        offset: i32,
+        size: i32,
+    ) -> Result<(), CompileError> {
+        let variable = VariableInfo {
+            name: name.clone(),
+            var_type,
+            level: if self.current_function.is_none() {
+                0
+            } else {
+                1
+            },
+            offset,
+            size,
+        };
+        match (&self.current_function, self.source_map.functions.last_mut()) {
+            (Some(_), Some(function)) => function.variables.push(variable),
+            _ => self.source_map.globals.push(variable),
+        }
+        self.simbols
+            .new_variable(self.current_function.clone(), Variable::new(name, offset))
+    }
+
     fn program(&mut self) -> Result<(), CompileError> {
-        self.generated_code.insert((None, Instruction::INPP));
+        self.emit((None, Instruction::INPP));
         let global_vars = self.declarations()?;
         while is_token!(self.tokens.next(), Token::Function) {
             self.function_def()?;
@@ -44,17 +86,16 @@ impl Compiler {
 
         //after defining the functions, there should be no token left
         if let None = self.tokens.next() {
-            self.generated_code.insert((None, Instruction::AMEM(1)));
-            self.generated_code.insert((
+            self.emit((None, Instruction::AMEM(1)));
+            self.emit((
                 None,
                 Instruction::CHPR(Label::new(self.simbols.get_fn_label("main").ok_or_else(
                     || CompileError::Semantic(format!("Função 'main' não encontrada")),
                 )?)),
             ));
             //libera as variaveis globais + vars reservada para offset + variavel de retorno da main
-            self.generated_code
-                .insert((None, Instruction::DMEM(global_vars as i32 + 3)));
-            self.generated_code.insert((None, Instruction::PARA));
+            self.emit((None, Instruction::DMEM(global_vars as i32 + 3)));
+            self.emit((None, Instruction::PARA));
             Ok(())
         } else {
             Err(CompileError::Sintatic(
@@ -70,14 +111,20 @@ impl Compiler {
             self.tokens.current_line()
         );
         self.tokens.consume()?;
+        let start_line = self.tokens.last_line();
         if let Token::Identifier(id) = self.tokens.consume()? {
             self.current_function = Some(id.clone());
             let label_init = Label::new(self.simbols.new_function(id.clone())?);
+            self.source_map.functions.push(FunctionInfo {
+                name: id.clone(),
+                label: label_init.to_string(),
+                start_line,
+                end_line: start_line,
+                variables: Vec::new(),
+            });
             let label_end = Label::new(self.simbols.new_label());
-            self.generated_code
-                .insert((None, Instruction::DSVS(label_end.clone())));
-            self.generated_code
-                .insert((Some(label_init), Instruction::ENPR(1)));
+            self.emit((None, Instruction::DSVS(label_end.clone())));
+            self.emit((Some(label_init), Instruction::ENPR(1)));
             ensure_is_token!(
                 self.tokens.next(),
                 Token::OpenParenthesis,
@@ -86,9 +133,8 @@ impl Compiler {
             self.tokens.consume()?;
             let parameters = self.parameter_list()?;
             let l = parameters.len() as i32;
-            for (i, (_, name)) in parameters.into_iter().enumerate() {
-                self.simbols
-                    .new_variable(Some(id.clone()), Variable::new(name, i as i32 - (2 + l)))?;
+            for (i, (var_type, name)) in parameters.into_iter().enumerate() {
+                self.declare(name, var_type, i as i32 - (2 + l), 1)?;
             }
             ensure_is_token!(
                 self.tokens.next(),
@@ -117,11 +163,10 @@ impl Compiler {
                 );
                 self.tokens.consume()?;
             } else {
-                self.generated_code.insert((None, Instruction::CRCT(0)));
+                self.emit((None, Instruction::CRCT(0)));
             }
             //store at reserved return position
-            self.generated_code
-                .insert((None, Instruction::ARMZ(1, -(3 + l))));
+            self.emit((None, Instruction::ARMZ(1, -(3 + l))));
 
             ensure_is_token!(
                 self.tokens.next(),
@@ -130,13 +175,13 @@ impl Compiler {
             );
             self.tokens.consume()?;
             self.current_function = None;
+            if let Some(function) = self.source_map.functions.last_mut() {
+                function.end_line = self.tokens.last_line();
+            }
 
-            self.generated_code
-                .insert((None, Instruction::DMEM(local_vars as i32 + 2)));
-            self.generated_code
-                .insert((None, Instruction::RTPR(1, l as i32)));
-            self.generated_code
-                .insert((Some(label_end), Instruction::NADA));
+            self.emit((None, Instruction::DMEM(local_vars as i32 + 2)));
+            self.emit((None, Instruction::RTPR(1, l as i32)));
+            self.emit((Some(label_end), Instruction::NADA));
             Ok(())
         } else {
             unreachable!()
@@ -151,16 +196,12 @@ impl Compiler {
 
         let l: usize = (v.iter().map(|(_, _, value)| value).sum::<i32>()) as usize;
         //reserva duas variaveis para  calculo de offsets de array: uma para lvalue e outra para rvalue
-        self.generated_code
-            .insert((None, Instruction::AMEM(l as i32 + 2)));
+        self.emit((None, Instruction::AMEM(l as i32 + 2)));
         let mut acumulator = 2;
         for (var_type, name, size) in v.into_iter() {
-            self.simbols.new_variable(
-                self.current_function.clone(),
-                Variable::new(name, acumulator),
-            )?;
+            self.declare(name, var_type, acumulator, size)?;
             if let VarType::Array = var_type {
-                self.generated_code.insert((
+                self.emit((
                     None,
                     Instruction::CREN(
                         if self.current_function.is_none() {
@@ -171,7 +212,7 @@ impl Compiler {
                         acumulator + 1,
                     ),
                 ));
-                self.generated_code.insert((
+                self.emit((
                     None,
                     Instruction::ARMZ(
                         if self.current_function.is_none() {
@@ -339,10 +380,10 @@ impl Compiler {
             if is_token!(self.tokens.next(), Token::OpenBrackets) {
                 self.tokens.consume()?;
 
-                self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                self.emit((None, Instruction::CRVL(m, n)));
                 self.expression()?;
-                self.generated_code.insert((None, Instruction::SOMA));
-                self.generated_code.insert((
+                self.emit((None, Instruction::SOMA));
+                self.emit((
                     None,
                     Instruction::ARMZ(
                         if self.current_function.is_none() {
@@ -355,7 +396,7 @@ impl Compiler {
                 ));
 
                 if is_indirect_assignment {
-                    self.generated_code.insert((
+                    self.emit((
                         None,
                         Instruction::CRVI(
                             if self.current_function.is_none() {
@@ -366,7 +407,7 @@ impl Compiler {
                             1, //guarda endereço na pos reservada para lvalue
                         ),
                     ));
-                    self.generated_code.insert((
+                    self.emit((
                         None,
                         Instruction::ARMZ(
                             if self.current_function.is_none() {
@@ -393,7 +434,7 @@ impl Compiler {
                 self.tokens.consume()?;
                 self.expression()?;
 
-                self.generated_code.insert((
+                self.emit((
                     None,
                     Instruction::ARMI(
                         if self.current_function.is_none() {
@@ -412,7 +453,7 @@ impl Compiler {
                 );
                 self.tokens.consume()?;
                 self.expression()?;
-                self.generated_code.insert((
+                self.emit((
                     None,
                     if is_indirect_assignment {
                         Instruction::ARMI(m, n)
@@ -429,7 +470,7 @@ impl Compiler {
         while is_token!(self.tokens.next(), Token::Or) {
             self.tokens.consume()?;
             self.logic_expr()?;
-            self.generated_code.insert((None, Instruction::DISJ));
+            self.emit((None, Instruction::DISJ));
         }
         Ok(())
     }
@@ -438,7 +479,7 @@ impl Compiler {
         while is_token!(self.tokens.next(), Token::And) {
             self.tokens.consume()?;
             self.relational_expr()?;
-            self.generated_code.insert((None, Instruction::CONJ));
+            self.emit((None, Instruction::CONJ));
         }
         Ok(())
     }
@@ -453,7 +494,7 @@ impl Compiler {
         {
             let comparison = self.tokens.consume()?;
             self.sum()?;
-            self.generated_code.insert((
+            self.emit((
                 None,
                 match comparison {
                     Token::LesserThan => Instruction::CMME,
@@ -475,7 +516,7 @@ impl Compiler {
         {
             let op = self.tokens.consume()?;
             self.factor()?;
-            self.generated_code.insert((
+            self.emit((
                 None,
                 match op {
                     Token::Plus => Instruction::SOMA,
@@ -493,7 +534,7 @@ impl Compiler {
         {
             let op = self.tokens.consume()?;
             self.operand()?;
-            self.generated_code.insert((
+            self.emit((
                 None,
                 match op {
                     Token::Asterisc => Instruction::MULT,
@@ -518,7 +559,7 @@ impl Compiler {
         } else if is_token!(self.tokens.next(), Token::Identifier(_)) {
             if is_token!(self.tokens.next_to_next(), Token::OpenParenthesis) {
                 self.function_call()?;
-                self.generated_code.insert((None, Instruction::DMEM(1)));
+                self.emit((None, Instruction::DMEM(1)));
             } else {
                 self.attribuition()?;
                 ensure_is_token!(
@@ -575,23 +616,18 @@ impl Compiler {
             Token::CloseParenthesis,
             self.tokens.current_line()
         );
-        self.generated_code
-            .insert((None, Instruction::DSVF(label_if.clone())));
+        self.emit((None, Instruction::DSVF(label_if.clone())));
         self.tokens.consume()?;
         self.command()?;
         if is_token!(self.tokens.next(), Token::Else) {
             let label_else = Label::new(self.simbols.new_label());
-            self.generated_code
-                .insert((None, Instruction::DSVS(label_else.clone())));
-            self.generated_code
-                .insert((Some(label_if), Instruction::NADA));
+            self.emit((None, Instruction::DSVS(label_else.clone())));
+            self.emit((Some(label_if), Instruction::NADA));
             self.tokens.consume()?;
             self.command()?;
-            self.generated_code
-                .insert((Some(label_else), Instruction::NADA));
+            self.emit((Some(label_else), Instruction::NADA));
         } else {
-            self.generated_code
-                .insert((Some(label_if), Instruction::NADA));
+            self.emit((Some(label_if), Instruction::NADA));
         }
         Ok(())
     }
@@ -600,8 +636,7 @@ impl Compiler {
         self.tokens.consume()?;
         let label_init = Label::new(self.simbols.new_label());
         let label_end = Label::new(self.simbols.new_label());
-        self.generated_code
-            .insert((Some(label_init.clone()), Instruction::NADA));
+        self.emit((Some(label_init.clone()), Instruction::NADA));
         ensure_is_token!(
             self.tokens.next(),
             Token::OpenParenthesis,
@@ -609,8 +644,7 @@ impl Compiler {
         );
         self.tokens.consume()?;
         self.expression()?;
-        self.generated_code
-            .insert((None, Instruction::DSVF(label_end.clone())));
+        self.emit((None, Instruction::DSVF(label_end.clone())));
         ensure_is_token!(
             self.tokens.next(),
             Token::CloseParenthesis,
@@ -618,10 +652,8 @@ impl Compiler {
         );
         self.tokens.consume()?;
         self.command()?;
-        self.generated_code
-            .insert((None, Instruction::DSVS(label_init)));
-        self.generated_code
-            .insert((Some(label_end), Instruction::NADA));
+        self.emit((None, Instruction::DSVS(label_init)));
+        self.emit((Some(label_end), Instruction::NADA));
         Ok(())
     }
     fn read_command(&mut self) -> Result<(), CompileError> {
@@ -651,10 +683,10 @@ impl Compiler {
                 })?;
             if is_token!(self.tokens.next(), Token::OpenBrackets) {
                 self.tokens.consume()?;
-                self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                self.emit((None, Instruction::CRVL(m, n)));
                 self.expression()?;
-                self.generated_code.insert((None, Instruction::SOMA));
-                self.generated_code.insert((
+                self.emit((None, Instruction::SOMA));
+                self.emit((
                     None,
                     Instruction::ARMZ(
                         if self.current_function.is_none() {
@@ -666,9 +698,9 @@ impl Compiler {
                     ),
                 ));
 
-                self.generated_code.insert((None, Instruction::LEIT));
+                self.emit((None, Instruction::LEIT));
 
-                self.generated_code.insert((
+                self.emit((
                     None,
                     Instruction::ARMI(
                         if self.current_function.is_none() {
@@ -687,8 +719,8 @@ impl Compiler {
                 );
                 self.tokens.consume()?;
             } else {
-                self.generated_code.insert((None, Instruction::LEIT));
-                self.generated_code.insert((None, Instruction::ARMZ(m, n)));
+                self.emit((None, Instruction::LEIT));
+                self.emit((None, Instruction::ARMZ(m, n)));
             }
         }
         ensure_is_token!(
@@ -711,7 +743,7 @@ impl Compiler {
         self.tokens.consume()?;
         let args = self.argument_list()?;
         for _ in 0..args {
-            self.generated_code.insert((None, Instruction::IMPR));
+            self.emit((None, Instruction::IMPR));
         }
         ensure_is_token!(
             self.tokens.next(),
@@ -737,7 +769,7 @@ impl Compiler {
                 );
                 self.tokens.consume()?;
                 //reserve a position for return value
-                self.generated_code.insert((None, Instruction::AMEM(1)));
+                self.emit((None, Instruction::AMEM(1)));
                 self.argument_list()?;
                 ensure_is_token!(
                     self.tokens.next(),
@@ -745,7 +777,7 @@ impl Compiler {
                     self.tokens.current_line()
                 );
                 self.tokens.consume()?;
-                self.generated_code.insert((None, Instruction::CHPR(label)));
+                self.emit((None, Instruction::CHPR(label)));
             } else {
                 return Err(CompileError::Semantic(format!(
                     "Função \"{}\" não foi declarada, na linha {}",
@@ -790,10 +822,10 @@ impl Compiler {
                         })?;
                     if is_token!(self.tokens.next(), Token::OpenBrackets) {
                         self.tokens.consume()?;
-                        self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                        self.emit((None, Instruction::CRVL(m, n)));
                         self.expression()?;
-                        self.generated_code.insert((None, Instruction::SOMA));
-                        self.generated_code.insert((
+                        self.emit((None, Instruction::SOMA));
+                        self.emit((
                             None,
                             Instruction::ARMZ(
                                 if self.current_function.is_none() {
@@ -805,7 +837,7 @@ impl Compiler {
                             ),
                         ));
 
-                        self.generated_code.insert((
+                        self.emit((
                             None,
                             Instruction::CRVI(
                                 if self.current_function.is_none() {
@@ -824,13 +856,13 @@ impl Compiler {
                         );
                         self.tokens.consume()?;
                     } else {
-                        self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                        self.emit((None, Instruction::CRVL(m, n)));
                     }
                 }
             }
         } else if is_token!(self.tokens.next(), Token::Number(_)) {
             if let Token::Number(n) = self.tokens.consume()? {
-                self.generated_code.insert((None, Instruction::CRCT(n)));
+                self.emit((None, Instruction::CRCT(n)));
             }
         } else if is_token!(self.tokens.next(), Token::OpenParenthesis) {
             self.tokens.consume()?;
@@ -844,11 +876,11 @@ impl Compiler {
         } else if is_token!(self.tokens.next(), Token::Minus) {
             self.tokens.consume()?;
             self.operand()?;
-            self.generated_code.insert((None, Instruction::INVR));
+            self.emit((None, Instruction::INVR));
         } else if is_token!(self.tokens.next(), Token::Not) {
             self.tokens.consume()?;
             self.operand()?;
-            self.generated_code.insert((None, Instruction::NEGA));
+            self.emit((None, Instruction::NEGA));
         } else if is_token!(self.tokens.next(), Token::AddressOf) {
             self.tokens.consume()?;
             ensure_is_token!(
@@ -869,9 +901,9 @@ impl Compiler {
                     })?;
                 if is_token!(self.tokens.next(), Token::OpenBrackets) {
                     self.tokens.consume()?;
-                    self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                    self.emit((None, Instruction::CRVL(m, n)));
                     self.expression()?;
-                    self.generated_code.insert((None, Instruction::SOMA));
+                    self.emit((None, Instruction::SOMA));
 
                     ensure_is_token!(
                         self.tokens.next(),
@@ -880,7 +912,7 @@ impl Compiler {
                     );
                     self.tokens.consume()?;
                 } else {
-                    self.generated_code.insert((None, Instruction::CREN(m, n)));
+                    self.emit((None, Instruction::CREN(m, n)));
                 }
             }
         } else if is_token!(self.tokens.next(), Token::Asterisc) {
@@ -903,10 +935,10 @@ impl Compiler {
                     })?;
                 if is_token!(self.tokens.next(), Token::OpenBrackets) {
                     self.tokens.consume()?;
-                    self.generated_code.insert((None, Instruction::CRVL(m, n)));
+                    self.emit((None, Instruction::CRVL(m, n)));
                     self.expression()?;
-                    self.generated_code.insert((None, Instruction::SOMA));
-                    self.generated_code.insert((
+                    self.emit((None, Instruction::SOMA));
+                    self.emit((
                         None,
                         Instruction::ARMZ(
                             if self.current_function.is_none() {
@@ -918,7 +950,7 @@ impl Compiler {
                         ),
                     ));
 
-                    self.generated_code.insert((
+                    self.emit((
                         None,
                         Instruction::CRVI(
                             if self.current_function.is_none() {
@@ -929,7 +961,7 @@ impl Compiler {
                             0,
                         ),
                     ));
-                    self.generated_code.insert((
+                    self.emit((
                         None,
                         Instruction::ARMZ(
                             if self.current_function.is_none() {
@@ -940,7 +972,7 @@ impl Compiler {
                             0,
                         ),
                     ));
-                    self.generated_code.insert((
+                    self.emit((
                         None,
                         Instruction::CRVI(
                             if self.current_function.is_none() {
@@ -959,7 +991,7 @@ impl Compiler {
                     );
                     self.tokens.consume()?;
                 } else {
-                    self.generated_code.insert((None, Instruction::CRVI(m, n)));
+                    self.emit((None, Instruction::CRVI(m, n)));
                 }
             }
         }
@@ -967,16 +999,28 @@ impl Compiler {
     }
 }
 
+/// Compila `origin` para `target`. Com `anotar`, o código MEPA traz como comentários
+/// as linhas do fonte e os nomes das variáveis e funções usadas
 pub fn compile(
     origin: &PathBuf,
     target: &PathBuf,
     otimizar: bool,
+    anotar: bool,
 ) -> Result<io::Result<()>, CompileError> {
     let mut c = Compiler::new(origin)?;
     c.program()?;
     // println!("Compilado com sucesso!");
     Ok({
-        let e = c.generated_code.to_file(target);
+        let saved = if anotar {
+            fs::read_to_string(origin).and_then(|source| {
+                let trivia = c.source_map.annotations(&c.generated_code, &source);
+                MepaSyntax::new(c.generated_code.clone(), trivia).to_file(target)
+            })
+        } else {
+            c.generated_code.clone().to_file(target)
+        };
+        let e =
+            saved.and_then(|_| save_debug_info(&c.source_map, &c.generated_code, target, otimizar));
         if otimizar {
             println!("Otimizando...");
             Otimizador::from(target)
@@ -989,9 +1033,37 @@ pub fn compile(
     })
 }
 
+// Grava `programa.mepa.dbg` ao lado do código. A otimização muda os endereços das
+// instruções, então nesse caso um arquivo antigo é apagado em vez de atualizado.
+fn save_debug_info(
+    source_map: &SourceMap,
+    code: &MepaCode,
+    target: &PathBuf,
+    otimizar: bool,
+) -> io::Result<()> {
+    let sidecar = SourceMap::sidecar_path(target);
+    if otimizar {
+        return match fs::remove_file(sidecar) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let json = source_map
+        .to_json(code)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    fs::write(sidecar, json)
+}
+
 pub fn compile_from_str(input: &str) -> Result<MepaCode, CompileError> {
     let mut c = Compiler::from_str(input)?;
     c.program()?;
     // println!("Compilado com sucesso!");
     Ok(c.generated_code)
 }
+
+/// Compila sem salvar, devolvendo também a relação entre o código gerado e o fonte
+pub fn compile_with_source_map(origin: &PathBuf) -> Result<(MepaCode, SourceMap), CompileError> {
+    let mut c = Compiler::new(origin)?;
+    c.program()?;
+    Ok((c.generated_code, c.source_map))
+}
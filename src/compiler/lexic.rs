@@ -318,7 +318,8 @@ impl Reader {
 }
 
 #[derive(Debug)]
-pub struct Lexic(Vec<(Token, usize)>, usize);
+// tokens em ordem reversa, linha do próximo token e linha do último consumido
+pub struct Lexic(Vec<(Token, usize)>, usize, usize);
 
 impl Lexic {
     pub fn new(file_path: &PathBuf) -> Result<Lexic, CompileError> {
@@ -337,7 +338,7 @@ impl Lexic {
             .last()
             .ok_or_else(|| CompileError::Lexic("Arquivo sem tokens".to_owned()))?
             .1;
-        Ok(Lexic(list, line))
+        Ok(Lexic(list, line, line))
     }
 
     pub fn from_str(input: &str) -> Result<Lexic, CompileError> {
@@ -356,7 +357,7 @@ impl Lexic {
             .last()
             .ok_or_else(|| CompileError::Lexic("Arquivo sem tokens".to_owned()))?
             .1;
-        Ok(Lexic(list, line))
+        Ok(Lexic(list, line, line))
     }
 
     pub fn next(&self) -> Option<&Token> {
@@ -374,6 +375,7 @@ impl Lexic {
     pub fn consume(&mut self) -> Result<Token, CompileError> {
         match self.0.pop() {
             Some((t, l)) => {
+                self.2 = l;
                 self.1 = match self.0.last() {
                     Some(last) => last.1,
                     None => l,
@@ -386,4 +388,8 @@ impl Lexic {
     pub fn current_line(&self) -> usize {
         self.1
     }
+    /// Linha do último token consumido
+    pub fn last_line(&self) -> usize {
+        self.2
+    }
 }
@@ -2,5 +2,10 @@ mod compiler;
 mod error;
 mod lexic;
 mod simbol_table;
-pub use compiler::{compile, compile_from_str};
+mod source_map;
+pub use compiler::{compile, compile_from_str, compile_with_source_map};
 pub use error::CompileError;
+pub use simbol_table::VarType;
+pub use source_map::{
+    load_with_debug_info, FunctionInfo, SourceMap, VariableInfo, DEBUG_INFO_VERSION,
+};
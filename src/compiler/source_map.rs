@@ -0,0 +1,277 @@
+use super::simbol_table::VarType;
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::{MepaError, MepaResult};
+use crate::mepa::instruction::Instruction;
+use crate::mepa::syntax::Trivia;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Versão atual do arquivo de informações de depuração; outras versões são recusadas
+pub const DEBUG_INFO_VERSION: u32 = 1;
+
+/// Uma função do programa ipt e o trecho do código fonte que ela ocupa
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionInfo {
+    pub name: String,
+    /// Rótulo do ENPR da função no código MEPA gerado
+    pub label: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Parâmetros e variáveis locais
+    pub variables: Vec<VariableInfo>,
+}
+
+/// Uma variável (ou parâmetro) do programa ipt e as posições que ocupa,
+/// relativas a `D[level]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariableInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub var_type: VarType,
+    pub level: i32,
+    pub offset: i32,
+    /// Quantas posições ocupa (vetores ocupam mais de uma)
+    pub size: i32,
+}
+
+/// Relaciona o código MEPA gerado com o código fonte ipt. É gravado em JSON ao lado
+/// do código pelo `compile` (`programa.mepa.dbg`) e lido com `load_with_debug_info`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Caminho do arquivo ipt, quando o código foi compilado de um arquivo
+    pub source: Option<String>,
+    /// Linha do fonte que gerou cada instrução
+    pub lines: Vec<usize>,
+    pub functions: Vec<FunctionInfo>,
+    pub globals: Vec<VariableInfo>,
+}
+
+// Formato do arquivo .dbg
+#[derive(Serialize, Deserialize)]
+struct DebugInfoFile {
+    version: u32,
+    // hash do texto canônico do código, que não muda com comentários nem alinhamento
+    code_hash: String,
+    #[serde(flatten)]
+    source_map: SourceMap,
+}
+
+impl SourceMap {
+    /// Nome da função de cada rótulo de entrada (ex.: "L3" -> "fatorial")
+    pub fn function_names(&self) -> HashMap<String, String> {
+        self.functions
+            .iter()
+            .map(|f| (f.label.clone(), f.name.clone()))
+            .collect()
+    }
+
+    /// Função cujo corpo contém a linha
+    pub fn function_at(&self, line: usize) -> Option<&FunctionInfo> {
+        self.functions
+            .iter()
+            .find(|f| f.start_line <= line && line <= f.end_line)
+    }
+
+    /// Variável na posição `D[level] + offset` vista pela instrução `index`
+    /// (ex.: os argumentos de um `CRVL`)
+    pub fn variable(&self, index: usize, level: i32, offset: i32) -> Option<&VariableInfo> {
+        let variables = if level == 0 {
+            &self.globals
+        } else {
+            let line = *self.lines.get(index)?;
+            &self.function_at(line)?.variables
+        };
+        variables
+            .iter()
+            .find(|v| v.level == level && (v.offset..v.offset + v.size).contains(&offset))
+    }
+
+    /// Comentários para gravar o código com `MepaSyntax`: a linha do fonte `source` antes de
+    /// cada grupo de instruções gerado por ela, a variável acessada por `CRVL`, `ARMZ`,
+    /// `CREN`, `CRVI` e `ARMI` e a função chamada por `CHPR`
+    pub fn annotations(&self, code: &MepaCode, source: &str) -> Vec<Trivia> {
+        let source_lines: Vec<&str> = source.lines().collect();
+        let functions = self.function_names();
+        let mut previous = None;
+        code.iter()
+            .enumerate()
+            .map(|(index, (_, instruction))| {
+                let line = self.lines.get(index).copied();
+                let mut annotation = Trivia::default();
+                if line != previous {
+                    if let Some(text) = line.and_then(|l| source_lines.get(l.wrapping_sub(1))) {
+                        annotation
+                            .leading
+                            .push(format!("# {}: {}", line.unwrap(), text.trim()));
+                    }
+                    previous = line;
+                }
+                annotation.trailing = match instruction {
+                    Instruction::CRVL(m, n)
+                    | Instruction::ARMZ(m, n)
+                    | Instruction::CREN(m, n)
+                    | Instruction::CRVI(m, n)
+                    | Instruction::ARMI(m, n) => self.variable(index, *m, *n).map(|v| {
+                        if v.offset == *n {
+                            v.name.clone()
+                        } else {
+                            // posições dos elementos de um vetor, depois do ponteiro
+                            format!("{}[{}]", v.name, n - v.offset - 1)
+                        }
+                    }),
+                    Instruction::CHPR(label) => functions.get(&label.to_string()).cloned(),
+                    _ => None,
+                }
+                .map(|name| format!("# {}", name));
+                annotation
+            })
+            .collect()
+    }
+
+    /// Completa os erros do sanitizador com o nome da variável acessada
+    pub fn name_variable(&self, mut error: MepaError) -> MepaError {
+        if let MepaError::UninitializedRead {
+            instruction_index,
+            instruction,
+            variable,
+            ..
+        }
+        | MepaError::WriteAboveStack {
+            instruction_index,
+            instruction,
+            variable,
+            ..
+        } = &mut error
+        {
+            if let Instruction::CRVL(m, n) | Instruction::CRVI(m, n) | Instruction::ARMI(m, n) =
+                instruction
+            {
+                *variable = self
+                    .variable(*instruction_index, *m, *n)
+                    .map(|v| v.name.clone());
+            }
+        }
+        error
+    }
+
+    /// Onde ficam as informações de depuração do código em `mepa`: `programa.mepa.dbg`
+    pub fn sidecar_path<P: AsRef<Path>>(mepa: P) -> PathBuf {
+        let mut path = mepa.as_ref().as_os_str().to_owned();
+        path.push(".dbg");
+        PathBuf::from(path)
+    }
+
+    /// Grava junto o hash de `code`, o código a que as informações se referem
+    pub fn to_json(&self, code: &MepaCode) -> MepaResult<String> {
+        let file = DebugInfoFile {
+            version: DEBUG_INFO_VERSION,
+            code_hash: code_hash(code)?,
+            source_map: self.clone(),
+        };
+        serde_json::to_string_pretty(&file).map_err(|e| MepaError::Other(e.to_string()))
+    }
+
+    /// `None` se as informações foram gravadas para um código diferente de `code`
+    pub fn from_json(json: &str, code: &MepaCode) -> MepaResult<Option<SourceMap>> {
+        let file: DebugInfoFile = serde_json::from_str(json)
+            .map_err(|e| MepaError::Other(format!("informações de depuração inválidas: {}", e)))?;
+        if file.version != DEBUG_INFO_VERSION {
+            return Err(MepaError::Other(format!(
+                "informações de depuração na versão {}, mas só a versão {} é suportada",
+                file.version, DEBUG_INFO_VERSION
+            )));
+        }
+        let same_code = file.code_hash == code_hash(code)?;
+        Ok(Some(file.source_map).filter(|_| same_code))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, code: &MepaCode) -> MepaResult<()> {
+        fs::write(path, self.to_json(code)?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, code: &MepaCode) -> MepaResult<Option<SourceMap>> {
+        SourceMap::from_json(&fs::read_to_string(path)?, code)
+    }
+}
+
+// FNV-1a de 64 bits, que ao contrário do `DefaultHasher` não muda entre versões do Rust
+fn code_hash(code: &MepaCode) -> MepaResult<String> {
+    let hash = code
+        .to_string()?
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    Ok(format!("{:016x}", hash))
+}
+
+/// Lê o código MEPA e, se houver, as informações de depuração gravadas ao lado dele.
+/// Informações gravadas para outro código (por exemplo, de antes de uma otimização ou
+/// de uma edição do arquivo) são descartadas.
+pub fn load_with_debug_info<P: AsRef<Path>>(path: P) -> MepaResult<(MepaCode, Option<SourceMap>)> {
+    let code: MepaCode = MepaCode::from_file(&path)?;
+    let sidecar = SourceMap::sidecar_path(&path);
+    let source_map = if sidecar.exists() {
+        SourceMap::load(sidecar, &code)?
+    } else {
+        None
+    };
+    Ok((code, source_map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::str::FromStr;
+
+    fn code(source: &str) -> MepaCode {
+        MepaCode::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn debug_info_is_only_used_with_the_same_code() {
+        let original = code("INPP\nAMEM 1\nCRCT 7\nARMZ 0, 0\nDMEM 1\nPARA\n");
+        let source_map = SourceMap {
+            lines: vec![1, 1, 2, 2, 3, 3],
+            ..SourceMap::default()
+        };
+        let json = source_map.to_json(&original).unwrap();
+
+        let annotated = code("# 1: int x;\nINPP\nAMEM 1\n  CRCT   7\nARMZ 0,0 # x\nDMEM 1\nPARA\n");
+        let loaded = SourceMap::from_json(&json, &annotated).unwrap();
+        assert_eq!(loaded.unwrap().lines, source_map.lines);
+
+        // mesmo número de instruções, mas outro código
+        let edited = code("INPP\nAMEM 1\nCRCT 8\nARMZ 0, 0\nDMEM 1\nPARA\n");
+        assert!(SourceMap::from_json(&json, &edited).unwrap().is_none());
+    }
+
+    #[test]
+    fn sidecar_of_an_edited_file_is_ignored() {
+        let dir = env::temp_dir().join(format!("mepa-rs-source-map-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("programa.mepa");
+        let source = "INPP\nAMEM 1\nCRCT 7\nARMZ 0, 0\nDMEM 1\nPARA\n";
+        fs::write(&path, source).unwrap();
+        let source_map = SourceMap {
+            lines: vec![1, 1, 2, 2, 3, 3],
+            ..SourceMap::default()
+        };
+        source_map
+            .save(SourceMap::sidecar_path(&path), &code(source))
+            .unwrap();
+
+        let (_, loaded) = load_with_debug_info(&path).unwrap();
+        assert_eq!(loaded.unwrap().lines, source_map.lines);
+
+        fs::write(&path, source.replace("CRCT 7", "CRCT 8")).unwrap();
+        let (_, loaded) = load_with_debug_info(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(loaded.is_none());
+    }
+}
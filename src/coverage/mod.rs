@@ -0,0 +1,258 @@
+use crate::compiler::SourceMap;
+use crate::machine::{BasicMachine, ExecutionLimits, FullMachine, MachineObserver};
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::{MepaError, MepaResult};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Cobertura de uma função do programa ipt
+#[derive(Debug, Clone)]
+pub struct FunctionCoverage {
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub calls: u64,
+}
+
+/// Quantas vezes cada linha do fonte foi executada, somando todas as execuções
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    /// Caminho do arquivo ipt
+    pub source: String,
+    /// Apenas linhas que geraram código aparecem aqui
+    pub lines: BTreeMap<usize, u64>,
+    pub functions: Vec<FunctionCoverage>,
+}
+
+// Quantas vezes cada instrução foi executada
+struct Counts(Vec<u64>);
+
+impl MachineObserver for Counts {
+    fn on_step_end(&mut self, after: &BasicMachine) {
+        if let Some(record) = after.last_step() {
+            self.0[record.i] += 1;
+        }
+    }
+}
+
+/// Executa o código compilado com a entrada dada e mapeia as instruções
+/// executadas de volta para as linhas do fonte
+pub fn run(
+    code: MepaCode,
+    source_map: &SourceMap,
+    source: &str,
+    input: Vec<i32>,
+    limits: ExecutionLimits,
+) -> MepaResult<Coverage> {
+    let code = code.assemble()?;
+    let labels = code.label_table().map_err(MepaError::Assembly)?;
+    let mut output = Vec::new();
+    let mut counts = Counts(vec![0; code.len()]);
+    let mut machine = FullMachine::new(code)?
+        .with_limits(limits)
+        .with_observer(&mut counts)
+        .add_output(&mut output);
+    if !input.is_empty() {
+        machine = machine.add_input_vec(input);
+    }
+    machine.execute()?;
+    drop(machine);
+    let counts = counts.0;
+
+    let mut lines: BTreeMap<usize, u64> = BTreeMap::new();
+    for (line, count) in source_map.lines.iter().zip(&counts) {
+        // a linha conta como executada tantas vezes quanto sua instrução mais executada
+        let hits = lines.entry(*line).or_default();
+        *hits = (*hits).max(*count);
+    }
+    let functions = source_map
+        .functions
+        .iter()
+        .map(|f| FunctionCoverage {
+            name: f.name.clone(),
+            start_line: f.start_line,
+            end_line: f.end_line,
+            calls: labels
+                .get(&f.label)
+                .and_then(|addr| counts.get(*addr))
+                .copied()
+                .unwrap_or(0),
+        })
+        .collect();
+
+    Ok(Coverage {
+        source: source.to_owned(),
+        lines,
+        functions,
+    })
+}
+
+impl Coverage {
+    /// Soma a cobertura de outra execução do mesmo arquivo
+    pub fn merge(&mut self, other: &Coverage) {
+        for (line, hits) in &other.lines {
+            *self.lines.entry(*line).or_default() += hits;
+        }
+        for function in &other.functions {
+            match self.functions.iter_mut().find(|f| f.name == function.name) {
+                Some(f) => f.calls += function.calls,
+                None => self.functions.push(function.clone()),
+            }
+        }
+    }
+
+    /// Relatório no formato lcov (tracefile)
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        let _ = writeln!(lcov, "TN:");
+        let _ = writeln!(lcov, "SF:{}", self.source);
+        for f in &self.functions {
+            let _ = writeln!(lcov, "FN:{},{}", f.start_line, f.name);
+        }
+        for f in &self.functions {
+            let _ = writeln!(lcov, "FNDA:{},{}", f.calls, f.name);
+        }
+        let _ = writeln!(lcov, "FNF:{}", self.functions.len());
+        let _ = writeln!(
+            lcov,
+            "FNH:{}",
+            self.functions.iter().filter(|f| f.calls > 0).count()
+        );
+        for (line, hits) in &self.lines {
+            let _ = writeln!(lcov, "DA:{},{}", line, hits);
+        }
+        let _ = writeln!(lcov, "LF:{}", self.lines.len());
+        let _ = writeln!(
+            lcov,
+            "LH:{}",
+            self.lines.values().filter(|hits| **hits > 0).count()
+        );
+        let _ = writeln!(lcov, "end_of_record");
+        lcov
+    }
+
+    /// Lê um relatório gerado por `to_lcov`, para somar com novas execuções.
+    /// Só o primeiro registro (`SF` até `end_of_record`) é considerado.
+    pub fn from_lcov(text: &str) -> Result<Coverage, String> {
+        let mut coverage = Coverage {
+            source: String::new(),
+            lines: BTreeMap::new(),
+            functions: Vec::new(),
+        };
+        let number = |n: &str| {
+            n.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("número inválido '{}' no lcov", n))
+        };
+        for line in text.lines() {
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            match key {
+                "SF" => coverage.source = value.to_owned(),
+                "FN" => {
+                    let (start, name) = value.split_once(',').ok_or("FN inválido")?;
+                    let start = number(start)? as usize;
+                    coverage.functions.push(FunctionCoverage {
+                        name: name.to_owned(),
+                        start_line: start,
+                        end_line: start,
+                        calls: 0,
+                    });
+                }
+                "FNDA" => {
+                    let (calls, name) = value.split_once(',').ok_or("FNDA inválido")?;
+                    if let Some(f) = coverage.functions.iter_mut().find(|f| f.name == name) {
+                        f.calls = number(calls)?;
+                    }
+                }
+                "DA" => {
+                    let mut fields = value.split(',');
+                    let line = number(fields.next().unwrap_or(""))? as usize;
+                    let hits = number(fields.next().unwrap_or(""))?;
+                    coverage.lines.insert(line, hits);
+                }
+                "end_of_record" => break,
+                _ => (),
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// Linhas que geraram código mas nunca foram executadas
+    pub fn uncovered_lines(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    /// Tabela com a cobertura de linhas de cada função
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        let _ = writeln!(
+            summary,
+            "{:<20} {:>8} {:>9} {:>8}",
+            "função", "chamadas", "linhas", "%"
+        );
+        let ratio = |covered: usize, total: usize| {
+            if total == 0 {
+                100.0
+            } else {
+                100.0 * covered as f64 / total as f64
+            }
+        };
+        let mut rows: Vec<(String, Option<u64>, Vec<u64>)> = self
+            .functions
+            .iter()
+            .map(|f| {
+                let hits = self
+                    .lines
+                    .range(f.start_line..=f.end_line)
+                    .map(|(_, hits)| *hits)
+                    .collect();
+                (f.name.clone(), Some(f.calls), hits)
+            })
+            .collect();
+        // linhas fora de qualquer função (declarações globais e início do programa)
+        let global: Vec<u64> = self
+            .lines
+            .iter()
+            .filter(|(line, _)| {
+                !self
+                    .functions
+                    .iter()
+                    .any(|f| f.start_line <= **line && **line <= f.end_line)
+            })
+            .map(|(_, hits)| *hits)
+            .collect();
+        if !global.is_empty() {
+            rows.push(("(global)".to_owned(), None, global));
+        }
+        for (name, calls, hits) in rows {
+            let covered = hits.iter().filter(|h| **h > 0).count();
+            let _ = writeln!(
+                summary,
+                "{:<20} {:>8} {:>9} {:>7.1}%",
+                name,
+                calls.map(|c| c.to_string()).unwrap_or_default(),
+                format!("{}/{}", covered, hits.len()),
+                ratio(covered, hits.len())
+            );
+        }
+        let covered = self.lines.values().filter(|h| **h > 0).count();
+        let _ = writeln!(
+            summary,
+            "{:<20} {:>8} {:>9} {:>7.1}%",
+            "total",
+            "",
+            format!("{}/{}", covered, self.lines.len()),
+            ratio(covered, self.lines.len())
+        );
+        let uncovered = self.uncovered_lines();
+        if !uncovered.is_empty() {
+            let list: Vec<String> = uncovered.iter().map(|l| l.to_string()).collect();
+            let _ = writeln!(summary, "\nLinhas não executadas: {}", list.join(", "));
+        }
+        summary
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     compiler::compile,
-    machine::FullMachine,
-    mepa::{code::MepaCode, error::MepaResult},
+    machine::{BasicMachine, FullMachine, MachineObserver},
+    mepa::{code::MepaCode, error::MepaResult, instruction::Instruction},
     otimizador::Otimizador,
 };
 use std::path::PathBuf;
@@ -13,25 +13,39 @@ struct ExecutionInfo {
     output: Vec<i32>,
 }
 
+impl MachineObserver for ExecutionInfo {
+    fn on_step(&mut self, before: &BasicMachine, _: &Instruction) {
+        self.max_memory = self
+            .max_memory
+            .max(before.current_memory_usage().max(0) as usize);
+    }
+    // só passos concluídos contam
+    fn on_step_end(&mut self, _: &BasicMachine) {
+        self.steps += 1;
+    }
+    fn on_halt(&mut self, machine: &BasicMachine) {
+        self.max_memory = self
+            .max_memory
+            .max(machine.current_memory_usage().max(0) as usize);
+    }
+}
+
 impl ExecutionInfo {
     pub fn new(filename: &PathBuf, input: Vec<i32>) -> MepaResult<Self> {
-        let mc: MepaCode = MepaCode::from_file(filename).unwrap();
+        let mc: MepaCode = MepaCode::from_file(filename)?;
         let mut info = ExecutionInfo {
             steps: 0,
             max_memory: 0,
             instructions: mc.len(),
             output: Vec::new(),
         };
-        let mut machine = FullMachine::new(mc)
+        let mut output = Vec::new();
+        FullMachine::new(mc)?
             .add_input_vec(input)
-            .add_output(&mut info.output);
-        while !machine.ended() {
-            machine.execute_step()?;
-            info.max_memory = info
-                .max_memory
-                .max(machine.current_memory_usage().max(0) as usize);
-            info.steps += 1
-        }
+            .add_output(&mut output)
+            .with_observer(&mut info)
+            .execute()?;
+        info.output = output;
 
         Ok(info)
     }
@@ -105,7 +119,7 @@ pub fn evaluate() {
         let input_path = samples_dir.join(format!("{}.ipt", filename));
         let output_path = PathBuf::from("output").join(format!("{}.mepa", filename));
 
-        match compile(&input_path, &output_path, false) {
+        match compile(&input_path, &output_path, false, false) {
             Ok(_) => {
                 let exec_info = ExecutionInfo::new(&output_path, input.clone()).unwrap();
                 if exec_info.output != *expected_output {
@@ -1,9 +1,13 @@
+pub mod backend;
 pub mod compiler;
+pub mod coverage;
 pub mod evaluator;
 pub mod machine;
 pub mod mepa;
 pub mod otimizador;
+pub mod profiler;
 pub mod utils;
+pub mod verifier;
 use std::path::PathBuf;
 
 use serde::Serialize;
@@ -32,7 +36,10 @@ pub fn compile_code(input: &str) -> Result<String, JsValue> {
     let output = match compiler::compile_from_str(input) {
         Ok(mepacode) => {
             let mepa_string = mepacode.to_string().unwrap_or_else(|e| e.to_string());
-            let optimization_result = match Otimizador::<PathBuf>::new(mepacode, None).otimizar() {
+            let optimization_result = match Otimizador::<PathBuf>::new(mepacode, None)
+                .map_err(|e| e.into())
+                .and_then(|otimizador| otimizador.otimizar())
+            {
                 Ok(optimized_code) => Some(Ok(optimized_code.to_string())),
                 Err(e) => Some(Err(e.to_string())),
             };
@@ -49,3 +56,13 @@ pub fn compile_code(input: &str) -> Result<String, JsValue> {
 
     output.to_json_string()
 }
+
+/// Traduz um programa MEPA para WAT, para ser montado e executado pelo navegador
+/// sem passar pelo interpretador
+#[wasm_bindgen]
+pub fn compile_to_wat(input: &str) -> Result<String, JsValue> {
+    input
+        .parse::<mepa::code::MepaCode>()
+        .and_then(backend::emit_wat)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// O que `SOMA`, `SUBT`, `MULT`, `INVR` e `DIVI` fazem quando o resultado não cabe na célula
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArithmeticMode {
+    /// Dá a volta, em complemento de dois
+    #[default]
+    Wrapping,
+    /// Interrompe a execução com `MepaError::ArithmeticOverflow`
+    Checked,
+    /// Fica no maior ou no menor valor representável
+    Saturating,
+}
+
+/// Como `DIVI` arredonda o quociente
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DivisionMode {
+    /// O resto é sempre positivo: -7 / 2 = -4 e 7 / -2 = -3
+    #[default]
+    Euclidean,
+    /// Em direção ao zero, como em C: -7 / 2 = -3
+    Truncating,
+    /// Em direção a menos infinito: -7 / 2 = -4 e 7 / -2 = -4
+    Floored,
+}
+
+/// Semântica das operações aritméticas da máquina, aplicada por `Cell`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arithmetic {
+    pub mode: ArithmeticMode,
+    pub division: DivisionMode,
+}
+
+impl Arithmetic {
+    pub fn new(mode: ArithmeticMode, division: DivisionMode) -> Arithmetic {
+        Arithmetic { mode, division }
+    }
+}
+
+impl FromStr for ArithmeticMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrapping" => Ok(ArithmeticMode::Wrapping),
+            "checked" => Ok(ArithmeticMode::Checked),
+            "saturating" => Ok(ArithmeticMode::Saturating),
+            _ => Err(format!(
+                "modo aritmético desconhecido '{}' (use wrapping, checked ou saturating)",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for DivisionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euclidean" => Ok(DivisionMode::Euclidean),
+            "truncating" => Ok(DivisionMode::Truncating),
+            "floored" => Ok(DivisionMode::Floored),
+            _ => Err(format!(
+                "modo de divisão desconhecido '{}' (use euclidean, truncating ou floored)",
+                s
+            )),
+        }
+    }
+}
@@ -1,50 +1,128 @@
 use serde::Serialize;
+use std::ops::Range;
+use std::str::FromStr;
 use std::usize;
 
+use super::arithmetic::Arithmetic;
+use super::history::{History, StepRecord};
+use super::snapshot::{Snapshot, SNAPSHOT_VERSION};
 use crate::{
+    compiler::SourceMap,
     mepa::{
+        cell::Cell,
         code::MepaCode,
-        error::{MepaError, MepaResult},
+        error::{Limit, MepaError, MepaResult},
         instruction::Instruction,
+        label::Label,
     },
     utils::print_matrix,
 };
 
+/// Máquina MEPA com células do tipo `C` em `M`
 #[derive(Serialize)]
-pub struct BasicMachine {
+pub struct BasicMachine<C = i32> {
     #[serde(skip)]
-    code: MepaCode,
-    pub m: Vec<i32>,
+    code: MepaCode<C>,
+    pub m: Vec<C>,
     pub d: Vec<i32>,
     pub i: usize,
     pub s: i32,
+    /// Quantas instruções já foram executadas
+    pub steps: usize,
+    /// Tamanho máximo que M pode atingir
+    #[serde(skip)]
+    pub max_memory: Option<usize>,
+    /// Comportamento de SOMA, SUBT, MULT, INVR e DIVI em overflow e arredondamento da divisão
+    #[serde(skip)]
+    pub arithmetic: Arithmetic,
+    /// Passos recentes, para execução reversa; desligado por padrão
+    #[serde(skip)]
+    history: Option<History<C>>,
+    // desfazimento do passo em andamento
+    #[serde(skip)]
+    pending: Option<StepRecord<C>>,
+    // se o registro do último passo deve ser guardado em last_step
+    #[serde(skip)]
+    keep_last_step: bool,
+    #[serde(skip)]
+    last_step: Option<StepRecord<C>>,
+    // com o sanitizador ligado, se cada posição de M já foi escrita desde que foi alocada
+    #[serde(skip)]
+    initialized: Option<Vec<bool>>,
 }
 
-impl BasicMachine {
-    pub fn new(code: MepaCode) -> BasicMachine {
+impl<C: Cell> BasicMachine<C> {
+    /// Monta o código (resolvendo todos os rótulos) antes de criar a máquina
+    pub fn new(code: MepaCode<C>) -> MepaResult<Self> {
+        let code = code.assemble()?;
         let mut m = Vec::new();
-        let d = vec![-1; 2];
+        let d = vec![-1; code.lexical_levels()];
         unsafe {
             m.set_len(m.capacity());
         }
-        BasicMachine {
+        Ok(BasicMachine {
             code,
             m,
             d,
             i: 0,
             s: -1,
-        }
+            steps: 0,
+            max_memory: None,
+            arithmetic: Arithmetic::default(),
+            history: None,
+            pending: None,
+            keep_last_step: false,
+            last_step: None,
+            initialized: None,
+        })
+    }
+    /// Passa a guardar os últimos `capacity` passos, permitindo desfazê-los com `step_back`
+    pub fn record_history(&mut self, capacity: usize) {
+        self.history = Some(History::new(capacity));
+    }
+    pub fn history(&self) -> Option<&History<C>> {
+        self.history.as_ref()
+    }
+    /// Passa a guardar o que o último passo alterou, disponível em `last_step`
+    pub fn keep_last_step(&mut self) {
+        self.keep_last_step = true;
+    }
+    pub fn last_step(&self) -> Option<&StepRecord<C>> {
+        self.last_step.as_ref()
     }
-    pub fn from_str(code: &str) -> MepaResult<BasicMachine> {
-        let code = MepaCode::from_str(code)?;
-        Ok(Self::new(code))
+    /// Liga o sanitizador: ler com `CRVL` ou `CRVI` uma posição alocada e ainda não
+    /// escrita, ou escrever com `ARMI` acima do topo da pilha, passa a ser um erro.
+    /// O que já está em M conta como inicializado.
+    pub fn sanitize(&mut self) {
+        self.initialized = Some(vec![true; self.m.len()]);
     }
-    pub fn show_state(&self, historico: Option<&[&[i32]]>) {
+    /// Quantidade de instruções do programa
+    pub fn code_len(&self) -> usize {
+        self.code.len()
+    }
+    pub fn instruction(&self, index: usize) -> Option<&Instruction<C>> {
+        self.code.0.get(index).map(|(_, instruction)| instruction)
+    }
+    /// Rótulo da instrução, se houver
+    pub fn label(&self, index: usize) -> Option<&Label> {
+        self.code.0.get(index).and_then(|(label, _)| label.as_ref())
+    }
+    pub fn show_state(&self) {
+        self.show_state_with(None);
+    }
+
+    /// Mostra o código, D e M lado a lado, seguidos dos quadros ativos;
+    /// com o mapa do fonte, as posições dos quadros aparecem pelo nome da variável
+    pub fn show_state_with(&self, source_map: Option<&SourceMap>) {
         let code_len = self.code.0.len();
 
         let s = (self.s + 1) as usize;
 
-        let max_atingido_m = s.max(historico.iter().map(|v| v.len()).max().unwrap_or(0));
+        let max_atingido_m = s.max(
+            self.history
+                .as_ref()
+                .map_or(0, |history| (history.max_s() + 1) as usize),
+        );
 
         let mut matrix = Vec::with_capacity(code_len + 2);
         matrix.push(vec![
@@ -57,7 +135,7 @@ impl BasicMachine {
             "D".to_owned(),
             "M".to_owned(),
         ]);
-        for j in 0..10.max(max_atingido_m) {
+        for j in 0..10.max(max_atingido_m).max(self.d.len()) {
             let i = j + self.i as usize;
             let mut v = vec![
                 (if i == self.i as usize { ">" } else { "" }).to_string(),
@@ -86,6 +164,7 @@ impl BasicMachine {
 
         print_matrix(&matrix);
         println!("");
+        print!("{}", self.backtrace(source_map));
     }
 
     pub fn current_memory_usage(&self) -> i32 {
@@ -93,173 +172,326 @@ impl BasicMachine {
     }
 
     pub fn ended(&self) -> bool {
-        if let Instruction::PARA = self.code.0[self.i].1 {
-            true
-        } else {
-            false
-        }
+        matches!(self.code.0.get(self.i), Some((_, Instruction::PARA)))
     }
 
-    pub fn aloc(&mut self, amount: usize) {
+    pub fn aloc(&mut self, amount: usize) -> MepaResult<()> {
+        if let Some(max) = self.max_memory {
+            if ((self.s + 1) as usize).saturating_add(amount) > max {
+                return Err(MepaError::LimitExceeded {
+                    limit: Limit::Memory(max),
+                    steps: self.steps,
+                    instruction_index: self.i,
+                });
+            }
+        }
         self.s += amount as i32;
         if self.s + 1 > self.m.len() as i32 {
-            self.m.resize((self.s + 1) as usize, 0);
+            self.m.resize((self.s + 1) as usize, C::default());
+        }
+        if let Some(initialized) = &mut self.initialized {
+            initialized.resize(self.m.len(), false);
         }
+        let top = (self.s + 1) as usize;
+        self.mark_initialized(top - amount..top, false);
+        Ok(())
     }
 
-    pub fn step_with_input(&mut self, input: Option<i32>) -> MepaResult<Option<i32>> {
+    // posições entre s + 1 e `top` foram liberadas por DMEM ou RTPR
+    fn release(&mut self, top: i32) {
+        self.mark_initialized((self.s + 1) as usize..(top + 1) as usize, false);
+    }
+
+    // Altera as marcas do sanitizador, guardando as anteriores para desfazer o passo
+    fn mark_initialized(&mut self, range: Range<usize>, value: bool) {
+        if let Some(initialized) = &mut self.initialized {
+            for addr in range {
+                if initialized[addr] != value {
+                    if let Some(record) = &mut self.pending {
+                        record.initialized.push((addr, initialized[addr]));
+                    }
+                    initialized[addr] = value;
+                }
+            }
+        }
+    }
+
+    // Toda escrita em M e D passa por aqui, para que o passo possa ser desfeito
+    fn set_m(&mut self, addr: usize, value: C) {
+        if let Some(record) = &mut self.pending {
+            record.m.push((addr, self.m[addr].clone()));
+        }
+        self.mark_initialized(addr..addr + 1, true);
+        self.m[addr] = value;
+    }
+
+    fn set_d(&mut self, k: usize, value: i32) {
+        if let Some(record) = &mut self.pending {
+            record.d.push((k, self.d[k]));
+        }
+        self.d[k] = value;
+    }
+
+    /// Desfaz o último passo registrado, devolvendo o que foi desfeito
+    /// (inclusive a entrada consumida e a saída produzida)
+    pub fn step_back(&mut self) -> Option<StepRecord<C>> {
+        let record = self.history.as_mut()?.pop()?;
+        for (addr, value) in record.m.iter().rev() {
+            self.m[*addr] = value.clone();
+        }
+        for (k, value) in record.d.iter().rev() {
+            self.d[*k] = *value;
+        }
+        self.m.truncate(record.m_len);
+        if let Some(initialized) = &mut self.initialized {
+            for (addr, value) in record.initialized.iter().rev() {
+                initialized[*addr] = *value;
+            }
+            initialized.truncate(record.m_len);
+        }
+        self.i = record.i;
+        self.s = record.s;
+        self.steps -= 1;
+        Some(record)
+    }
+
+    // Os auxiliares abaixo apenas validam; nenhum altera o estado da máquina,
+    // para que ela continue inspecionável depois de um erro
+
+    // Os erros guardam a instrução com literal i32. Só CRCT tem literal, e CRCT
+    // nunca causa erros que mostram a instrução.
+    fn current_instruction(&self) -> Instruction {
+        self.code.0[self.i]
+            .1
+            .clone()
+            .map_literal(|k| k.to_i32_saturating())
+    }
+
+    // índice de M[s - depth], se existir
+    fn stack(&self, depth: i32) -> MepaResult<usize> {
+        let addr = self.s - depth;
+        if addr < 0 {
+            Err(MepaError::StackUnderflow {
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+            })
+        } else if addr as usize >= self.m.len() {
+            // profundidade negativa, acima do topo
+            Err(MepaError::InvalidAddress {
+                addr,
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+            })
+        } else {
+            Ok(addr as usize)
+        }
+    }
+
+    fn level(&self, k: i32) -> MepaResult<usize> {
+        if k < 0 || k as usize >= self.d.len() {
+            Err(MepaError::InvalidLevel {
+                level: k,
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+            })
+        } else {
+            Ok(k as usize)
+        }
+    }
+
+    fn address(&self, addr: i32) -> MepaResult<usize> {
+        if addr < 0 || addr as usize >= self.m.len() {
+            Err(MepaError::InvalidAddress {
+                addr,
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+            })
+        } else {
+            Ok(addr as usize)
+        }
+    }
+
+    // endereço D[m] + n
+    fn variable(&self, m: i32, n: i32) -> MepaResult<usize> {
+        self.address(self.d[self.level(m)?] + n)
+    }
+
+    // valor de M[addr] usado como endereço
+    fn indirect(&self, addr: usize) -> MepaResult<usize> {
+        self.address(self.m[addr].to_i32_saturating())
+    }
+
+    // com o sanitizador ligado, M[addr] precisa ter sido escrita antes de ser lida;
+    // `indirect` quando addr veio de um ponteiro
+    fn read(&self, addr: usize, indirect: bool) -> MepaResult<usize> {
+        match &self.initialized {
+            Some(initialized) if !initialized[addr] => Err(MepaError::UninitializedRead {
+                addr,
+                indirect,
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+                variable: None,
+            }),
+            _ => Ok(addr),
+        }
+    }
+
+    // com o sanitizador ligado, ARMI só escreve abaixo do valor que está guardando
+    fn write_below_top(&self, addr: usize) -> MepaResult<usize> {
+        if self.initialized.is_some() && addr as i32 >= self.s {
+            Err(MepaError::WriteAboveStack {
+                addr,
+                s: self.s - 1,
+                instruction_index: self.i,
+                instruction: self.current_instruction(),
+                variable: None,
+            })
+        } else {
+            Ok(addr)
+        }
+    }
+
+    // endereços e valores de D guardados em M precisam caber na célula
+    fn cell(&self, value: i32) -> MepaResult<C> {
+        C::from_i32(value).ok_or_else(|| self.overflow())
+    }
+
+    fn binary_op(&mut self, op: impl Fn(&C, &C) -> C) -> MepaResult<()> {
+        let (a, b) = (self.stack(1)?, self.stack(0)?);
+        self.set_m(a, op(&self.m[a], &self.m[b]));
+        self.s -= 1;
+        self.i += 1;
+        Ok(())
+    }
+
+    fn unary_op(&mut self, op: impl Fn(&C) -> C) -> MepaResult<()> {
+        let a = self.stack(0)?;
+        self.set_m(a, op(&self.m[a]));
+        self.i += 1;
+        Ok(())
+    }
+
+    // como binary_op e unary_op, mas `op` devolve `None` em overflow
+    fn checked_binary_op(&mut self, op: impl Fn(&C, &C) -> Option<C>) -> MepaResult<()> {
+        let (a, b) = (self.stack(1)?, self.stack(0)?);
+        let result = op(&self.m[a], &self.m[b]).ok_or_else(|| self.overflow())?;
+        self.binary_op(|_, _| result.clone())
+    }
+
+    fn checked_unary_op(&mut self, op: impl Fn(&C) -> Option<C>) -> MepaResult<()> {
+        let a = self.stack(0)?;
+        let result = op(&self.m[a]).ok_or_else(|| self.overflow())?;
+        self.unary_op(|_| result.clone())
+    }
+
+    fn overflow(&self) -> MepaError {
+        MepaError::ArithmeticOverflow {
+            instruction_index: self.i,
+            instruction: self.current_instruction(),
+        }
+    }
+
+    pub fn step_with_input(&mut self, input: Option<C>) -> MepaResult<Option<C>> {
+        if self.history.is_some() || self.keep_last_step {
+            self.pending = Some(StepRecord {
+                i: self.i,
+                s: self.s,
+                m_len: self.m.len(),
+                ..Default::default()
+            });
+        }
+        let result = self.execute_instruction(input.clone());
+        // um passo com erro não altera o estado, então não há o que desfazer
+        if let (Some(mut record), Ok(output)) = (self.pending.take(), &result) {
+            if let Some((_, Instruction::LEIT)) = self.code.0.get(record.i) {
+                record.input = input;
+            }
+            record.output = output.clone();
+            if self.keep_last_step {
+                self.last_step = Some(record.clone());
+            }
+            if let Some(history) = &mut self.history {
+                history.push(record, self.s);
+            }
+        }
+        result
+    }
+
+    fn execute_instruction(&mut self, input: Option<C>) -> MepaResult<Option<C>> {
         let mut output = None;
+        let arithmetic = self.arithmetic;
 
         if let Some(code) = self.code.0.get(self.i) {
             match code.1.clone() {
                 Instruction::CRCT(k) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] = k;
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, k);
                     self.i += 1;
                 }
                 Instruction::CRVL(m, n) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] = self.m[(self.d[m as usize] + n) as usize];
+                    let value = self.m[self.read(self.variable(m, n)?, false)?].clone();
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, value);
                     self.i += 1;
                 }
                 Instruction::CREN(m, n) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] = self.d[m as usize] + n;
+                    let value = self.cell(self.d[self.level(m)?] + n)?;
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, value);
                     self.i += 1;
                 }
                 Instruction::ARMZ(m, n) => {
-                    self.m[(self.d[m as usize] + n) as usize] = self.m[self.s as usize];
+                    let top = self.stack(0)?;
+                    let addr = self.variable(m, n)?;
+                    self.set_m(addr, self.m[top].clone());
                     self.s -= 1;
                     self.i += 1;
                 }
                 Instruction::CRVI(m, n) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] =
-                        self.m[self.m[(self.d[m as usize] + n) as usize] as usize];
+                    let pointer = self.read(self.variable(m, n)?, false)?;
+                    let value = self.m[self.read(self.indirect(pointer)?, true)?].clone();
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, value);
                     self.i += 1;
                 }
                 Instruction::ARMI(m, n) => {
-                    let temp = self.m[(self.d[m as usize] + n) as usize] as usize;
-                    self.m[temp] = self.m[self.s as usize];
-                    self.s -= 1;
-                    self.i += 1;
-                }
-                Instruction::SOMA => {
-                    self.m[self.s as usize - 1] =
-                        self.m[self.s as usize - 1].wrapping_add(self.m[self.s as usize]);
-                    self.s -= 1;
-                    self.i += 1;
-                }
-                Instruction::SUBT => {
-                    self.m[self.s as usize - 1] =
-                        self.m[self.s as usize - 1].wrapping_sub(self.m[self.s as usize]);
-                    self.s -= 1;
-                    self.i += 1;
-                }
-                Instruction::MULT => {
-                    self.m[self.s as usize - 1] =
-                        self.m[self.s as usize - 1].wrapping_mul(self.m[self.s as usize]);
+                    let top = self.stack(0)?;
+                    let pointer = self.read(self.variable(m, n)?, false)?;
+                    let addr = self.write_below_top(self.indirect(pointer)?)?;
+                    self.set_m(addr, self.m[top].clone());
                     self.s -= 1;
                     self.i += 1;
                 }
+                Instruction::SOMA => self.checked_binary_op(|a, b| a.add(b, arithmetic.mode))?,
+                Instruction::SUBT => self.checked_binary_op(|a, b| a.sub(b, arithmetic.mode))?,
+                Instruction::MULT => self.checked_binary_op(|a, b| a.mul(b, arithmetic.mode))?,
                 Instruction::DIVI => {
-                    self.m[self.s as usize - 1] =
-                        self.m[self.s as usize - 1].div_euclid(self.m[self.s as usize]);
-                    self.s -= 1;
-                    self.i += 1;
-                }
-                Instruction::INVR => {
-                    self.m[self.s as usize] = -self.m[self.s as usize];
-                    self.i += 1
+                    if self.m[self.stack(0)?].is_zero() {
+                        return Err(MepaError::DivisionByZero {
+                            instruction_index: self.i,
+                            instruction: self.current_instruction(),
+                        });
+                    }
+                    self.checked_binary_op(|a, b| a.div(b, arithmetic))?
                 }
+                Instruction::INVR => self.checked_unary_op(|a| a.neg(arithmetic.mode))?,
                 Instruction::CONJ => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] != 0 && self.m[self.s as usize] != 0 {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
+                    self.binary_op(|a, b| C::from_bool(!a.is_zero() && !b.is_zero()))?
                 }
                 Instruction::DISJ => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] != 0 || self.m[self.s as usize] != 0 {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::NEGA => {
-                    self.m[self.s as usize] = if self.m[self.s as usize] == 0 { 1 } else { 0 };
-                    self.i += 1
-                }
-                Instruction::CMME => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] < self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::CMMA => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] > self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::CMIG => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] == self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::CMDG => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] != self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
+                    self.binary_op(|a, b| C::from_bool(!a.is_zero() || !b.is_zero()))?
                 }
-                Instruction::CMEG => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] <= self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::CMAG => {
-                    self.m[self.s as usize - 1] =
-                        if self.m[self.s as usize - 1] >= self.m[self.s as usize] {
-                            1
-                        } else {
-                            0
-                        };
-                    self.s -= 1;
-                    self.i += 1
-                }
-                Instruction::DSVS(p) => self.i = p.locate(&self.code).unwrap(),
+                Instruction::NEGA => self.unary_op(|a| C::from_bool(a.is_zero()))?,
+                Instruction::CMME => self.binary_op(|a, b| C::from_bool(a < b))?,
+                Instruction::CMMA => self.binary_op(|a, b| C::from_bool(a > b))?,
+                Instruction::CMIG => self.binary_op(|a, b| C::from_bool(a == b))?,
+                Instruction::CMDG => self.binary_op(|a, b| C::from_bool(a != b))?,
+                Instruction::CMEG => self.binary_op(|a, b| C::from_bool(a <= b))?,
+                Instruction::CMAG => self.binary_op(|a, b| C::from_bool(a >= b))?,
+                Instruction::DSVS(p) => self.i = p.unwrap(),
                 Instruction::DSVF(p) => {
-                    if self.m[self.s as usize] == 0 {
-                        self.i = p.locate(&self.code).unwrap()
+                    if self.m[self.stack(0)?].is_zero() {
+                        self.i = p.unwrap()
                     } else {
                         self.i += 1
                     }
@@ -269,50 +501,491 @@ impl BasicMachine {
                 Instruction::PARA => (),
                 Instruction::LEIT => match input {
                     Some(n) => {
-                        self.aloc(1);
-                        self.m[self.s as usize] = n;
+                        self.aloc(1)?;
+                        self.set_m(self.s as usize, n);
                         self.i += 1;
                     }
                     None => return Err(MepaError::MissingInput(self.i)),
                 },
                 Instruction::IMPR => {
-                    output = Some(self.m[self.s as usize]);
+                    output = Some(self.m[self.stack(0)?].clone());
                     self.s -= 1;
                     self.i += 1;
                 }
                 Instruction::AMEM(n) => {
-                    self.aloc(n as usize);
+                    self.aloc(n as usize)?;
                     self.i += 1;
                 }
                 Instruction::DMEM(n) => {
+                    // s pode chegar a -1 (pilha vazia), mas não abaixo disso
+                    self.stack(n - 1)?;
                     self.s -= n;
+                    self.release(self.s + n);
                     self.i += 1;
                 }
                 Instruction::INPP => {
                     self.s = -1;
-                    self.d[0] = 0;
+                    self.set_d(0, 0);
                     self.i = 1;
                 }
                 Instruction::CHPR(p) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] = self.i as i32 + 1;
-                    self.i = p.locate(&self.code).unwrap();
+                    let ret = self.cell(self.i as i32 + 1)?;
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, ret);
+                    self.i = p.unwrap();
                 }
                 Instruction::ENPR(k) => {
-                    self.aloc(1);
-                    self.m[self.s as usize] = self.d[k as usize];
-                    self.d[k as usize] = self.s + 1;
+                    let k = self.level(k)?;
+                    let base = self.cell(self.d[k])?;
+                    self.aloc(1)?;
+                    self.set_m(self.s as usize, base);
+                    self.set_d(k, self.s + 1);
                     self.i += 1;
                 }
                 Instruction::RTPR(k, n) => {
-                    self.d[k as usize] = self.m[self.s as usize];
-                    self.i = self.m[self.s as usize - 1] as usize;
+                    let k = self.level(k)?;
+                    let (ret, top) = (self.stack(1)?, self.stack(0)?);
+                    self.stack(n + 1)?;
+                    let target = self.m[ret].to_i32_saturating();
+                    if target < 0 || target as usize >= self.code.0.len() {
+                        return Err(MepaError::InvalidJump {
+                            target,
+                            instruction_index: self.i,
+                            instruction: self.current_instruction(),
+                        });
+                    }
+                    self.set_d(k, self.m[top].to_i32_saturating());
+                    self.i = target as usize;
                     self.s -= n + 2;
+                    self.release(self.s + n + 2);
                 }
             }
+            self.steps += 1;
             Ok(output)
         } else {
             Err(MepaError::Runtime("Programa encerrado".to_owned()))
         }
     }
 }
+
+impl<C: Cell> FromStr for BasicMachine<C> {
+    type Err = MepaError;
+
+    fn from_str(code: &str) -> MepaResult<Self> {
+        Self::new(MepaCode::from_str(code)?)
+    }
+}
+
+// Snapshots guardam células i32
+impl BasicMachine {
+    /// Estado atual, sem entrada nem saída, que ficam a cargo de quem executa a máquina
+    pub fn snapshot(&self) -> MepaResult<Snapshot> {
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            code: self
+                .code
+                .to_string()?
+                .lines()
+                .map(|line| line.trim().to_owned())
+                .collect(),
+            m: self.m.clone(),
+            d: self.d.clone(),
+            i: self.i,
+            s: self.s,
+            steps: self.steps,
+            arithmetic: self.arithmetic,
+            input: None,
+            output: Vec::new(),
+        })
+    }
+
+    /// Recria a máquina no estado salvo
+    pub fn from_snapshot(snapshot: &Snapshot) -> MepaResult<BasicMachine> {
+        let mut machine = Self::new(snapshot.code()?)?;
+        snapshot.validate(machine.code_len(), machine.d.len())?;
+        machine.m = snapshot.m.clone();
+        machine.d = snapshot.d.clone();
+        machine.i = snapshot.i;
+        machine.s = snapshot.s;
+        machine.steps = snapshot.steps;
+        machine.arithmetic = snapshot.arithmetic;
+        Ok(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::arithmetic::{ArithmeticMode, DivisionMode};
+    use crate::mepa::cell::Big;
+
+    // executa até o primeiro erro (ou PARA), com as entradas dadas
+    fn run_with(source: &str, arithmetic: Arithmetic, input: &[i32]) -> MepaResult<Vec<i32>> {
+        let mut machine = BasicMachine::new(MepaCode::from_str(source)?)?;
+        machine.arithmetic = arithmetic;
+        run(machine, input)
+    }
+
+    fn run(mut machine: BasicMachine, input: &[i32]) -> MepaResult<Vec<i32>> {
+        let mut input = input.iter().copied();
+        let mut output = Vec::new();
+        while !machine.ended() {
+            let value = match machine.instruction(machine.i) {
+                Some(Instruction::LEIT) => input.next(),
+                _ => None,
+            };
+            output.extend(machine.step_with_input(value)?);
+        }
+        Ok(output)
+    }
+
+    fn error(source: &str) -> MepaError {
+        run_with(source, Arithmetic::default(), &[]).unwrap_err()
+    }
+
+    // executa com o sanitizador ligado
+    fn run_sanitized(source: &str) -> MepaResult<Vec<i32>> {
+        let mut machine = BasicMachine::new(MepaCode::from_str(source)?)?;
+        machine.sanitize();
+        run(machine, &[])
+    }
+
+    #[test]
+    fn runs_to_the_end() {
+        let output = run_with(
+            "    INPP\n    LEIT\n    CRCT 2\n    MULT\n    IMPR\n    PARA\n",
+            Arithmetic::default(),
+            &[21],
+        );
+        assert_eq!(output.unwrap(), vec![42]);
+    }
+
+    #[test]
+    fn stack_underflow() {
+        let e = error("    INPP\n    CRCT 1\n    SOMA\n    PARA\n");
+        assert!(matches!(
+            e,
+            MepaError::StackUnderflow {
+                instruction_index: 2,
+                instruction: Instruction::SOMA
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_address() {
+        let e = error("    INPP\n    CRVL 0 -5\n    PARA\n");
+        assert!(matches!(
+            e,
+            MepaError::InvalidAddress {
+                addr: -5,
+                instruction_index: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let e = error("    INPP\n    CRCT 1\n    CRCT 0\n    DIVI\n    PARA\n");
+        assert!(matches!(
+            e,
+            MepaError::DivisionByZero {
+                instruction_index: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn arithmetic_overflow_when_checked() {
+        let source = "    INPP\n    CRCT 2147483647\n    CRCT 1\n    SOMA\n    IMPR\n    PARA\n";
+        let checked = Arithmetic::new(ArithmeticMode::Checked, Default::default());
+        assert!(matches!(
+            run_with(source, checked, &[]),
+            Err(MepaError::ArithmeticOverflow {
+                instruction_index: 3,
+                ..
+            })
+        ));
+        assert_eq!(
+            run_with(source, Arithmetic::default(), &[]).unwrap(),
+            vec![i32::MIN]
+        );
+    }
+
+    // resultado de uma operação sobre as constantes dadas
+    fn operate(operands: &[i32], op: &str, arithmetic: Arithmetic) -> MepaResult<i32> {
+        let constants: String = operands
+            .iter()
+            .map(|n| format!("    CRCT {}\n", n))
+            .collect();
+        let source = format!("    INPP\n{}    {}\n    IMPR\n    PARA\n", constants, op);
+        Ok(run_with(&source, arithmetic, &[])?[0])
+    }
+
+    #[test]
+    fn division_modes_with_negative_operands() {
+        let cases = [
+            (DivisionMode::Euclidean, [-4, -3, 4]),
+            (DivisionMode::Truncating, [-3, -3, 3]),
+            (DivisionMode::Floored, [-4, -4, 3]),
+        ];
+        for (division, expected) in cases {
+            let arithmetic = Arithmetic::new(ArithmeticMode::Wrapping, division);
+            let quotients: Vec<i32> = [[-7, 2], [7, -2], [-7, -2]]
+                .iter()
+                .map(|operands| operate(operands, "DIVI", arithmetic).unwrap())
+                .collect();
+            assert_eq!(quotients, expected, "{:?}", division);
+        }
+    }
+
+    #[test]
+    fn invr_and_divi_at_the_minimum() {
+        let mode = |mode| Arithmetic::new(mode, Default::default());
+        for (operands, op) in [(&[i32::MIN][..], "INVR"), (&[i32::MIN, -1][..], "DIVI")] {
+            assert_eq!(
+                operate(operands, op, mode(ArithmeticMode::Wrapping)).unwrap(),
+                i32::MIN
+            );
+            assert_eq!(
+                operate(operands, op, mode(ArithmeticMode::Saturating)).unwrap(),
+                i32::MAX
+            );
+            assert!(matches!(
+                operate(operands, op, mode(ArithmeticMode::Checked)),
+                Err(MepaError::ArithmeticOverflow { .. })
+            ));
+        }
+    }
+
+    fn run_cells<C: Cell>(source: &str, mode: ArithmeticMode) -> MepaResult<Vec<C>> {
+        let mut machine = BasicMachine::<C>::new(source.parse()?)?;
+        machine.arithmetic = Arithmetic::new(mode, Default::default());
+        let mut output = Vec::new();
+        while !machine.ended() {
+            output.extend(machine.step_with_input(None)?);
+        }
+        Ok(output)
+    }
+
+    #[test]
+    fn i16_cells_overflow_at_their_bounds() {
+        let sum = "    INPP\n    CRCT 32767\n    CRCT 1\n    SOMA\n    IMPR\n    PARA\n";
+        let difference = "    INPP\n    CRCT -32768\n    CRCT 1\n    SUBT\n    IMPR\n    PARA\n";
+        assert_eq!(
+            run_cells::<i16>(sum, ArithmeticMode::Wrapping).unwrap(),
+            [i16::MIN]
+        );
+        assert_eq!(
+            run_cells::<i16>(difference, ArithmeticMode::Wrapping).unwrap(),
+            [i16::MAX]
+        );
+        for source in [sum, difference] {
+            assert!(matches!(
+                run_cells::<i16>(source, ArithmeticMode::Checked),
+                Err(MepaError::ArithmeticOverflow { .. })
+            ));
+        }
+        // o mesmo programa cabe numa célula de 32 bits
+        assert_eq!(
+            run_cells::<i32>(sum, ArithmeticMode::Checked).unwrap(),
+            [32768]
+        );
+    }
+
+    #[test]
+    fn big_cells_hold_values_past_64_bits() {
+        let source = "    INPP\n    CRCT 18446744073709551616\n    CRCT 18446744073709551616\n    MULT\n    IMPR\n    PARA\n";
+        let output = run_cells::<Big>(source, ArithmeticMode::Checked).unwrap();
+        assert_eq!(
+            output[0].to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert!(matches!(
+            run_cells::<i64>(source, ArithmeticMode::Checked),
+            Err(MepaError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_level() {
+        let e = error("    INPP\n    CRVL -1 0\n    PARA\n");
+        assert!(matches!(
+            e,
+            MepaError::InvalidLevel {
+                level: -1,
+                instruction_index: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_jump() {
+        // o endereço de retorno empilhado à mão está fora do programa
+        let e = error("    INPP\n    CRCT 99\n    CRCT 0\n    RTPR 1 0\n    PARA\n");
+        assert!(matches!(
+            e,
+            MepaError::InvalidJump {
+                target: 99,
+                instruction_index: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn missing_input() {
+        let e = error("    INPP\n    LEIT\n    PARA\n");
+        assert!(matches!(e, MepaError::MissingInput(1)));
+    }
+
+    #[test]
+    fn negative_amem_and_dmem_are_rejected() {
+        for source in [
+            "    INPP\n    AMEM -1\n    PARA\n",
+            "    INPP\n    DMEM -5\n    PARA\n",
+        ] {
+            match BasicMachine::<i32>::new(MepaCode::from_str(source).unwrap()) {
+                Err(MepaError::Assembly(errors)) => assert!(matches!(
+                    errors[0].kind,
+                    crate::mepa::error::AssemblyErrorKind::NegativeAmount(_, n) if n < 0
+                )),
+                other => panic!("esperava erro de montagem, veio {:?}", other.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn negative_rtpr_is_rejected() {
+        // desempilharia -3 posições, deixando s acima do topo de M
+        let source = "    INPP\n    CRCT 4\n    CRCT 0\n    RTPR 0 -5\n    IMPR\n    PARA\n";
+        match BasicMachine::<i32>::new(MepaCode::from_str(source).unwrap()) {
+            Err(MepaError::Assembly(errors)) => {
+                assert_eq!(errors[0].address, 3);
+                assert!(matches!(
+                    &errors[0].kind,
+                    crate::mepa::error::AssemblyErrorKind::NegativeAmount(m, -5) if m == "RTPR"
+                ));
+            }
+            other => panic!("esperava erro de montagem, veio {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn sanitizer_reports_uninitialized_reads() {
+        let e = run_sanitized(
+            "    INPP\n    AMEM 2\n    CRCT 5\n    ARMZ 0 0\n    CRVL 0 1\n    PARA\n",
+        );
+        assert!(matches!(
+            e,
+            Err(MepaError::UninitializedRead {
+                addr: 1,
+                indirect: false,
+                instruction_index: 4,
+                ..
+            })
+        ));
+
+        // M[0] aponta para M[1], que nunca foi escrita
+        let e = run_sanitized(
+            "    INPP\n    AMEM 2\n    CREN 0 1\n    ARMZ 0 0\n    CRVI 0 0\n    PARA\n",
+        );
+        assert!(matches!(
+            e,
+            Err(MepaError::UninitializedRead {
+                addr: 1,
+                indirect: true,
+                instruction_index: 4,
+                ..
+            })
+        ));
+
+        // depois de escrita, a posição pode ser lida
+        let output = run_sanitized(
+            "    INPP\n    AMEM 1\n    CRCT 5\n    ARMZ 0 0\n    CRVL 0 0\n    IMPR\n    PARA\n",
+        );
+        assert_eq!(output.unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn sanitizer_clears_marks_on_dmem() {
+        let e = run_sanitized(
+            "    INPP\n    AMEM 1\n    CRCT 5\n    ARMZ 0 0\n    DMEM 1\n    AMEM 1\n    CRVL 0 0\n    PARA\n",
+        );
+        assert!(matches!(
+            e,
+            Err(MepaError::UninitializedRead {
+                addr: 0,
+                instruction_index: 6,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sanitizer_clears_marks_on_rtpr() {
+        // M[1] e M[2] guardavam o endereço de retorno e o D salvo pelo ENPR
+        let e = run_sanitized(
+            "    INPP\n    AMEM 1\n    CHPR L1\n    AMEM 2\n    CRVL 0 1\n    PARA\nL1: ENPR 1\n    RTPR 1 0\n",
+        );
+        assert!(matches!(
+            e,
+            Err(MepaError::UninitializedRead {
+                addr: 1,
+                instruction_index: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sanitizer_reports_armi_above_the_stack() {
+        // M já teve 4 posições; M[0] = 2 aponta para uma delas, acima do topo
+        let e = run_sanitized(
+            "    INPP\n    AMEM 1\n    AMEM 3\n    DMEM 3\n    CRCT 2\n    ARMZ 0 0\n    CRCT 7\n    ARMI 0 0\n    PARA\n",
+        );
+        assert!(matches!(
+            e,
+            Err(MepaError::WriteAboveStack {
+                addr: 2,
+                s: 0,
+                instruction_index: 7,
+                ..
+            })
+        ));
+
+        // abaixo do topo a escrita é permitida
+        let output = run_sanitized(
+            "    INPP\n    AMEM 1\n    CRCT 0\n    ARMZ 0 0\n    CRCT 7\n    ARMI 0 0\n    CRVL 0 0\n    IMPR\n    PARA\n",
+        );
+        assert_eq!(output.unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn step_back_restores_sanitizer_marks() {
+        let source = "    INPP\n    AMEM 1\n    CRCT 5\n    ARMZ 0 0\n    CRVL 0 0\n    PARA\n";
+        let mut machine: BasicMachine =
+            BasicMachine::new(MepaCode::from_str(source).unwrap()).unwrap();
+        machine.sanitize();
+        machine.record_history(100);
+        for _ in 0..4 {
+            machine.step_with_input(None).unwrap();
+        }
+        assert_eq!(machine.i, 4);
+        assert!(machine.read(0, false).is_ok());
+
+        // desfaz ARMZ e CRCT: M[0] volta a não estar inicializada
+        machine.step_back();
+        machine.step_back();
+        assert!(machine.read(0, false).is_err());
+        assert_eq!(machine.initialized.as_ref().unwrap().len(), machine.m.len());
+
+        // e volta a ser marcada quando o passo é refeito
+        machine.step_with_input(None).unwrap();
+        machine.step_with_input(None).unwrap();
+        assert!(machine.step_with_input(None).is_ok());
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use crate::compiler::SourceMap;
+use crate::machine::arithmetic::Arithmetic;
+use crate::machine::full_machine::FullMachine;
+use crate::mepa::{
+    code::MepaCode,
+    error::{MepaError, MepaResult},
+    instruction::Instruction,
+    label::Label,
+};
+
+const HELP: &str = "\
+Comandos:
+  break <endereço|rótulo>   (b)   adiciona um ponto de parada
+  delete [endereço|rótulo]  (d)   remove um ponto de parada (todos, sem argumento)
+  continue                  (c)   executa até um ponto de parada ou o fim
+  step [n]                  (s)   executa n instruções (padrão 1)
+  next                      (n)   executa uma instrução, sem entrar em CHPR
+  finish                          executa até o RTPR do procedimento atual
+  step-back [n]             (sb)  desfaz n instruções (padrão 1)
+  reverse-continue          (rc)  volta até um ponto de parada ou o início do histórico
+  goto <passo>                    avança ou volta até o passo indicado
+  print M[i] | D[k] | i | s (p)   mostra um valor
+  watch M[i]                (w)   para quando M[i] mudar
+  stack                           mostra a pilha M até s
+  backtrace                 (bt)  mostra os quadros ativos, com argumentos, locais e temporários
+  state                           mostra o estado completo da máquina
+  restart                         reinicia o programa
+  help                      (h)   mostra esta ajuda
+  quit                      (q)   encerra o depurador
+Uma linha vazia repete o último comando.";
+
+#[derive(Clone, Debug)]
+enum Location {
+    Address(usize),
+    Label(String),
+}
+
+#[derive(Clone, Debug)]
+enum Cell {
+    M(usize),
+    D(usize),
+    I,
+    S,
+}
+
+#[derive(Clone, Debug)]
+enum Command {
+    Break(Location),
+    Delete(Option<Location>),
+    Continue,
+    Step(usize),
+    Next,
+    Finish,
+    StepBack(usize),
+    ReverseContinue,
+    Goto(usize),
+    Print(Cell),
+    Watch(usize),
+    Stack,
+    Backtrace,
+    State,
+    Restart,
+    Help,
+    Quit,
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err("argumentos demais".to_owned());
+        }
+        let required = || arg.ok_or(format!("'{}' precisa de um argumento", name));
+
+        Ok(match name {
+            "break" | "b" => Command::Break(parse_location(required()?)),
+            "delete" | "d" => Command::Delete(arg.map(parse_location)),
+            "continue" | "c" => Command::Continue,
+            "step" | "s" => Command::Step(parse_count(arg)?),
+            "step-back" | "sb" => Command::StepBack(parse_count(arg)?),
+            "reverse-continue" | "rc" => Command::ReverseContinue,
+            "goto" => Command::Goto(parse_count(Some(required()?))?),
+            "next" | "n" => Command::Next,
+            "finish" => Command::Finish,
+            "print" | "p" => Command::Print(parse_cell(required()?)?),
+            "watch" | "w" => match parse_cell(required()?)? {
+                Cell::M(addr) => Command::Watch(addr),
+                _ => return Err("só é possível observar posições de M".to_owned()),
+            },
+            "stack" => Command::Stack,
+            "backtrace" | "bt" => Command::Backtrace,
+            "state" => Command::State,
+            "restart" => Command::Restart,
+            "help" | "h" => Command::Help,
+            "quit" | "q" => Command::Quit,
+            _ => return Err(format!("comando desconhecido '{}'; digite 'help'", name)),
+        })
+    }
+}
+
+fn parse_count(arg: Option<&str>) -> Result<usize, String> {
+    match arg {
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("quantidade de passos inválida '{}'", n)),
+        None => Ok(1),
+    }
+}
+
+fn parse_location(arg: &str) -> Location {
+    match arg.parse() {
+        Ok(addr) => Location::Address(addr),
+        Err(_) => Location::Label(arg.to_owned()),
+    }
+}
+
+// aceita M[i], D[k], i e s
+fn parse_cell(arg: &str) -> Result<Cell, String> {
+    let invalid = || format!("posição inválida '{}', use M[i], D[k], i ou s", arg);
+    match arg {
+        "i" => return Ok(Cell::I),
+        "s" => return Ok(Cell::S),
+        _ => (),
+    }
+    let index = |prefix: &str| {
+        arg.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|n| n.trim().parse().ok())
+    };
+    if let Some(i) = index("M[") {
+        Ok(Cell::M(i))
+    } else if let Some(k) = index("D[") {
+        Ok(Cell::D(k))
+    } else {
+        Err(invalid())
+    }
+}
+
+// Motivo pelo qual a execução parou
+enum Stop {
+    Done,
+    Breakpoint(usize),
+    Watch {
+        addr: usize,
+        old: Option<i32>,
+        new: Option<i32>,
+    },
+    Ended,
+    HistoryStart,
+    Error(MepaError),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+/// Depurador interativo de MEPA, que controla uma `FullMachine`
+pub struct Debugger {
+    code: MepaCode,
+    input: Vec<i32>,
+    arithmetic: Arithmetic,
+    machine: FullMachine<'static>,
+    breakpoints: BTreeSet<usize>,
+    // endereço observado e último valor visto
+    watches: Vec<(usize, Option<i32>)>,
+    // procedimentos ativos, contados pelos CHPR e RTPR executados (para next e finish);
+    // os quadros em si são reconstruídos pela máquina
+    depth: usize,
+    // nomes das funções e variáveis, quando o código veio de um programa ipt
+    source_map: Option<SourceMap>,
+}
+
+// Quantos passos podem ser desfeitos
+const HISTORY_CAPACITY: usize = 100_000;
+
+impl Debugger {
+    pub fn new(code: MepaCode, input: Vec<i32>) -> MepaResult<Debugger> {
+        let code = code.assemble()?;
+        let arithmetic = Arithmetic::default();
+        let machine = Self::new_machine(&code, &input, arithmetic)?;
+        Ok(Debugger {
+            code,
+            input,
+            arithmetic,
+            machine,
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            depth: 0,
+            source_map: None,
+        })
+    }
+
+    /// Mostra as posições dos quadros pelo nome das variáveis do programa ipt.
+    /// O mapa precisa ser do código sem otimização.
+    pub fn with_source_map(mut self, source_map: SourceMap) -> Debugger {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Usa outra semântica aritmética; deve ser chamado antes de executar o programa
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> MepaResult<Debugger> {
+        self.arithmetic = arithmetic;
+        self.machine = Self::new_machine(&self.code, &self.input, arithmetic)?;
+        Ok(self)
+    }
+
+    fn new_machine(
+        code: &MepaCode,
+        input: &[i32],
+        arithmetic: Arithmetic,
+    ) -> MepaResult<FullMachine<'static>> {
+        let machine = FullMachine::new(code.clone())?
+            .with_history(HISTORY_CAPACITY)
+            .with_arithmetic(arithmetic);
+        Ok(if input.is_empty() {
+            machine
+        } else {
+            machine.add_input_vec(input.to_vec())
+        })
+    }
+
+    /// Lê comandos do stdin até `quit` ou o fim da entrada
+    pub fn repl(&mut self) -> MepaResult<()> {
+        println!("Depurador MEPA; digite 'help' para ver os comandos");
+        self.show_state();
+        self.show_location();
+
+        let mut last = Command::Step(1);
+        loop {
+            print!("(mepa) ");
+            io::stdout().flush()?;
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let command = if line.trim().is_empty() {
+                last.clone()
+            } else {
+                match Command::parse(&line) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                }
+            };
+            if let Command::Quit = command {
+                return Ok(());
+            }
+            self.execute(&command)?;
+            last = command;
+        }
+    }
+
+    fn execute(&mut self, command: &Command) -> MepaResult<()> {
+        match command {
+            Command::Break(location) => match self.resolve(location) {
+                Ok(addr) => {
+                    self.breakpoints.insert(addr);
+                    println!("Ponto de parada em {}", self.describe(addr));
+                }
+                Err(e) => println!("{}", e),
+            },
+            Command::Delete(None) => {
+                self.breakpoints.clear();
+                println!("Todos os pontos de parada removidos");
+            }
+            Command::Delete(Some(location)) => match self.resolve(location) {
+                Ok(addr) if self.breakpoints.remove(&addr) => {
+                    println!("Ponto de parada em {} removido", addr)
+                }
+                Ok(addr) => println!("Não há ponto de parada em {}", addr),
+                Err(e) => println!("{}", e),
+            },
+            Command::Continue => {
+                let stop = self.run(Direction::Forward, |_| false);
+                self.report(stop);
+            }
+            Command::Step(n) => {
+                let mut remaining = *n;
+                let stop = self.run(Direction::Forward, |_| {
+                    remaining = remaining.saturating_sub(1);
+                    remaining == 0
+                });
+                self.report(stop);
+            }
+            Command::Next => {
+                let depth = self.depth;
+                let stop = self.run(Direction::Forward, |d| d.depth <= depth);
+                self.report(stop);
+            }
+            Command::Finish => {
+                if self.depth == 0 {
+                    println!("'finish' só faz sentido dentro de um procedimento");
+                } else {
+                    let depth = self.depth;
+                    let stop = self.run(Direction::Forward, |d| d.depth < depth);
+                    self.report(stop);
+                }
+            }
+            Command::StepBack(n) => {
+                let mut remaining = *n;
+                let stop = self.run(Direction::Backward, |_| {
+                    remaining = remaining.saturating_sub(1);
+                    remaining == 0
+                });
+                self.report(stop);
+            }
+            Command::ReverseContinue => {
+                let stop = self.run(Direction::Backward, |_| false);
+                self.report(stop);
+            }
+            Command::Goto(target) => self.goto(*target),
+            Command::Print(cell) => self.print(cell),
+            Command::Watch(addr) => {
+                let value = self.value_at(*addr);
+                self.watches.push((*addr, value));
+                println!("Observando M[{}] = {}", addr, show_value(value));
+            }
+            Command::Stack => self.print_stack(),
+            Command::Backtrace => self.print_backtrace(),
+            Command::State => self.show_state(),
+            Command::Restart => {
+                self.machine = Self::new_machine(&self.code, &self.input, self.arithmetic)?;
+                self.depth = 0;
+                for watch in &mut self.watches {
+                    watch.1 = None;
+                }
+                println!("Programa reiniciado");
+                self.show_location();
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => (),
+        }
+        Ok(())
+    }
+
+    // Executa uma instrução, mantendo a profundidade de chamadas atualizada
+    fn step(&mut self) -> MepaResult<()> {
+        let i = self.machine.basic_machine().i;
+        let instruction = self.code.0[i].1.clone();
+        self.machine.execute_step()?;
+        match instruction {
+            Instruction::CHPR(_) => self.depth += 1,
+            Instruction::RTPR(_, _) => self.depth = self.depth.saturating_sub(1),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Desfaz uma instrução, retornando false no início do histórico
+    fn step_back(&mut self) -> bool {
+        if !self.machine.step_back() {
+            return false;
+        }
+        match self.code.0[self.machine.basic_machine().i].1 {
+            Instruction::CHPR(_) => self.depth = self.depth.saturating_sub(1),
+            Instruction::RTPR(_, _) => self.depth += 1,
+            _ => (),
+        }
+        true
+    }
+
+    // Executa (ou desfaz) ao menos uma instrução, parando quando `done` for verdadeiro,
+    // num ponto de parada, numa posição observada que mudou, no fim ou num erro
+    fn run(&mut self, direction: Direction, mut done: impl FnMut(&Self) -> bool) -> Stop {
+        loop {
+            match direction {
+                Direction::Forward => {
+                    if self.machine.ended() {
+                        return Stop::Ended;
+                    }
+                    if let Err(e) = self.step() {
+                        return Stop::Error(e);
+                    }
+                }
+                Direction::Backward => {
+                    if !self.step_back() {
+                        return Stop::HistoryStart;
+                    }
+                }
+            }
+            for j in 0..self.watches.len() {
+                let (addr, old) = self.watches[j];
+                let new = self.value_at(addr);
+                if new != old {
+                    self.watches[j].1 = new;
+                    return Stop::Watch { addr, old, new };
+                }
+            }
+            if done(self) {
+                return Stop::Done;
+            }
+            let i = self.machine.basic_machine().i;
+            if self.breakpoints.contains(&i) {
+                return Stop::Breakpoint(i);
+            }
+        }
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Done => (),
+            Stop::Breakpoint(addr) => println!("Ponto de parada em {}", addr),
+            Stop::Watch { addr, old, new } => println!(
+                "M[{}] mudou: {} -> {}",
+                addr,
+                show_value(old),
+                show_value(new)
+            ),
+            Stop::Ended => {
+                println!("Programa encerrado");
+                return;
+            }
+            Stop::HistoryStart => println!("Início do histórico"),
+            Stop::Error(e) => println!("{}", e),
+        }
+        self.show_location();
+    }
+
+    // Vai direto ao passo indicado, ignorando pontos de parada
+    fn goto(&mut self, target: usize) {
+        while self.machine.basic_machine().steps < target {
+            if self.machine.ended() {
+                println!(
+                    "O programa termina no passo {}",
+                    self.machine.basic_machine().steps
+                );
+                break;
+            }
+            if let Err(e) = self.step() {
+                println!("{}", e);
+                break;
+            }
+        }
+        while self.machine.basic_machine().steps > target {
+            if !self.step_back() {
+                println!(
+                    "O histórico só alcança o passo {}",
+                    self.machine.basic_machine().steps
+                );
+                break;
+            }
+        }
+        for j in 0..self.watches.len() {
+            self.watches[j].1 = self.value_at(self.watches[j].0);
+        }
+        self.show_location();
+    }
+
+    fn resolve(&self, location: &Location) -> Result<usize, String> {
+        match location {
+            Location::Address(addr) if *addr < self.code.len() => Ok(*addr),
+            Location::Address(addr) => Err(format!("Endereço {} fora do programa", addr)),
+            Location::Label(name) => self
+                .code
+                .0
+                .iter()
+                .position(|(label, _)| matches!(label, Some(Label::Simbolic(s)) if s == name))
+                .ok_or(format!("Rótulo '{}' não encontrado", name)),
+        }
+    }
+
+    // "12 (L3: CRVL 0 1)"
+    fn describe(&self, addr: usize) -> String {
+        match self.code.0.get(addr) {
+            Some((Some(label), instruction)) => format!("{} ({}: {})", addr, label, instruction),
+            Some((None, instruction)) => format!("{} ({})", addr, instruction),
+            None => addr.to_string(),
+        }
+    }
+
+    fn show_location(&self) {
+        let machine = self.machine.basic_machine();
+        println!(
+            "=> {}   s = {}   passo {}",
+            self.describe(machine.i),
+            machine.s,
+            machine.steps
+        );
+    }
+
+    // valor de M[addr], se estiver dentro da pilha
+    fn value_at(&self, addr: usize) -> Option<i32> {
+        let machine = self.machine.basic_machine();
+        if (addr as i32) <= machine.s {
+            machine.m.get(addr).copied()
+        } else {
+            None
+        }
+    }
+
+    fn print(&self, cell: &Cell) {
+        let machine = self.machine.basic_machine();
+        match cell {
+            Cell::M(addr) => println!("M[{}] = {}", addr, show_value(self.value_at(*addr))),
+            Cell::D(k) => match machine.d.get(*k) {
+                Some(value) => println!("D[{}] = {}", k, value),
+                None => println!("D[{}] não existe", k),
+            },
+            Cell::I => println!("i = {}", machine.i),
+            Cell::S => println!("s = {}", machine.s),
+        }
+    }
+
+    fn print_stack(&self) {
+        let machine = self.machine.basic_machine();
+        if machine.s < 0 {
+            println!("Pilha vazia");
+        }
+        for addr in (0..=machine.s).rev() {
+            println!("M[{}] = {}", addr, machine.m[addr as usize]);
+        }
+    }
+
+    fn show_state(&self) {
+        self.machine
+            .basic_machine()
+            .show_state_with(self.source_map.as_ref());
+    }
+
+    fn print_backtrace(&self) {
+        print!(
+            "{}",
+            self.machine
+                .basic_machine()
+                .backtrace(self.source_map.as_ref())
+        );
+    }
+}
+
+fn show_value(value: Option<i32>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "(fora da pilha)".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn debugger() -> Debugger {
+        let source = "    INPP\n    LEIT\n    IMPR\n    LEIT\n    IMPR\n    PARA\n";
+        Debugger::new(MepaCode::from_str(source).unwrap(), vec![3, 4]).unwrap()
+    }
+
+    fn steps(debugger: &Debugger) -> usize {
+        debugger.machine.basic_machine().steps
+    }
+
+    fn output(debugger: &Debugger) -> Vec<i32> {
+        debugger.machine.snapshot().unwrap().output
+    }
+
+    #[test]
+    fn step_back_and_goto_over_input_and_output() {
+        let mut debugger = debugger();
+        debugger.execute(&Command::Continue).unwrap();
+        assert!(debugger.machine.ended());
+        assert_eq!(output(&debugger), vec![3, 4]);
+
+        debugger.execute(&Command::StepBack(2)).unwrap();
+        assert_eq!(
+            (steps(&debugger), debugger.machine.basic_machine().i),
+            (3, 3)
+        );
+        assert_eq!(output(&debugger), vec![3]);
+
+        debugger.execute(&Command::Goto(1)).unwrap();
+        assert_eq!(steps(&debugger), 1);
+        assert!(output(&debugger).is_empty());
+        debugger.execute(&Command::Goto(5)).unwrap();
+        assert_eq!(output(&debugger), vec![3, 4]);
+
+        // a execução refeita lê as mesmas entradas e produz a mesma saída
+        debugger.execute(&Command::ReverseContinue).unwrap();
+        assert_eq!(steps(&debugger), 0);
+        debugger.execute(&Command::Continue).unwrap();
+        assert!(debugger.machine.ended());
+        assert_eq!(output(&debugger), vec![3, 4]);
+    }
+
+    #[test]
+    fn reverse_continue_stops_at_breakpoints() {
+        let mut debugger = debugger();
+        debugger.execute(&Command::Continue).unwrap();
+        debugger
+            .execute(&Command::Break(Location::Address(2)))
+            .unwrap();
+        debugger.execute(&Command::ReverseContinue).unwrap();
+        assert_eq!(
+            (steps(&debugger), debugger.machine.basic_machine().i),
+            (2, 2)
+        );
+        assert_eq!(output(&debugger), Vec::<i32>::new());
+    }
+}
@@ -0,0 +1,626 @@
+use std::io::{BufReader, Read};
+use std::time::Instant;
+
+use super::arithmetic::Arithmetic;
+use super::full_machine::ExecutionLimits;
+use super::io::{MepaIo, ReaderIo, StdinIo, VecIo};
+use crate::mepa::{
+    cell::Cell,
+    code::MepaCode,
+    error::{Limit, MepaError, MepaResult},
+    instruction::Instruction,
+};
+
+// Operações binárias que não dependem do divisor; só SOMA, SUBT e MULT podem falhar,
+// por overflow na aritmética `Checked`
+#[derive(Clone, Copy, Debug)]
+enum BinOp {
+    Soma,
+    Subt,
+    Mult,
+    Conj,
+    Disj,
+    Cmme,
+    Cmma,
+    Cmig,
+    Cmdg,
+    Cmeg,
+    Cmag,
+}
+
+impl BinOp {
+    fn from_instruction(instruction: &Instruction) -> Option<BinOp> {
+        Some(match instruction {
+            Instruction::SOMA => BinOp::Soma,
+            Instruction::SUBT => BinOp::Subt,
+            Instruction::MULT => BinOp::Mult,
+            Instruction::CONJ => BinOp::Conj,
+            Instruction::DISJ => BinOp::Disj,
+            Instruction::CMME => BinOp::Cmme,
+            Instruction::CMMA => BinOp::Cmma,
+            Instruction::CMIG => BinOp::Cmig,
+            Instruction::CMDG => BinOp::Cmdg,
+            Instruction::CMEG => BinOp::Cmeg,
+            Instruction::CMAG => BinOp::Cmag,
+            _ => return None,
+        })
+    }
+
+    #[inline(always)]
+    fn apply(self, a: i32, b: i32, arithmetic: Arithmetic) -> Option<i32> {
+        Some(match self {
+            BinOp::Soma => return a.add(&b, arithmetic.mode),
+            BinOp::Subt => return a.sub(&b, arithmetic.mode),
+            BinOp::Mult => return a.mul(&b, arithmetic.mode),
+            BinOp::Conj => (a != 0 && b != 0) as i32,
+            BinOp::Disj => (a != 0 || b != 0) as i32,
+            BinOp::Cmme => (a < b) as i32,
+            BinOp::Cmma => (a > b) as i32,
+            BinOp::Cmig => (a == b) as i32,
+            BinOp::Cmdg => (a != b) as i32,
+            BinOp::Cmeg => (a <= b) as i32,
+            BinOp::Cmag => (a >= b) as i32,
+        })
+    }
+}
+
+// Instrução pré-decodificada: sem rótulos, desvios já resolvidos e tudo `Copy`
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Crct(i32),
+    Crvl(i32, i32),
+    Cren(i32, i32),
+    Armz(i32, i32),
+    Crvi(i32, i32),
+    Armi(i32, i32),
+    Bin(BinOp),
+    Divi,
+    Invr,
+    Nega,
+    Dsvs(usize),
+    Dsvf(usize),
+    Nada,
+    Para,
+    Leit,
+    Impr,
+    Amem(i32),
+    Dmem(i32),
+    Inpp,
+    Chpr(usize),
+    Enpr(i32),
+    Rtpr(i32, i32),
+    // Superinstruções: executam as instruções seguintes em sequência, sem voltar ao laço
+    // principal. As instruções originais continuam nas posições seguintes, então desvios
+    // para o meio de uma sequência funcionam normalmente.
+    /// CRVL m n; CRCT k; op
+    CrvlCrctBin(i32, i32, i32, BinOp),
+    /// CRVL m n; CRVL m2 n2; op
+    CrvlCrvlBin(i32, i32, i32, i32, BinOp),
+    /// op; DSVF p
+    BinDsvf(BinOp, usize),
+    /// CRCT k; ARMZ m n
+    CrctArmz(i32, i32, i32),
+}
+
+impl Op {
+    fn lower(instruction: &Instruction) -> Op {
+        match instruction {
+            Instruction::CRCT(k) => Op::Crct(*k),
+            Instruction::CRVL(m, n) => Op::Crvl(*m, *n),
+            Instruction::CREN(m, n) => Op::Cren(*m, *n),
+            Instruction::ARMZ(m, n) => Op::Armz(*m, *n),
+            Instruction::CRVI(m, n) => Op::Crvi(*m, *n),
+            Instruction::ARMI(m, n) => Op::Armi(*m, *n),
+            Instruction::DIVI => Op::Divi,
+            Instruction::INVR => Op::Invr,
+            Instruction::NEGA => Op::Nega,
+            Instruction::DSVS(p) => Op::Dsvs(p.unwrap()),
+            Instruction::DSVF(p) => Op::Dsvf(p.unwrap()),
+            Instruction::NADA => Op::Nada,
+            Instruction::PARA => Op::Para,
+            Instruction::LEIT => Op::Leit,
+            Instruction::IMPR => Op::Impr,
+            Instruction::AMEM(n) => Op::Amem(*n),
+            Instruction::DMEM(n) => Op::Dmem(*n),
+            Instruction::INPP => Op::Inpp,
+            Instruction::CHPR(p) => Op::Chpr(p.unwrap()),
+            Instruction::ENPR(k) => Op::Enpr(*k),
+            Instruction::RTPR(k, n) => Op::Rtpr(*k, *n),
+            other => Op::Bin(BinOp::from_instruction(other).expect("operação binária")),
+        }
+    }
+
+    // Tenta formar uma superinstrução a partir do início de `ops`
+    fn fuse(ops: &[Op]) -> Option<Op> {
+        match ops {
+            [Op::Crvl(m, n), Op::Crct(k), Op::Bin(op), ..] => {
+                Some(Op::CrvlCrctBin(*m, *n, *k, *op))
+            }
+            [Op::Crvl(m, n), Op::Crvl(m2, n2), Op::Bin(op), ..] => {
+                Some(Op::CrvlCrvlBin(*m, *n, *m2, *n2, *op))
+            }
+            [Op::Bin(op), Op::Dsvf(p), ..] => Some(Op::BinDsvf(*op, *p)),
+            [Op::Crct(k), Op::Armz(m, n), ..] => Some(Op::CrctArmz(*k, *m, *n)),
+            _ => None,
+        }
+    }
+
+    // quantas instruções originais a operação executa
+    fn len(self) -> usize {
+        match self {
+            Op::CrvlCrctBin(..) | Op::CrvlCrvlBin(..) => 3,
+            Op::BinDsvf(..) | Op::CrctArmz(..) => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Máquina MEPA voltada para desempenho: o código é convertido uma única vez em um
+/// vetor compacto, com desvios resolvidos, e executado num laço sem alocações.
+/// O comportamento observável (saídas, erros, passos, estado final) é o mesmo da
+/// `FullMachine`, mas não há histórico, trace nem cobertura.
+pub struct FastMachine<'a> {
+    // código original, usado apenas nas mensagens de erro
+    code: MepaCode,
+    plain: Vec<Op>,
+    ops: Vec<Op>,
+    pub m: Vec<i32>,
+    pub d: Vec<i32>,
+    pub i: usize,
+    pub s: i32,
+    pub steps: usize,
+    limits: ExecutionLimits,
+    arithmetic: Arithmetic,
+    io: Box<dyn MepaIo + 'a>,
+    output: Option<&'a mut Vec<i32>>,
+}
+
+impl<'a> FastMachine<'a> {
+    pub fn new(code: MepaCode) -> MepaResult<FastMachine<'a>> {
+        let code = code.assemble()?;
+        let plain: Vec<Op> = code
+            .iter()
+            .map(|(_, instruction)| Op::lower(instruction))
+            .collect();
+        let ops = (0..plain.len())
+            .map(|i| Op::fuse(&plain[i..]).unwrap_or(plain[i]))
+            .collect();
+        let levels = code.lexical_levels();
+        Ok(FastMachine {
+            code,
+            plain,
+            ops,
+            m: Vec::new(),
+            d: vec![-1; levels],
+            i: 0,
+            s: -1,
+            steps: 0,
+            limits: ExecutionLimits::default(),
+            arithmetic: Arithmetic::default(),
+            io: Box::new(StdinIo),
+            output: None,
+        })
+    }
+    /// Desliga as superinstruções, executando uma instrução por vez
+    pub fn without_superinstructions(mut self) -> Self {
+        self.ops = self.plain.clone();
+        self
+    }
+    pub fn with_io<I: MepaIo + 'a>(mut self, io: I) -> Self {
+        self.io = Box::new(io);
+        self
+    }
+    pub fn add_input_vec(self, input: Vec<i32>) -> Self {
+        self.with_io(VecIo::new(input).printing())
+    }
+    pub fn add_output(mut self, output: &'a mut Vec<i32>) -> Self {
+        self.output = Some(output);
+        self
+    }
+    pub fn add_input<R: Read + 'a>(self, readable: R) -> Self {
+        self.with_io(ReaderIo::new(BufReader::new(readable)))
+    }
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    pub fn ended(&self) -> bool {
+        matches!(self.plain.get(self.i), Some(Op::Para))
+    }
+
+    /// Executa até PARA, um erro ou um limite
+    pub fn execute(&mut self) -> MepaResult<()> {
+        let max_steps = self.limits.max_steps.unwrap_or(usize::MAX);
+        let deadline = self.limits.timeout.map(|t| (Instant::now() + t, t));
+        let mut next_clock_check = 0;
+        loop {
+            let op = self.ops.get(self.i).copied();
+            if let Some(Op::Para) = op {
+                return Ok(());
+            }
+            if self.steps >= max_steps {
+                return Err(self.limit_exceeded(Limit::Steps(max_steps)));
+            }
+            if let Some((deadline, timeout)) = deadline {
+                // consultar o relógio a cada passo seria caro demais
+                if self.steps >= next_clock_check {
+                    next_clock_check = self.steps + 1024;
+                    if Instant::now() >= deadline {
+                        return Err(self.limit_exceeded(Limit::Timeout(timeout.as_millis() as u64)));
+                    }
+                }
+            }
+            let Some(op) = op else {
+                return Err(MepaError::Runtime("Programa encerrado".to_owned()));
+            };
+            // perto do limite de passos, as instruções voltam a ser executadas uma a uma,
+            // para que o erro aconteça exatamente no mesmo passo
+            if op.len() > 1 && self.steps + op.len() > max_steps {
+                self.exec(self.plain[self.i])?;
+            } else {
+                self.exec(op)?;
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn exec(&mut self, op: Op) -> MepaResult<()> {
+        match op {
+            Op::Crct(k) => self.crct(k),
+            Op::Crvl(m, n) => self.crvl(m, n),
+            Op::Cren(m, n) => {
+                let value = self.d[self.level(m)?] + n;
+                self.push(value)
+            }
+            Op::Armz(m, n) => self.armz(m, n),
+            Op::Crvi(m, n) => {
+                let value = self.m[self.address(self.m[self.variable(m, n)?])?];
+                self.push(value)
+            }
+            Op::Armi(m, n) => {
+                let top = self.stack(0)?;
+                let addr = self.address(self.m[self.variable(m, n)?])?;
+                self.m[addr] = self.m[top];
+                self.s -= 1;
+                self.next()
+            }
+            Op::Bin(op) => self.bin(op),
+            Op::Divi => {
+                let b = self.stack(0)?;
+                if self.m[b] == 0 {
+                    return Err(MepaError::DivisionByZero {
+                        instruction_index: self.i,
+                        instruction: self.instruction(),
+                    });
+                }
+                let a = self.stack(1)?;
+                self.m[a] = self.m[a]
+                    .div(&self.m[b], self.arithmetic)
+                    .ok_or_else(|| self.overflow())?;
+                self.s -= 1;
+                self.next()
+            }
+            Op::Invr => {
+                let a = self.stack(0)?;
+                self.m[a] = self.m[a]
+                    .neg(self.arithmetic.mode)
+                    .ok_or_else(|| self.overflow())?;
+                self.next()
+            }
+            Op::Nega => {
+                let a = self.stack(0)?;
+                self.m[a] = (self.m[a] == 0) as i32;
+                self.next()
+            }
+            Op::Dsvs(p) => self.jump(p),
+            Op::Dsvf(p) => self.dsvf(p),
+            Op::Nada => self.next(),
+            Op::Para => Ok(()),
+            Op::Leit => match self.io.read()? {
+                Some(n) => self.push(n),
+                None => Err(MepaError::EndOfInput {
+                    instruction_index: self.i,
+                }),
+            },
+            Op::Impr => {
+                let value = self.m[self.stack(0)?];
+                match &mut self.output {
+                    Some(buffer) => buffer.push(value),
+                    None => self.io.write(value)?,
+                }
+                self.s -= 1;
+                self.next()
+            }
+            Op::Amem(n) => {
+                self.aloc(n as usize)?;
+                self.next()
+            }
+            Op::Dmem(n) => {
+                self.stack(n - 1)?;
+                self.s -= n;
+                self.next()
+            }
+            Op::Inpp => {
+                self.s = -1;
+                self.d[0] = 0;
+                self.steps += 1;
+                self.i = 1;
+                Ok(())
+            }
+            Op::Chpr(p) => {
+                self.aloc(1)?;
+                self.m[self.s as usize] = self.i as i32 + 1;
+                self.jump(p)
+            }
+            Op::Enpr(k) => {
+                let k = self.level(k)?;
+                self.aloc(1)?;
+                self.m[self.s as usize] = self.d[k];
+                self.d[k] = self.s + 1;
+                self.next()
+            }
+            Op::Rtpr(k, n) => {
+                let k = self.level(k)?;
+                let (ret, top) = (self.stack(1)?, self.stack(0)?);
+                self.stack(n + 1)?;
+                let target = self.m[ret];
+                if target < 0 || target as usize >= self.plain.len() {
+                    return Err(MepaError::InvalidJump {
+                        target,
+                        instruction_index: self.i,
+                        instruction: self.instruction(),
+                    });
+                }
+                self.d[k] = self.m[top];
+                self.s -= n + 2;
+                self.jump(target as usize)
+            }
+            Op::CrvlCrctBin(m, n, k, op) => {
+                self.crvl(m, n)?;
+                self.crct(k)?;
+                self.bin(op)
+            }
+            Op::CrvlCrvlBin(m, n, m2, n2, op) => {
+                self.crvl(m, n)?;
+                self.crvl(m2, n2)?;
+                self.bin(op)
+            }
+            Op::BinDsvf(op, p) => {
+                self.bin(op)?;
+                self.dsvf(p)
+            }
+            Op::CrctArmz(k, m, n) => {
+                self.crct(k)?;
+                self.armz(m, n)
+            }
+        }
+    }
+
+    // Cada instrução valida tudo antes de alterar o estado, como na BasicMachine,
+    // então um erro no meio de uma superinstrução deixa a máquina no passo certo
+
+    #[inline(always)]
+    fn next(&mut self) -> MepaResult<()> {
+        self.i += 1;
+        self.steps += 1;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn jump(&mut self, p: usize) -> MepaResult<()> {
+        self.i = p;
+        self.steps += 1;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn push(&mut self, value: i32) -> MepaResult<()> {
+        self.aloc(1)?;
+        self.m[self.s as usize] = value;
+        self.next()
+    }
+
+    #[inline(always)]
+    fn crct(&mut self, k: i32) -> MepaResult<()> {
+        self.push(k)
+    }
+
+    #[inline(always)]
+    fn crvl(&mut self, m: i32, n: i32) -> MepaResult<()> {
+        let value = self.m[self.variable(m, n)?];
+        self.push(value)
+    }
+
+    #[inline(always)]
+    fn armz(&mut self, m: i32, n: i32) -> MepaResult<()> {
+        let top = self.stack(0)?;
+        let addr = self.variable(m, n)?;
+        self.m[addr] = self.m[top];
+        self.s -= 1;
+        self.next()
+    }
+
+    #[inline(always)]
+    fn bin(&mut self, op: BinOp) -> MepaResult<()> {
+        let (a, b) = (self.stack(1)?, self.stack(0)?);
+        self.m[a] = op
+            .apply(self.m[a], self.m[b], self.arithmetic)
+            .ok_or_else(|| self.overflow())?;
+        self.s -= 1;
+        self.next()
+    }
+
+    #[inline(always)]
+    fn dsvf(&mut self, p: usize) -> MepaResult<()> {
+        let condition = self.m[self.stack(0)?];
+        self.s -= 1;
+        if condition == 0 {
+            self.jump(p)
+        } else {
+            self.next()
+        }
+    }
+
+    #[inline(always)]
+    fn aloc(&mut self, amount: usize) -> MepaResult<()> {
+        if let Some(max) = self.limits.max_memory {
+            if ((self.s + 1) as usize).saturating_add(amount) > max {
+                return Err(self.limit_exceeded(Limit::Memory(max)));
+            }
+        }
+        self.s += amount as i32;
+        if self.s + 1 > self.m.len() as i32 {
+            self.m.resize((self.s + 1) as usize, 0);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn stack(&self, depth: i32) -> MepaResult<usize> {
+        let addr = self.s - depth;
+        if addr < 0 {
+            Err(MepaError::StackUnderflow {
+                instruction_index: self.i,
+                instruction: self.instruction(),
+            })
+        } else if addr as usize >= self.m.len() {
+            // profundidade negativa, acima do topo
+            Err(MepaError::InvalidAddress {
+                addr,
+                instruction_index: self.i,
+                instruction: self.instruction(),
+            })
+        } else {
+            Ok(addr as usize)
+        }
+    }
+
+    #[inline(always)]
+    fn level(&self, k: i32) -> MepaResult<usize> {
+        if k < 0 || k as usize >= self.d.len() {
+            Err(MepaError::InvalidLevel {
+                level: k,
+                instruction_index: self.i,
+                instruction: self.instruction(),
+            })
+        } else {
+            Ok(k as usize)
+        }
+    }
+
+    #[inline(always)]
+    fn address(&self, addr: i32) -> MepaResult<usize> {
+        if addr < 0 || addr as usize >= self.m.len() {
+            Err(MepaError::InvalidAddress {
+                addr,
+                instruction_index: self.i,
+                instruction: self.instruction(),
+            })
+        } else {
+            Ok(addr as usize)
+        }
+    }
+
+    #[inline(always)]
+    fn variable(&self, m: i32, n: i32) -> MepaResult<usize> {
+        self.address(self.d[self.level(m)?] + n)
+    }
+
+    // usados só quando há erro
+    #[cold]
+    fn instruction(&self) -> Instruction {
+        self.code[self.i].1.clone()
+    }
+
+    #[cold]
+    fn overflow(&self) -> MepaError {
+        MepaError::ArithmeticOverflow {
+            instruction_index: self.i,
+            instruction: self.instruction(),
+        }
+    }
+
+    #[cold]
+    fn limit_exceeded(&self, limit: Limit) -> MepaError {
+        MepaError::LimitExceeded {
+            limit,
+            steps: self.steps,
+            instruction_index: self.i,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::samples::{programs, run_full, INPUT};
+    use std::str::FromStr;
+
+    fn run_fast(
+        code: MepaCode,
+        limits: ExecutionLimits,
+        fuse: bool,
+    ) -> (Result<(), String>, Vec<i32>, usize) {
+        let mut output = Vec::new();
+        let mut machine = FastMachine::new(code).unwrap();
+        if !fuse {
+            machine = machine.without_superinstructions();
+        }
+        let mut machine = machine
+            .add_input_vec(INPUT.to_vec())
+            .add_output(&mut output)
+            .with_limits(limits);
+        let result = machine.execute().map_err(|e| e.to_string());
+        let steps = machine.steps;
+        drop(machine);
+        (result, output, steps)
+    }
+
+    #[test]
+    fn same_behavior_as_full_machine() {
+        let limits = ExecutionLimits {
+            max_steps: Some(2_000_000),
+            ..Default::default()
+        };
+        for (name, code) in programs() {
+            let expected = run_full(code.clone(), limits);
+            assert_eq!(run_fast(code.clone(), limits, true), expected, "{}", name);
+            assert_eq!(
+                run_fast(code, limits, false),
+                expected,
+                "{} (sem fusão)",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn step_limit_inside_superinstruction() {
+        // CRVL; CRCT; SOMA, CRCT; ARMZ e CMME; DSVF viram superinstruções
+        let code = MepaCode::from_str(
+            "    INPP\n    AMEM 1\n    CRCT 0\n    ARMZ 0 0\nL1: CRVL 0 0\n    CRCT 3\n    CMME\n    DSVF L2\n    CRVL 0 0\n    CRCT 1\n    SOMA\n    ARMZ 0 0\n    DSVS L1\nL2: CRVL 0 0\n    IMPR\n    PARA\n",
+        )
+        .unwrap();
+        let (result, output, total) = run_full(code.clone(), ExecutionLimits::default());
+        assert_eq!((result, output), (Ok(()), vec![3]));
+        // o limite cai em cada posição, inclusive no meio das sequências fundidas
+        for max_steps in 0..=total {
+            let limits = ExecutionLimits {
+                max_steps: Some(max_steps),
+                ..Default::default()
+            };
+            let expected = run_full(code.clone(), limits);
+            assert_eq!(
+                run_fast(code.clone(), limits, true),
+                expected,
+                "{}",
+                max_steps
+            );
+        }
+    }
+}
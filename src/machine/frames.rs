@@ -0,0 +1,173 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::basic_machine::BasicMachine;
+use crate::compiler::SourceMap;
+use crate::mepa::{cell::Cell, instruction::Instruction};
+
+/// Um quadro ativo na pilha M, reconstruído a partir dos `D` salvos pelos `ENPR`
+/// e dos endereços de retorno empilhados pelos `CHPR`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    /// Endereço do `ENPR` do procedimento; `None` no programa principal
+    /// ou se o `CHPR` que fez a chamada não foi encontrado
+    pub entry: Option<usize>,
+    pub level: usize,
+    /// Valor de `D[level]` no quadro
+    pub base: usize,
+    /// Para onde o `RTPR` volta; `None` no programa principal
+    pub return_address: Option<usize>,
+    /// Posições de M dos argumentos (deslocamentos negativos)
+    pub arguments: Range<usize>,
+    /// Posições reservadas pelo `AMEM` logo depois do `ENPR` (ou do `INPP`)
+    pub locals: Range<usize>,
+    /// O restante do quadro, até o próximo quadro ou o topo da pilha
+    pub temporaries: Range<usize>,
+}
+
+impl<C: Cell> BasicMachine<C> {
+    /// Quadros ativos, do mais interno ao programa principal. Vazio antes do `INPP`.
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        let top = (self.s + 1) as usize;
+        let address = |value: &C| usize::try_from(value.to_i32_saturating()).ok();
+
+        // os quadros de cada nível formam uma lista ligada pelos D salvos
+        let mut bases: Vec<(usize, usize)> = Vec::new();
+        for level in 1..self.d.len() {
+            let mut base = self.d[level];
+            while base >= 2 && base as usize <= top && !bases.iter().any(|b| b.0 == base as usize) {
+                bases.push((base as usize, level));
+                base = self.m[base as usize - 1].to_i32_saturating();
+            }
+        }
+        bases.sort_unstable_by_key(|&(base, _)| std::cmp::Reverse(base));
+
+        let mut frames = Vec::with_capacity(bases.len() + 1);
+        // o início do quadro mais interno já visto, que limita os temporários
+        let mut end = top;
+        for (base, level) in bases {
+            let return_address = address(&self.m[base - 2]);
+            let entry = return_address
+                .and_then(|r| r.checked_sub(1))
+                .and_then(|call| match self.instruction(call) {
+                    Some(Instruction::CHPR(target)) => Some(target.unwrap()),
+                    _ => None,
+                });
+            let arguments = entry.map_or(0, |entry| self.parameters(entry, level));
+            let locals = entry.map_or(0, |entry| self.reserved(entry + 1));
+            let start = (base - 2).saturating_sub(arguments);
+            let locals_end = (base + locals).min(end);
+            frames.push(CallFrame {
+                entry,
+                level,
+                base,
+                return_address,
+                arguments: start..base - 2,
+                locals: base..locals_end,
+                temporaries: locals_end..end,
+            });
+            end = start;
+        }
+        if self.d.first().is_some_and(|&base| base >= 0) {
+            let base = (self.d[0] as usize).min(end);
+            let locals_end = (base + self.reserved(1)).min(end);
+            frames.push(CallFrame {
+                entry: None,
+                level: 0,
+                base,
+                return_address: None,
+                arguments: base..base,
+                locals: base..locals_end,
+                temporaries: locals_end..end,
+            });
+        }
+        frames
+    }
+
+    // tamanho do AMEM no endereço, se houver
+    fn reserved(&self, addr: usize) -> usize {
+        match self.instruction(addr) {
+            Some(Instruction::AMEM(n)) => (*n).max(0) as usize,
+            _ => 0,
+        }
+    }
+
+    // quantidade de parâmetros, tirada do primeiro RTPR do mesmo nível depois da entrada
+    fn parameters(&self, entry: usize, level: usize) -> usize {
+        (entry..self.code_len())
+            .find_map(|addr| match self.instruction(addr) {
+                Some(Instruction::RTPR(k, n)) if *k as usize == level => Some((*n).max(0) as usize),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    /// Os quadros de `call_stack` em texto, com os argumentos, locais e temporários de
+    /// cada um. Com o mapa do fonte, as posições aparecem pelo nome da variável ipt.
+    pub fn backtrace(&self, source_map: Option<&SourceMap>) -> String {
+        let mut text = String::new();
+        let frames = self.call_stack();
+        if frames.is_empty() {
+            text.push_str("Nenhum quadro ativo\n");
+        }
+        for (depth, frame) in frames.iter().enumerate() {
+            let name = match frame.entry {
+                None if frame.level == 0 => "programa principal".to_owned(),
+                None => "procedimento desconhecido".to_owned(),
+                Some(entry) => self.procedure_name(entry, source_map),
+            };
+            let _ = write!(text, "#{} {}", depth, name);
+            if let Some(r) = frame.return_address {
+                let _ = write!(text, ", retorna para {}", r);
+            }
+            let _ = writeln!(text, " (D[{}] = {})", frame.level, frame.base);
+            let cells = |range: &Range<usize>| {
+                range
+                    .clone()
+                    .map(|addr| self.describe_cell(frame, addr, source_map))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            for (title, range) in [
+                ("argumentos", &frame.arguments),
+                ("locais", &frame.locals),
+                ("temporários", &frame.temporaries),
+            ] {
+                if !range.is_empty() {
+                    let _ = writeln!(text, "    {}: {}", title, cells(range));
+                }
+            }
+        }
+        text
+    }
+
+    fn procedure_name(&self, entry: usize, source_map: Option<&SourceMap>) -> String {
+        let label = match self.label(entry) {
+            Some(label) => label.to_string(),
+            None => format!("procedimento em {}", entry),
+        };
+        match source_map.and_then(|map| map.function_names().remove(&label)) {
+            Some(function) => format!("{} ({})", function, label),
+            None => label,
+        }
+    }
+
+    // "x = 5" com o nome da variável, senão "[+2] = 5"
+    fn describe_cell(
+        &self,
+        frame: &CallFrame,
+        addr: usize,
+        source_map: Option<&SourceMap>,
+    ) -> String {
+        let offset = addr as i32 - frame.base as i32;
+        let variable = source_map
+            .and_then(|map| map.variable(frame.entry.unwrap_or(0), frame.level as i32, offset));
+        let value = &self.m[addr];
+        match variable {
+            Some(v) if v.offset == offset => format!("{} = {}", v.name, value),
+            // vetores: a primeira posição aponta para os elementos, que vêm logo depois
+            Some(v) => format!("{}[{}] = {}", v.name, offset - v.offset - 1, value),
+            None => format!("[{:+}] = {}", offset, value),
+        }
+    }
+}
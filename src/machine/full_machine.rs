@@ -1,99 +1,205 @@
+use crate::machine::arithmetic::Arithmetic;
 use crate::machine::basic_machine::BasicMachine;
+use crate::machine::io::{MepaIo, ReaderIo, StdinIo, VecIo};
+use crate::machine::observer::MachineObserver;
+use crate::machine::snapshot::Snapshot;
+use crate::mepa::cell::Cell;
 use crate::mepa::code::MepaCode;
-use crate::mepa::error::MepaResult;
-use crate::utils::input_i32;
+use crate::mepa::error::{Limit, MepaError, MepaResult};
+use crate::mepa::instruction::Instruction;
 
 use std::io::{BufReader, Read};
+use std::time::{Duration, Instant};
 
-pub enum InputSource {
-    Vec(Vec<i32>),
-    Readable(Box<dyn Read>),
-    Stdin,
+/// Limites opcionais para proteger contra programas que não terminam
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExecutionLimits {
+    pub max_steps: Option<usize>,
+    /// Tamanho máximo de M
+    pub max_memory: Option<usize>,
+    pub timeout: Option<Duration>,
 }
-impl InputSource {
-    pub fn readable<R: Read + 'static>(readable: R) -> Self {
-        InputSource::Readable(Box::new(readable))
-    }
-    pub fn read(&mut self) -> Option<i32> {
-        match self {
-            Self::Vec(v) => {
-                if v.len() > 1 {
-                    v.pop()
-                } else {
-                    v.get(0).copied()
-                }
-            }
-
-            Self::Stdin => Some(input_i32()),
-
-            Self::Readable(readable) => {
-                let mut buf_reader = BufReader::new(readable);
-                let mut buffer = String::new();
 
-                if buf_reader.read_to_string(&mut buffer).is_ok() {
-                    buffer
-                        .split_whitespace()
-                        .next()
-                        .and_then(|s| s.parse::<i32>().ok())
-                } else {
-                    None
-                }
-            }
-        }
-    }
-}
-
-pub struct FullMachine<'a> {
-    basic_machine: BasicMachine,
-    input: InputSource,
-    output: Option<&'a mut Vec<i32>>,
+/// `BasicMachine` com entrada e saída, limites e observadores
+pub struct FullMachine<'a, C = i32> {
+    basic_machine: BasicMachine<C>,
+    io: Box<dyn MepaIo<C> + 'a>,
+    // quando definido, recebe as saídas no lugar de `io`
+    output: Option<&'a mut Vec<C>>,
+    // todas as saídas produzidas, para snapshots
+    written: Vec<C>,
+    limits: ExecutionLimits,
+    // quando o prazo acaba; definido no primeiro passo
+    deadline: Option<Instant>,
+    // entradas devolvidas por step_back, lidas antes da fonte de entrada
+    replay: Vec<C>,
+    // vazio na maioria das execuções, e então nenhum evento é montado
+    observers: Vec<Box<dyn MachineObserver<C> + 'a>>,
+    // se os observadores já receberam `on_finish`
+    finished: bool,
 }
 
-impl<'a> FullMachine<'a> {
-    pub fn new(code: MepaCode) -> FullMachine<'a> {
-        let basic_machine = BasicMachine::new(code);
-        FullMachine {
+impl<'a, C: Cell> FullMachine<'a, C> {
+    pub fn new(code: MepaCode<C>) -> MepaResult<Self> {
+        let basic_machine = BasicMachine::new(code)?;
+        Ok(FullMachine {
             basic_machine,
-            input: InputSource::Stdin,
+            io: Box::new(StdinIo),
             output: None,
-        }
+            written: Vec::new(),
+            limits: ExecutionLimits::default(),
+            deadline: None,
+            replay: Vec::new(),
+            observers: Vec::new(),
+            finished: false,
+        })
     }
-    pub fn add_input_vec(mut self, input: Vec<i32>) -> Self {
-        self.input = InputSource::Vec(input);
+    /// Usa `io` para `LEIT` e `IMPR` (o padrão é `StdinIo`)
+    pub fn with_io<I: MepaIo<C> + 'a>(mut self, io: I) -> Self {
+        self.io = Box::new(io);
         self
     }
-    pub fn add_output(mut self, output: &'a mut Vec<i32>) -> Self {
+    /// Lê os valores em ordem e imprime as saídas
+    pub fn add_input_vec(self, input: Vec<C>) -> Self {
+        self.with_io(VecIo::new(input).printing())
+    }
+    /// Guarda as saídas no vetor em vez de enviá-las para o `MepaIo`
+    pub fn add_output(mut self, output: &'a mut Vec<C>) -> Self {
         self.output = Some(output);
         self
     }
-    pub fn add_input<R: Read + 'static>(mut self, readable: R) -> Self {
-        self.input = InputSource::readable(readable);
+    /// Lê inteiros separados por espaços do leitor e imprime as saídas
+    pub fn add_input<R: Read + 'a>(self, readable: R) -> Self {
+        self.with_io(ReaderIo::new(BufReader::new(readable)))
+    }
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.basic_machine.max_memory = limits.max_memory;
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.basic_machine.arithmetic = arithmetic;
+        self
+    }
+
+    /// Detecta leituras de posições não inicializadas; ver `BasicMachine::sanitize`
+    pub fn with_sanitizer(mut self) -> Self {
+        self.basic_machine.sanitize();
+        self
+    }
+
+    /// Guarda os últimos `capacity` passos, permitindo voltar com `step_back`
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.basic_machine.record_history(capacity);
         self
     }
 
+    /// Avisa `observer` de cada passo executado. Pode ser chamado mais de uma vez;
+    /// os observadores são avisados na ordem em que foram registrados.
+    pub fn with_observer<O: MachineObserver<C> + 'a>(mut self, observer: O) -> Self {
+        self.basic_machine.keep_last_step();
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Estado interno da máquina, que continua disponível depois de um erro
+    pub fn basic_machine(&self) -> &BasicMachine<C> {
+        &self.basic_machine
+    }
+
     pub fn ended(&self) -> bool {
         self.basic_machine.ended()
     }
-    pub fn show_state(&self, historico: Option<&[&[i32]]>) {
-        self.basic_machine.show_state(historico);
+    pub fn show_state(&self) {
+        self.basic_machine.show_state();
+    }
+
+    /// Desfaz o último passo. A entrada consumida volta a ser a próxima lida
+    /// e a saída produzida é retirada do buffer (se já foi impressa, não há como desfazer).
+    /// Retorna false se não houver passo no histórico.
+    pub fn step_back(&mut self) -> bool {
+        match self.basic_machine.step_back() {
+            Some(record) => {
+                if let Some(input) = record.input {
+                    self.replay.push(input);
+                }
+                if record.output.is_some() {
+                    self.written.pop();
+                    if let Some(buffer) = &mut self.output {
+                        buffer.pop();
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn check_limits(&mut self) -> MepaResult<()> {
+        let steps = self.basic_machine.steps;
+        let exceeded = |limit| MepaError::LimitExceeded {
+            limit,
+            steps,
+            instruction_index: self.basic_machine.i,
+        };
+        if let Some(max_steps) = self.limits.max_steps {
+            if steps >= max_steps {
+                return Err(exceeded(Limit::Steps(max_steps)));
+            }
+        }
+        if let Some(timeout) = self.limits.timeout {
+            let deadline = *self
+                .deadline
+                .get_or_insert_with(|| Instant::now() + timeout);
+            // consultar o relógio a cada passo seria caro demais
+            if steps.is_multiple_of(1024) && Instant::now() >= deadline {
+                return Err(exceeded(Limit::Timeout(timeout.as_millis() as u64)));
+            }
+        }
+        Ok(())
     }
 
     pub fn execute_step(&mut self) -> MepaResult<()> {
-        let r: MepaResult<Option<i32>> = match self.basic_machine.step_with_input(None) {
+        self.check_limits()?;
+        let i = self.basic_machine.i;
+        if !self.observers.is_empty() {
+            if let Some(instruction) = self.basic_machine.instruction(i) {
+                for observer in &mut self.observers {
+                    observer.on_step(&self.basic_machine, instruction);
+                }
+            }
+        }
+        let r: MepaResult<Option<C>> = match self.basic_machine.step_with_input(None) {
             Ok(n) => Ok(n),
-            Err(crate::mepa::error::MepaError::MissingInput(_)) => {
+            Err(MepaError::MissingInput(_)) => {
                 // se causou erro MissingInput uma vez, vou rodar com input
-                self.basic_machine.step_with_input(self.input.read())
+                let input = match self.replay.pop() {
+                    Some(input) => input,
+                    None => self.io.read()?.ok_or(MepaError::EndOfInput {
+                        instruction_index: i,
+                    })?,
+                };
+                let result = self.basic_machine.step_with_input(Some(input.clone()));
+                if result.is_err() {
+                    // a entrada não foi consumida
+                    self.replay.push(input);
+                }
+                result
             }
             Err(e) => Err(e),
         };
 
+        if r.is_ok() && !self.observers.is_empty() {
+            self.notify_observers(i);
+        }
+
         match r {
             Ok(Some(output)) => {
-                if let Some(buffer) = &mut self.output {
-                    buffer.push(output);
-                } else {
-                    println!("{}", output);
+                self.written.push(output.clone());
+                match &mut self.output {
+                    Some(buffer) => buffer.push(output),
+                    None => self.io.write(output)?,
                 }
                 Ok(())
             }
@@ -101,13 +207,179 @@ impl<'a> FullMachine<'a> {
             Err(e) => Err(e),
         }
     }
+
+    // Eventos do passo que começou em `i`, a partir do que ele alterou
+    fn notify_observers(&mut self, i: usize) {
+        let machine = &self.basic_machine;
+        let Some(record) = machine.last_step() else {
+            return;
+        };
+        for (n, (addr, old)) in record.m.iter().enumerate() {
+            // o valor escrito é o anterior da próxima escrita no mesmo endereço, ou o atual
+            let new = record.m[n + 1..]
+                .iter()
+                .find(|(other, _)| other == addr)
+                .map_or(&machine.m[*addr], |(_, value)| value);
+            for observer in &mut self.observers {
+                observer.on_memory_write(*addr, old, new);
+            }
+        }
+        match machine.instruction(i) {
+            Some(Instruction::CHPR(_)) => {
+                for observer in &mut self.observers {
+                    observer.on_call(i, machine.i);
+                }
+            }
+            Some(Instruction::RTPR(_, _)) => {
+                for observer in &mut self.observers {
+                    observer.on_return(i, machine.i);
+                }
+            }
+            _ => (),
+        }
+        if let Some(input) = &record.input {
+            for observer in &mut self.observers {
+                observer.on_input(input);
+            }
+        }
+        if let Some(output) = &record.output {
+            for observer in &mut self.observers {
+                observer.on_output(output);
+            }
+        }
+        if machine.ended() {
+            for observer in &mut self.observers {
+                observer.on_halt(machine);
+            }
+        }
+        for observer in &mut self.observers {
+            observer.on_step_end(machine);
+        }
+    }
+
     pub fn current_memory_usage(&self) -> i32 {
         self.basic_machine.current_memory_usage()
     }
     pub fn execute(&mut self) -> MepaResult<()> {
-        while !self.ended() {
-            self.execute_step()?;
+        self.execute_until(usize::MAX)
+    }
+
+    /// Executa até o programa terminar ou até completar `step` passos no total
+    pub fn execute_until(&mut self, step: usize) -> MepaResult<()> {
+        let mut result = Ok(());
+        while !self.ended() && self.basic_machine.steps < step && result.is_ok() {
+            result = self.execute_step();
         }
-        Ok(())
+        if !self.finished && (result.is_err() || self.ended()) {
+            self.finished = true;
+            for observer in &mut self.observers {
+                // o erro da execução tem precedência sobre o dos observadores
+                let finish = observer.on_finish();
+                if result.is_ok() {
+                    result = finish;
+                }
+            }
+        }
+        result
+    }
+}
+
+// Snapshots guardam células i32
+impl<'a> FullMachine<'a> {
+    /// Retoma a execução salva. As entradas restantes, se conhecidas, são lidas
+    /// antes de qualquer outra; senão a entrada volta a ser o stdin.
+    pub fn from_snapshot(snapshot: &Snapshot) -> MepaResult<FullMachine<'a>> {
+        let mut machine = FullMachine {
+            basic_machine: BasicMachine::from_snapshot(snapshot)?,
+            ..FullMachine::new(snapshot.code()?)?
+        };
+        machine.written = snapshot.output.clone();
+        if let Some(input) = &snapshot.input {
+            machine = machine.add_input_vec(input.clone());
+        }
+        Ok(machine)
+    }
+
+    /// Estado atual, com as entradas ainda não lidas e as saídas já produzidas
+    pub fn snapshot(&self) -> MepaResult<Snapshot> {
+        let mut snapshot = self.basic_machine.snapshot()?;
+        // entradas devolvidas por step_back vêm antes das que ainda estão na fonte
+        snapshot.input = self
+            .io
+            .remaining_input()
+            .map(|rest| self.replay.iter().rev().copied().chain(rest).collect());
+        snapshot.output = self.written.clone();
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // conta as chamadas de `on_finish`, que sempre falha
+    struct FailingFinish(usize);
+
+    impl MachineObserver for FailingFinish {
+        fn on_finish(&mut self) -> MepaResult<()> {
+            self.0 += 1;
+            Err(MepaError::Other("falha ao finalizar".to_owned()))
+        }
+    }
+
+    fn machine(source: &str) -> FullMachine<'static> {
+        FullMachine::new(MepaCode::from_str(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn on_finish_is_called_once_at_the_end() {
+        let mut observer = FailingFinish(0);
+        let mut full = machine("    INPP\n    CRCT 1\n    CRCT 2\n    SOMA\n    PARA\n")
+            .with_observer(&mut observer);
+        full.execute_until(2).unwrap();
+        full.execute_until(3).unwrap();
+        assert!(matches!(full.execute(), Err(MepaError::Other(_))));
+        full.execute().unwrap();
+        drop(full);
+        assert_eq!(observer.0, 1);
+    }
+
+    #[test]
+    fn execution_error_takes_precedence_over_on_finish() {
+        let mut observer = FailingFinish(0);
+        let result = machine("    INPP\n    SOMA\n    PARA\n")
+            .with_observer(&mut observer)
+            .execute();
+        assert!(matches!(result, Err(MepaError::StackUnderflow { .. })));
+        assert_eq!(observer.0, 1);
+    }
+
+    #[test]
+    fn step_back_replays_input_and_removes_output() {
+        let source = "    INPP\n    LEIT\n    IMPR\n    LEIT\n    IMPR\n    PARA\n";
+        let mut output = Vec::new();
+        let mut full = machine(source)
+            .with_history(100)
+            .add_input_vec(vec![3, 4])
+            .add_output(&mut output);
+        full.execute().unwrap();
+
+        // desfaz o último IMPR e o último LEIT
+        for _ in 0..2 {
+            assert!(full.step_back());
+        }
+        assert_eq!(full.snapshot().unwrap().output, vec![3]);
+        assert_eq!(full.snapshot().unwrap().input, Some(vec![4]));
+        full.execute().unwrap();
+        assert_eq!(full.snapshot().unwrap().output, vec![3, 4]);
+
+        // volta ao início e executa de novo, relendo as duas entradas
+        while full.step_back() {}
+        assert_eq!(full.basic_machine().steps, 0);
+        assert!(full.snapshot().unwrap().output.is_empty());
+        full.execute().unwrap();
+        drop(full);
+        assert_eq!(output, vec![3, 4]);
     }
 }
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+/// O necessário para desfazer um passo da máquina: os valores anteriores
+/// de tudo que a instrução alterou
+#[derive(Clone, Debug, Default)]
+pub struct StepRecord<C = i32> {
+    pub i: usize,
+    pub s: i32,
+    /// Posições de M alteradas, com o valor anterior, na ordem em que foram escritas
+    pub m: Vec<(usize, C)>,
+    /// Posições de D alteradas, com o valor anterior
+    pub d: Vec<(usize, i32)>,
+    // tamanho de M antes do passo, para desfazer o crescimento
+    pub(crate) m_len: usize,
+    // marcas do sanitizador alteradas, com o valor anterior
+    pub(crate) initialized: Vec<(usize, bool)>,
+    /// Entrada consumida por LEIT
+    pub input: Option<C>,
+    /// Saída produzida por IMPR
+    pub output: Option<C>,
+}
+
+/// Histórico limitado dos últimos passos executados.
+/// Quando a capacidade é atingida, os passos mais antigos são descartados.
+#[derive(Clone, Debug)]
+pub struct History<C = i32> {
+    records: VecDeque<StepRecord<C>>,
+    capacity: usize,
+    // maior valor de s já visto, usado por show_state
+    max_s: i32,
+}
+
+impl<C> History<C> {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            records: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+            max_s: -1,
+        }
+    }
+
+    pub fn push(&mut self, record: StepRecord<C>, s: i32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.max_s = self.max_s.max(s);
+        self.records.push_back(record);
+    }
+
+    pub fn pop(&mut self) -> Option<StepRecord<C>> {
+        self.records.pop_back()
+    }
+
+    /// Quantos passos podem ser desfeitos
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn max_s(&self) -> i32 {
+        self.max_s
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.max_s = -1;
+    }
+}
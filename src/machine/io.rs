@@ -0,0 +1,228 @@
+use crate::mepa::cell::Cell;
+use crate::mepa::error::{MepaError, MepaResult};
+use crate::utils::input_value;
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Entrada e saída da máquina: `LEIT` chama `read` e `IMPR` chama `write`.
+/// `C` é o tipo das células da máquina.
+pub trait MepaIo<C = i32> {
+    /// Próximo valor da entrada, ou `None` quando ela acabou
+    fn read(&mut self) -> MepaResult<Option<C>>;
+    fn write(&mut self, value: C) -> MepaResult<()>;
+    /// Entradas ainda não lidas, se a fonte permitir saber quais são (para snapshots)
+    fn remaining_input(&self) -> Option<Vec<C>> {
+        None
+    }
+}
+
+impl<C, T: MepaIo<C> + ?Sized> MepaIo<C> for &mut T {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        (**self).read()
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        (**self).write(value)
+    }
+    fn remaining_input(&self) -> Option<Vec<C>> {
+        (**self).remaining_input()
+    }
+}
+
+impl<C, T: MepaIo<C> + ?Sized> MepaIo<C> for Box<T> {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        (**self).read()
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        (**self).write(value)
+    }
+    fn remaining_input(&self) -> Option<Vec<C>> {
+        (**self).remaining_input()
+    }
+}
+
+/// Lê os valores de um vetor, na ordem, e guarda as saídas em `output`
+#[derive(Debug, Clone, Default)]
+pub struct VecIo<C = i32> {
+    input: VecDeque<C>,
+    pub output: Vec<C>,
+    print: bool,
+}
+
+impl<C> VecIo<C> {
+    pub fn new(input: Vec<C>) -> Self {
+        VecIo {
+            input: input.into(),
+            output: Vec::new(),
+            print: false,
+        }
+    }
+    /// Imprime as saídas no stdout em vez de guardá-las
+    pub fn printing(mut self) -> Self {
+        self.print = true;
+        self
+    }
+    /// Valores ainda não lidos
+    pub fn remaining(&self) -> &VecDeque<C> {
+        &self.input
+    }
+}
+
+impl<C: Cell> MepaIo<C> for VecIo<C> {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        Ok(self.input.pop_front())
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        if self.print {
+            println!("{}", value);
+        } else {
+            self.output.push(value);
+        }
+        Ok(())
+    }
+    fn remaining_input(&self) -> Option<Vec<C>> {
+        Some(self.input.iter().cloned().collect())
+    }
+}
+
+/// Lê inteiros separados por espaços ou quebras de linha à medida que são necessários,
+/// sem esperar o fim do leitor, e escreve um valor por linha
+pub struct ReaderIo<R, W> {
+    reader: R,
+    writer: W,
+    // palavras já lidas da linha atual
+    pending: VecDeque<String>,
+}
+
+impl<R: BufRead> ReaderIo<R, io::Stdout> {
+    pub fn new(reader: R) -> Self {
+        ReaderIo::with_writer(reader, io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> ReaderIo<R, W> {
+    pub fn with_writer(reader: R, writer: W) -> Self {
+        ReaderIo {
+            reader,
+            writer,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<C: Cell, R: BufRead, W: Write> MepaIo<C> for ReaderIo<R, W> {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.pending
+                .extend(line.split_whitespace().map(str::to_owned));
+        }
+        let word = self.pending.pop_front().unwrap_or_default();
+        word.parse()
+            .map(Some)
+            .map_err(|_| MepaError::IO(format!("valor de entrada inválido '{}'", word)))
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        writeln!(self.writer, "{}", value)?;
+        Ok(())
+    }
+}
+
+/// Pede cada valor no terminal e imprime as saídas no stdout.
+/// O fim do stdin (Ctrl-D) encerra a entrada.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdinIo;
+
+impl<C: Cell> MepaIo<C> for StdinIo {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        Ok(input_value()?)
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        println!("{}", value);
+        Ok(())
+    }
+}
+
+/// Troca valores com outra thread. A entrada acaba quando o `Sender` correspondente
+/// é descartado.
+pub struct ChannelIo<C = i32> {
+    input: Receiver<C>,
+    output: Sender<C>,
+}
+
+impl<C> ChannelIo<C> {
+    pub fn new(input: Receiver<C>, output: Sender<C>) -> Self {
+        ChannelIo { input, output }
+    }
+}
+
+impl<C: Cell> MepaIo<C> for ChannelIo<C> {
+    fn read(&mut self) -> MepaResult<Option<C>> {
+        Ok(self.input.recv().ok())
+    }
+    fn write(&mut self, value: C) -> MepaResult<()> {
+        self.output
+            .send(value)
+            .map_err(|_| MepaError::IO("o canal de saída foi fechado".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::FullMachine;
+    use crate::mepa::code::MepaCode;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    #[test]
+    fn vec_io_reads_in_order() {
+        let mut io = VecIo::new(vec![1, 2, 3]);
+        for expected in [1, 2] {
+            assert_eq!(io.read().unwrap(), Some(expected));
+        }
+        assert_eq!(io.remaining_input(), Some(vec![3]));
+        assert_eq!(io.read().unwrap(), Some(3));
+        assert_eq!(io.read().unwrap(), None);
+        io.write(9).unwrap();
+        assert_eq!(io.output, vec![9]);
+    }
+
+    #[test]
+    fn reader_io_streams_values() {
+        // a segunda linha só seria lida se faltassem valores na primeira
+        let mut io = ReaderIo::with_writer(Cursor::new("4 5  6\nx\n"), Vec::new());
+        for expected in [4, 5, 6] {
+            assert_eq!(MepaIo::<i32>::read(&mut io).unwrap(), Some(expected));
+        }
+        assert!(MepaIo::<i32>::read(&mut io).is_err());
+        assert_eq!(MepaIo::<i32>::read(&mut io).unwrap(), None);
+
+        MepaIo::<i32>::write(&mut io, 7).unwrap();
+        MepaIo::<i32>::write(&mut io, -8).unwrap();
+        assert_eq!(io.writer, b"7\n-8\n");
+    }
+
+    #[test]
+    fn missing_input_is_an_error() {
+        let source = "    INPP\n    LEIT\n    IMPR\n    LEIT\n    IMPR\n    PARA\n";
+        let mut output = Vec::new();
+        let result = FullMachine::new(MepaCode::from_str(source).unwrap())
+            .unwrap()
+            .with_io(VecIo::new(vec![7]))
+            .add_output(&mut output)
+            .execute();
+        assert!(matches!(
+            result,
+            Err(MepaError::EndOfInput {
+                instruction_index: 3
+            })
+        ));
+        // o último valor não é repetido
+        assert_eq!(output, vec![7]);
+    }
+}
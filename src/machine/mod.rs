@@ -1,41 +1,223 @@
+mod arithmetic;
 mod basic_machine;
+mod debugger;
+mod fast_machine;
+mod frames;
 mod full_machine;
+mod history;
+mod io;
+mod observer;
+mod snapshot;
+mod trace;
 
 // #[cfg(target_arch = "wasm32")]
 pub mod wasm_machine;
 
-use crate::mepa::code::MepaCode;
-pub use full_machine::FullMachine;
+use crate::compiler::load_with_debug_info;
+use crate::mepa::{
+    cell::Cell,
+    code::MepaCode,
+    error::{MepaError, MepaResult},
+};
+pub use arithmetic::{Arithmetic, ArithmeticMode, DivisionMode};
+pub use basic_machine::BasicMachine;
+pub use debugger::Debugger;
+pub use fast_machine::FastMachine;
+pub use frames::CallFrame;
+pub use full_machine::{ExecutionLimits, FullMachine};
+pub use history::{History, StepRecord};
+pub use io::{ChannelIo, MepaIo, ReaderIo, StdinIo, VecIo};
+pub use observer::MachineObserver;
+pub use snapshot::{Snapshot, SNAPSHOT_VERSION};
+pub use trace::{TraceEntry, TraceFilter, TraceFormat, Tracer};
 
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-pub fn interactive_execution(filename: &PathBuf, input: Vec<i32>) {
-    let mc = MepaCode::from_file(filename).unwrap();
-    let mut machine = FullMachine::new(mc);
+/// Abre o depurador interativo para o arquivo. Se o compilador gravou informações de
+/// depuração ao lado dele, os quadros mostram os nomes das funções e variáveis ipt.
+pub fn interactive_execution(
+    filename: &PathBuf,
+    input: Vec<i32>,
+    arithmetic: Arithmetic,
+) -> MepaResult<()> {
+    let (mc, source_map) = load_with_debug_info(filename)?;
+    let mut debugger = Debugger::new(mc, input)?.with_arithmetic(arithmetic)?;
+    if let Some(source_map) = source_map {
+        debugger = debugger.with_source_map(source_map);
+    }
+    debugger.repl()
+}
+
+/// Executa o arquivo com células do tipo `C`
+pub fn execute<C: Cell>(
+    filename: &PathBuf,
+    input: Vec<C>,
+    output: Option<&mut Vec<C>>,
+    limits: ExecutionLimits,
+    trace: Option<Tracer>,
+    arithmetic: Arithmetic,
+    sanitize: bool,
+) -> MepaResult<()> {
+    let mc = MepaCode::from_file(filename)?;
+    let mut machine = FullMachine::new(mc)?
+        .with_limits(limits)
+        .with_arithmetic(arithmetic);
+    if sanitize {
+        machine = machine.with_sanitizer();
+    }
+    if let Some(tracer) = trace {
+        machine = machine.with_observer(tracer);
+    }
     if input.len() > 0 {
         machine = machine.add_input_vec(input);
     }
-    let mut input_line = String::new();
+    if let Some(output) = output {
+        machine = machine.add_output(output);
+    }
+    machine.execute()
+}
 
-    while !machine.ended() {
-        machine.show_state(None);
-        machine.execute_step().unwrap();
-        std::io::stdin()
-            .read_line(&mut input_line)
-            .expect("Failed to read line");
+/// Como `execute`, mas começando do snapshot em `filename` quando `resume` é verdadeiro,
+/// e parando no passo `snapshot_at.0` para salvar o estado em `snapshot_at.1`.
+/// Entradas dadas substituem as que ficaram guardadas no snapshot, e o mesmo vale para
+/// `arithmetic`; sem ela, vale a semântica do snapshot (ou a padrão).
+pub fn execute_with_snapshot(
+    filename: &PathBuf,
+    resume: bool,
+    input: Vec<i32>,
+    limits: ExecutionLimits,
+    trace: Option<Tracer>,
+    arithmetic: Option<Arithmetic>,
+    snapshot_at: Option<(usize, PathBuf)>,
+) -> MepaResult<()> {
+    let mut machine = if resume {
+        FullMachine::from_snapshot(&Snapshot::load(filename)?)?
+    } else {
+        FullMachine::new(MepaCode::from_file(filename)?)?
+    };
+    machine = machine.with_limits(limits);
+    if let Some(arithmetic) = arithmetic {
+        machine = machine.with_arithmetic(arithmetic);
+    }
+    if let Some(tracer) = trace {
+        machine = machine.with_observer(tracer);
+    }
+    if !input.is_empty() {
+        machine = machine.add_input_vec(input);
+    }
+    match snapshot_at {
+        Some((step, path)) => {
+            let steps = machine.basic_machine().steps;
+            if steps > step {
+                return Err(MepaError::Other(format!(
+                    "a execução já está no passo {}, depois do passo {}",
+                    steps, step
+                )));
+            }
+            machine.execute_until(step)?;
+            if machine.ended() {
+                println!(
+                    "O programa terminou antes do passo {}; nenhum snapshot foi salvo",
+                    step
+                );
+            } else {
+                machine.snapshot()?.save(&path)?;
+                println!(
+                    "Snapshot do passo {} salvo em {}",
+                    machine.basic_machine().steps,
+                    path.display()
+                );
+            }
+            Ok(())
+        }
+        None => machine.execute(),
     }
-    machine.show_state(None);
-    println!("Program executed successfully");
 }
 
-pub fn execute(filename: &PathBuf, input: Vec<i32>, output: Option<&mut Vec<i32>>) {
-    let mc = MepaCode::from_file(filename).unwrap();
-    let mut machine = FullMachine::new(mc);
-    if input.len() > 0 {
+/// Executa com a `FastMachine`, sem suporte a trace
+pub fn execute_fast(
+    filename: &PathBuf,
+    input: Vec<i32>,
+    limits: ExecutionLimits,
+    arithmetic: Arithmetic,
+) -> MepaResult<()> {
+    let mc = MepaCode::from_file(filename)?;
+    let mut machine = FastMachine::new(mc)?
+        .with_limits(limits)
+        .with_arithmetic(arithmetic);
+    if !input.is_empty() {
         machine = machine.add_input_vec(input);
     }
-    if let Some(output) = output {
-        machine = machine.add_output(output);
+    machine.execute()
+}
+
+/// Resultado de uma máquina no benchmark
+pub struct BenchResult {
+    pub name: &'static str,
+    pub steps: usize,
+    /// Melhor tempo entre as repetições
+    pub time: Duration,
+    pub output: Vec<i32>,
+}
+
+/// Executa o programa `repeat` vezes em cada máquina (`FullMachine`, `FastMachine` sem e
+/// com superinstruções), guardando o melhor tempo de cada uma
+pub fn benchmark(code: MepaCode, input: Vec<i32>, repeat: usize) -> MepaResult<Vec<BenchResult>> {
+    let mut results = Vec::new();
+    let best = |run: &mut dyn FnMut() -> MepaResult<(usize, Vec<i32>)>| {
+        let mut best: Option<(Duration, usize, Vec<i32>)> = None;
+        for _ in 0..repeat.max(1) {
+            let start = Instant::now();
+            let (steps, output) = run()?;
+            let time = start.elapsed();
+            if best.as_ref().is_none_or(|(t, _, _)| time < *t) {
+                best = Some((time, steps, output));
+            }
+        }
+        Ok::<_, MepaError>(best.unwrap())
+    };
+
+    let (time, steps, output) = best(&mut || {
+        let mut output = Vec::new();
+        let mut machine = FullMachine::new(code.clone())?
+            .add_input_vec(input.clone())
+            .add_output(&mut output);
+        machine.execute()?;
+        let steps = machine.basic_machine().steps;
+        drop(machine);
+        Ok((steps, output))
+    })?;
+    results.push(BenchResult {
+        name: "FullMachine",
+        steps,
+        time,
+        output,
+    });
+
+    for (name, fused) in [
+        ("FastMachine", false),
+        ("FastMachine + superinstruções", true),
+    ] {
+        let (time, steps, output) = best(&mut || {
+            let mut output = Vec::new();
+            let mut machine = FastMachine::new(code.clone())?
+                .add_input_vec(input.clone())
+                .add_output(&mut output);
+            if !fused {
+                machine = machine.without_superinstructions();
+            }
+            machine.execute()?;
+            let steps = machine.steps;
+            drop(machine);
+            Ok((steps, output))
+        })?;
+        results.push(BenchResult {
+            name,
+            steps,
+            time,
+            output,
+        });
     }
-    machine.execute().unwrap();
+    Ok(results)
 }
@@ -0,0 +1,62 @@
+use super::basic_machine::BasicMachine;
+use crate::mepa::error::MepaResult;
+use crate::mepa::instruction::Instruction;
+
+/// Acompanha a execução de uma `FullMachine`, registrado com `with_observer`.
+/// Todos os métodos têm implementação vazia, então basta implementar os eventos
+/// de interesse. `on_step` é enviado antes de o passo começar, mesmo que ele falhe;
+/// os demais eventos de um passo só são enviados se ele for concluído.
+pub trait MachineObserver<C = i32> {
+    /// Antes de cada passo, com a máquina ainda no estado anterior
+    fn on_step(&mut self, _before: &BasicMachine<C>, _instruction: &Instruction<C>) {}
+    /// Depois dos outros eventos de um passo concluído, com a máquina no estado novo
+    /// e o que o passo alterou em `last_step`
+    fn on_step_end(&mut self, _after: &BasicMachine<C>) {}
+    /// Escrita em M[addr], na ordem em que acontecem dentro do passo
+    fn on_memory_write(&mut self, _addr: usize, _old: &C, _new: &C) {}
+    /// `CHPR` no endereço `from`, desviando para `to`
+    fn on_call(&mut self, _from: usize, _to: usize) {}
+    /// `RTPR` no endereço `from`, voltando para `to`
+    fn on_return(&mut self, _from: usize, _to: usize) {}
+    /// Valor lido por `LEIT`
+    fn on_input(&mut self, _value: &C) {}
+    /// Valor escrito por `IMPR`
+    fn on_output(&mut self, _value: &C) {}
+    /// A execução chegou ao `PARA`
+    fn on_halt(&mut self, _machine: &BasicMachine<C>) {}
+    /// Fim da execução, uma única vez, quando `execute` ou `execute_until` chega ao `PARA`
+    /// ou a um erro. Um erro devolvido aqui só é o resultado se a execução não falhou.
+    fn on_finish(&mut self) -> MepaResult<()> {
+        Ok(())
+    }
+}
+
+impl<C, T: MachineObserver<C> + ?Sized> MachineObserver<C> for &mut T {
+    fn on_step(&mut self, before: &BasicMachine<C>, instruction: &Instruction<C>) {
+        (**self).on_step(before, instruction)
+    }
+    fn on_step_end(&mut self, after: &BasicMachine<C>) {
+        (**self).on_step_end(after)
+    }
+    fn on_memory_write(&mut self, addr: usize, old: &C, new: &C) {
+        (**self).on_memory_write(addr, old, new)
+    }
+    fn on_call(&mut self, from: usize, to: usize) {
+        (**self).on_call(from, to)
+    }
+    fn on_return(&mut self, from: usize, to: usize) {
+        (**self).on_return(from, to)
+    }
+    fn on_input(&mut self, value: &C) {
+        (**self).on_input(value)
+    }
+    fn on_output(&mut self, value: &C) {
+        (**self).on_output(value)
+    }
+    fn on_halt(&mut self, machine: &BasicMachine<C>) {
+        (**self).on_halt(machine)
+    }
+    fn on_finish(&mut self) -> MepaResult<()> {
+        (**self).on_finish()
+    }
+}
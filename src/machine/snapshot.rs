@@ -0,0 +1,183 @@
+use super::arithmetic::Arithmetic;
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::{MepaError, MepaResult};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Versão atual do formato; snapshots de outras versões são recusados
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Estado completo de uma execução, para ser salvo em JSON e retomado depois
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Programa já montado, uma instrução por linha
+    pub code: Vec<String>,
+    pub m: Vec<i32>,
+    pub d: Vec<i32>,
+    pub i: usize,
+    pub s: i32,
+    pub steps: usize,
+    /// Semântica aritmética da execução; snapshots sem o campo usam a padrão
+    #[serde(default)]
+    pub arithmetic: Arithmetic,
+    /// Entradas ainda não lidas; `None` se a fonte de entrada não permite saber
+    /// (ex.: stdin), e então a execução retomada volta a pedir pelo stdin
+    pub input: Option<Vec<i32>>,
+    /// Saídas produzidas até aqui
+    pub output: Vec<i32>,
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> MepaResult<String> {
+        serde_json::to_string_pretty(self).map_err(|e| MepaError::Other(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> MepaResult<Snapshot> {
+        let snapshot: Snapshot = serde_json::from_str(json)
+            .map_err(|e| MepaError::Other(format!("snapshot inválido: {}", e)))?;
+        snapshot.check_version()?;
+        Ok(snapshot)
+    }
+
+    fn check_version(&self) -> MepaResult<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(MepaError::Other(format!(
+                "snapshot na versão {}, mas só a versão {} é suportada",
+                self.version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> MepaResult<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> MepaResult<Snapshot> {
+        Snapshot::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// O programa salvo no snapshot
+    pub fn code(&self) -> MepaResult<MepaCode> {
+        self.code.join("\n").parse()
+    }
+
+    /// Confere a versão e se o estado é consistente com o programa, para que um
+    /// snapshot editado à mão não leve a máquina a acessar posições inexistentes
+    pub(crate) fn validate(&self, code_len: usize, levels: usize) -> MepaResult<()> {
+        self.check_version()?;
+        let invalid =
+            |reason: String| Err(MepaError::Other(format!("snapshot inválido: {}", reason)));
+        if self.i > code_len {
+            return invalid(format!("i = {} fora do programa", self.i));
+        }
+        if self.s < -1 || self.s >= self.m.len() as i32 {
+            return invalid(format!("s = {} fora de M", self.s));
+        }
+        if self.d.len() != levels {
+            return invalid(format!(
+                "D deve ter {} níveis, mas tem {}",
+                levels,
+                self.d.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{BasicMachine, FullMachine};
+    use std::env;
+    use std::str::FromStr;
+
+    // lê n e depois n valores, imprimindo a soma parcial depois de cada um
+    const SOURCE: &str = "\
+    INPP
+    AMEM 2
+    LEIT
+    ARMZ 0 0
+    CRCT 0
+    ARMZ 0 1
+L1: CRVL 0 0
+    CRCT 0
+    CMMA
+    DSVF L2
+    LEIT
+    CRVL 0 1
+    SOMA
+    ARMZ 0 1
+    CRVL 0 1
+    IMPR
+    CRVL 0 0
+    CRCT 1
+    SUBT
+    ARMZ 0 0
+    DSVS L1
+L2: DMEM 2
+    PARA
+";
+    const INPUT: [i32; 5] = [4, 10, 20, 30, 40];
+
+    fn machine(output: &mut Vec<i32>) -> FullMachine<'_> {
+        FullMachine::new(MepaCode::from_str(SOURCE).unwrap())
+            .unwrap()
+            .add_input_vec(INPUT.to_vec())
+            .add_output(output)
+    }
+
+    #[test]
+    fn resumed_run_matches_uninterrupted_run() {
+        let mut expected = Vec::new();
+        machine(&mut expected).execute().unwrap();
+        assert_eq!(expected, vec![10, 30, 60, 100]);
+
+        let mut before = Vec::new();
+        let mut full = machine(&mut before);
+        full.execute_until(30).unwrap();
+        let path = env::temp_dir().join(format!("mepa-rs-snapshot-{}.json", std::process::id()));
+        full.snapshot().unwrap().save(&path).unwrap();
+        drop(full);
+
+        let snapshot = Snapshot::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(snapshot.steps, 30);
+        assert_eq!(snapshot.output, before);
+        assert!(snapshot
+            .input
+            .as_ref()
+            .is_some_and(|input| !input.is_empty()));
+
+        let mut after = Vec::new();
+        FullMachine::from_snapshot(&snapshot)
+            .unwrap()
+            .add_output(&mut after)
+            .execute()
+            .unwrap();
+        assert_eq!([before, after].concat(), expected);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut output = Vec::new();
+        let mut full = machine(&mut output);
+        full.execute_until(5).unwrap();
+        let mut snapshot = full.snapshot().unwrap();
+        drop(full);
+
+        let code_len = snapshot.code().unwrap().len();
+        assert!(snapshot.validate(code_len, snapshot.d.len()).is_ok());
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        assert!(snapshot.validate(code_len, snapshot.d.len()).is_err());
+        assert!(BasicMachine::from_snapshot(&snapshot).is_err());
+        assert!(Snapshot::from_json(&snapshot.to_json().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use super::basic_machine::BasicMachine;
+use super::observer::MachineObserver;
+use crate::mepa::cell::Cell;
+use crate::mepa::error::MepaResult;
+
+/// Quantas posições do topo de M cada linha do trace mostra, por padrão
+const DEFAULT_TOP: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    JsonLines,
+    Csv,
+}
+impl TraceFormat {
+    /// `.csv` gera CSV; qualquer outra extensão gera JSON Lines
+    pub fn from_path<P: AsRef<Path>>(path: P) -> TraceFormat {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("csv") => TraceFormat::Csv,
+            _ => TraceFormat::JsonLines,
+        }
+    }
+}
+
+/// Quais passos entram no trace. Sem restrições, todos entram.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    /// Mnemônicos aceitos, ex.: `CHPR`, `RTPR`
+    pub instructions: Option<Vec<String>>,
+    /// Endereços de instrução aceitos
+    pub addresses: Option<RangeInclusive<usize>>,
+}
+impl TraceFilter {
+    pub fn accepts(&self, index: usize, mnemonic: &str) -> bool {
+        self.instructions
+            .as_ref()
+            .is_none_or(|list| list.iter().any(|m| m.eq_ignore_ascii_case(mnemonic)))
+            && self
+                .addresses
+                .as_ref()
+                .is_none_or(|range| range.contains(&index))
+    }
+}
+
+/// Uma linha do trace, com o estado logo depois da instrução
+#[derive(Serialize, Debug)]
+pub struct TraceEntry<C = i32> {
+    pub step: usize,
+    pub index: usize,
+    pub instruction: String,
+    pub s: i32,
+    /// Topo de M, começando por M[s]
+    pub top: Vec<C>,
+    /// Posições de D alteradas, com o novo valor
+    pub d: Vec<(usize, i32)>,
+    pub input: Option<C>,
+    pub output: Option<C>,
+}
+
+impl<C: Cell> TraceEntry<C> {
+    fn csv_header() -> &'static str {
+        "step,index,instruction,s,top,d,input,output"
+    }
+    fn to_csv(&self) -> String {
+        let join = |values: Vec<String>| values.join(" ");
+        let optional =
+            |value: &Option<C>| value.as_ref().map(|v| v.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.step,
+            self.index,
+            self.instruction,
+            self.s,
+            join(self.top.iter().map(|v| v.to_string()).collect()),
+            join(self.d.iter().map(|(k, v)| format!("{}={}", k, v)).collect()),
+            optional(&self.input),
+            optional(&self.output)
+        )
+    }
+}
+
+/// Escreve o trace de uma execução, um passo por linha.
+/// É um observador, registrado com `FullMachine::with_observer`.
+pub struct Tracer {
+    writer: Box<dyn Write>,
+    format: TraceFormat,
+    filter: TraceFilter,
+    top: usize,
+    header_written: bool,
+    // primeiro erro de escrita, devolvido em `on_finish`; depois dele nada é gravado
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new<W: Write + 'static>(writer: W, format: TraceFormat) -> Tracer {
+        Tracer {
+            writer: Box::new(writer),
+            format,
+            filter: TraceFilter::default(),
+            top: DEFAULT_TOP,
+            header_written: false,
+            error: None,
+        }
+    }
+    /// Cria o arquivo, escolhendo o formato pela extensão
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Tracer> {
+        let format = TraceFormat::from_path(&path);
+        Ok(Self::new(BufWriter::new(File::create(path)?), format))
+    }
+    pub fn with_filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+    /// Quantas posições do topo de M registrar
+    pub fn with_top(mut self, top: usize) -> Self {
+        self.top = top;
+        self
+    }
+
+    // Registra o último passo da máquina, guardado em `last_step`
+    fn record<C: Cell>(&mut self, machine: &BasicMachine<C>) -> io::Result<()> {
+        let Some(record) = machine.last_step() else {
+            return Ok(());
+        };
+        let Some(instruction) = machine.instruction(record.i) else {
+            return Ok(());
+        };
+        let mnemonic = &instruction.to_string_vec()[0];
+        if !self.filter.accepts(record.i, mnemonic) {
+            return Ok(());
+        }
+
+        let depth = self.top.min((machine.s + 1) as usize);
+        let entry = TraceEntry {
+            step: machine.steps,
+            index: record.i,
+            instruction: instruction.to_string(),
+            s: machine.s,
+            top: (0..depth)
+                .map(|j| machine.m[machine.s as usize - j].clone())
+                .collect(),
+            d: record.d.iter().map(|(k, _)| (*k, machine.d[*k])).collect(),
+            input: record.input.clone(),
+            output: record.output.clone(),
+        };
+
+        match self.format {
+            TraceFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, &entry)?;
+                writeln!(self.writer)
+            }
+            TraceFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.writer, "{}", TraceEntry::<C>::csv_header())?;
+                    self.header_written = true;
+                }
+                writeln!(self.writer, "{}", entry.to_csv())
+            }
+        }
+    }
+}
+
+impl<C: Cell> MachineObserver<C> for Tracer {
+    fn on_step_end(&mut self, after: &BasicMachine<C>) {
+        if self.error.is_none() {
+            self.error = self.record(after).err();
+        }
+    }
+
+    // o trace é gravado mesmo que a execução falhe
+    fn on_finish(&mut self) -> MepaResult<()> {
+        match self.error.take() {
+            Some(e) => Err(e.into()),
+            None => Ok(self.writer.flush()?),
+        }
+    }
+}
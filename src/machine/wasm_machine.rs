@@ -1,49 +1,254 @@
+use serde::Serialize;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
+use crate::machine::arithmetic::Arithmetic;
 use crate::machine::basic_machine::BasicMachine;
+use crate::machine::snapshot::Snapshot;
+use crate::mepa::cell::{Big, Cell};
+use crate::mepa::error::{MepaError, MepaResult};
+
+#[derive(Serialize)]
+enum RunStatus {
+    Ended,
+    BudgetExhausted,
+    /// Parou num LEIT; continue com `step(input)`
+    NeedsInput,
+    Error,
+}
+
+#[derive(Serialize)]
+struct RunResult<C> {
+    status: RunStatus,
+    steps: usize,
+    outputs: Vec<C>,
+    error: Option<MepaError>,
+}
+
+/// The machine for each cell type accepted by the constructor
+enum Cells {
+    I16(BasicMachine<i16>),
+    I32(BasicMachine<i32>),
+    I64(BasicMachine<i64>),
+    Big(BasicMachine<Big>),
+}
+
+// Runs `$body` with `$machine` bound to the machine, whatever its cell type
+macro_rules! dispatch {
+    ($cells:expr, $machine:ident => $body:expr) => {
+        match $cells {
+            Cells::I16($machine) => $body,
+            Cells::I32($machine) => $body,
+            Cells::I64($machine) => $body,
+            Cells::Big($machine) => $body,
+        }
+    };
+}
+
 #[wasm_bindgen]
 pub struct MepaMachine {
-    internal: Option<BasicMachine>,
-    error: Option<String>,
+    internal: Option<Cells>,
+    error: Option<MepaError>,
 }
 
 #[wasm_bindgen]
 impl MepaMachine {
+    /// `arithmetic` is "wrapping" (default), "checked" or "saturating", `division` is
+    /// "euclidean" (default), "truncating" or "floored", and `cell` is the type of the
+    /// memory cells: "i16", "i32" (default), "i64" or "big" (arbitrary precision).
+    /// All three may be omitted. With "big", values are passed to and from JS as strings
     #[wasm_bindgen(constructor)]
-    pub fn new(input: &str) -> MepaMachine {
-        match BasicMachine::from_str(input) {
+    pub fn new(
+        input: &str,
+        arithmetic: Option<String>,
+        division: Option<String>,
+        cell: Option<String>,
+    ) -> MepaMachine {
+        let machine = load_cells(input, arithmetic, division, cell.as_deref());
+        match machine {
             Ok(machine) => MepaMachine {
                 internal: Some(machine),
                 error: None,
             },
             Err(e) => MepaMachine {
                 internal: None,
-                error: Some(e.to_string()),
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Restores a machine saved by `snapshot` or by `mepa-rs run --snapshot-at`.
+    /// The saved input and output are in the JSON itself (`input`, `output`)
+    pub fn from_snapshot(json: &str) -> MepaMachine {
+        match Snapshot::from_json(json).and_then(|snapshot| BasicMachine::from_snapshot(&snapshot))
+        {
+            Ok(machine) => MepaMachine {
+                internal: Some(Cells::I32(machine)),
+                error: None,
+            },
+            Err(e) => MepaMachine {
+                internal: None,
+                error: Some(e),
             },
         }
     }
 
+    /// Saves the current state as a versioned JSON snapshot. Input and output are
+    /// handled by the page, so it passes the outputs shown so far and, if known,
+    /// the values still to be read. Only machines with i32 cells can be saved
+    pub fn snapshot(&self, output: Vec<i32>, input: Option<Vec<i32>>) -> Result<String, JsValue> {
+        let machine = match &self.internal {
+            Some(Cells::I32(machine)) => machine,
+            Some(_) => return Err(JsValue::from_str("Snapshots need i32 cells")),
+            None => return Err(JsValue::from_str("Machine not initialized")),
+        };
+        let mut snapshot = machine
+            .snapshot()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        snapshot.output = output;
+        snapshot.input = input;
+        snapshot
+            .to_json()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     /// Call this after `new` to check why it failed
     pub fn get_error(&self) -> Option<String> {
-        self.error.clone()
+        self.error.as_ref().map(|e| e.to_string())
     }
 
-    pub fn step(&mut self, input: Option<i32>) -> Result<JsValue, JsValue> {
-        if let Some(machine) = &mut self.internal {
-            match machine.step_with_input(input) {
-                Ok(Some(output)) => Ok(JsValue::from_f64(output as f64)),
-                Ok(None) => Ok(JsValue::null()),
-                Err(e) => Err(serde_wasm_bindgen::to_value(&e)?),
-            }
-        } else {
-            Err(JsValue::from_str(
-                self.error.as_deref().unwrap_or("Machine not initialized"),
-            ))
+    /// Structured parse diagnostics (file, line, column, source line and reason)
+    /// for every line that failed to load. Empty if the code was loaded
+    pub fn get_parse_errors(&self) -> Result<JsValue, JsValue> {
+        match &self.error {
+            Some(MepaError::Parse(errors)) => Ok(serde_wasm_bindgen::to_value(errors)?),
+            _ => Ok(serde_wasm_bindgen::to_value(&Vec::<()>::new())?),
+        }
+    }
+
+    /// `input` is the value for a pending LEIT, as a number or a string (needed for
+    /// values beyond the JS safe integers); null or undefined if there is none
+    pub fn step(&mut self, input: JsValue) -> Result<JsValue, JsValue> {
+        match &mut self.internal {
+            Some(cells) => dispatch!(cells, machine => step(machine, &input)),
+            None => Err(self.not_initialized()),
+        }
+    }
+
+    /// Runs at most `max_steps` instructions and returns control to JS, so long
+    /// (or infinite) programs don't hang the page. Stops early at PARA, on errors
+    /// and when a LEIT needs input
+    pub fn run(&mut self, max_steps: u32) -> Result<JsValue, JsValue> {
+        match &mut self.internal {
+            Some(cells) => dispatch!(cells, machine => run(machine, max_steps)),
+            None => Err(self.not_initialized()),
+        }
+    }
+
+    pub fn get_state(&self) -> Result<JsValue, JsValue> {
+        match &self.internal {
+            Some(cells) => dispatch!(cells, machine => Ok(serde_wasm_bindgen::to_value(machine)?)),
+            None => Ok(JsValue::UNDEFINED),
+        }
+    }
+}
+
+impl MepaMachine {
+    fn not_initialized(&self) -> JsValue {
+        JsValue::from_str(
+            &self
+                .get_error()
+                .unwrap_or_else(|| "Machine not initialized".to_owned()),
+        )
+    }
+}
+
+fn load_cells(
+    code: &str,
+    arithmetic: Option<String>,
+    division: Option<String>,
+    cell: Option<&str>,
+) -> MepaResult<Cells> {
+    let arithmetic = Arithmetic::new(
+        arithmetic
+            .map(|mode| mode.parse())
+            .transpose()?
+            .unwrap_or_default(),
+        division
+            .map(|mode| mode.parse())
+            .transpose()?
+            .unwrap_or_default(),
+    );
+    Ok(match cell.unwrap_or("i32") {
+        "i16" => Cells::I16(load(code, arithmetic)?),
+        "i32" => Cells::I32(load(code, arithmetic)?),
+        "i64" => Cells::I64(load(code, arithmetic)?),
+        "big" => Cells::Big(load(code, arithmetic)?),
+        other => {
+            return Err(MepaError::Other(format!(
+                "tipo de célula desconhecido '{}' (use i16, i32, i64 ou big)",
+                other
+            )))
         }
+    })
+}
+
+fn load<C: Cell>(code: &str, arithmetic: Arithmetic) -> MepaResult<BasicMachine<C>> {
+    let mut machine: BasicMachine<C> = code.parse()?;
+    machine.arithmetic = arithmetic;
+    Ok(machine)
+}
+
+// A number or a string with an integer, or nothing (null, undefined)
+fn input_value<C: Cell>(input: &JsValue) -> Result<Option<C>, JsValue> {
+    if input.is_null() || input.is_undefined() {
+        return Ok(None);
     }
+    let text = match input.as_f64() {
+        Some(n) => n.to_string(),
+        None => input.as_string().unwrap_or_default(),
+    };
+    text.parse()
+        .map(Some)
+        .map_err(|_| JsValue::from_str(&format!("Invalid input '{}'", text)))
+}
 
-    pub fn get_state(&self) -> JsValue {
-        serde_wasm_bindgen::to_value(&self.internal).unwrap()
+fn step<C: Cell>(machine: &mut BasicMachine<C>, input: &JsValue) -> Result<JsValue, JsValue> {
+    match machine.step_with_input(input_value(input)?) {
+        Ok(Some(output)) => Ok(serde_wasm_bindgen::to_value(&output)?),
+        Ok(None) => Ok(JsValue::null()),
+        Err(e) => Err(serde_wasm_bindgen::to_value(&e)?),
+    }
+}
+
+fn run<C: Cell>(machine: &mut BasicMachine<C>, max_steps: u32) -> Result<JsValue, JsValue> {
+    let mut result = RunResult {
+        status: RunStatus::BudgetExhausted,
+        steps: 0,
+        outputs: Vec::new(),
+        error: None,
+    };
+    while result.steps < max_steps as usize {
+        if machine.ended() {
+            result.status = RunStatus::Ended;
+            break;
+        }
+        match machine.step_with_input(None) {
+            Ok(output) => result.outputs.extend(output),
+            Err(MepaError::MissingInput(_)) => {
+                result.status = RunStatus::NeedsInput;
+                break;
+            }
+            Err(e) => {
+                result.status = RunStatus::Error;
+                result.error = Some(e);
+                break;
+            }
+        }
+        result.steps += 1;
+    }
+    if machine.ended() && matches!(result.status, RunStatus::BudgetExhausted) {
+        result.status = RunStatus::Ended;
     }
+    Ok(serde_wasm_bindgen::to_value(&result)?)
 }
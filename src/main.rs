@@ -1,12 +1,30 @@
 use mepa_rs::{
-    compiler::{compile, CompileError},
+    backend::{emit_c, emit_wat},
+    compiler::{compile, compile_with_source_map, load_with_debug_info, CompileError, SourceMap},
+    coverage::{self, Coverage},
     evaluator::evaluate,
-    machine,
+    machine::{
+        self, Arithmetic, ArithmeticMode, DivisionMode, ExecutionLimits, TraceFilter, Tracer,
+    },
+    mepa::{
+        cell::{Big, Cell},
+        code::MepaCode,
+        error::{MepaError, MepaResult},
+        syntax::MepaSyntax,
+    },
     otimizador::Otimizador,
+    profiler::{flamegraph, profile},
+    verifier::verify,
 };
 
 use clap::{Arg, Command};
-use std::{env, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env, fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 const DEBUG: bool = false;
 
@@ -22,16 +40,25 @@ fn main() {
                 input_path.file_name().unwrap()
             );
             // Directly call the interactive execution for the given file
-            machine::interactive_execution(&input_path, vec![]);
+            report(machine::interactive_execution(
+                &input_path,
+                vec![],
+                Arithmetic::default(),
+            ));
         } else {
             eprintln!("Error: File not found '{}'", args[1]);
             std::process::exit(1);
         }
     } else if DEBUG {
         let output_path = PathBuf::from("output/test.mepa");
-        compile(&PathBuf::from("samples/ipt/test.ipt"), &output_path, false)
-            .unwrap()
-            .unwrap();
+        compile(
+            &PathBuf::from("samples/ipt/test.ipt"),
+            &output_path,
+            false,
+            false,
+        )
+        .unwrap()
+        .unwrap();
         let mut otm = Otimizador::from(&output_path);
         otm.open_browser_visualization()
             .expect("Falha ao abrir no navegador");
@@ -39,7 +66,11 @@ fn main() {
         otm.open_browser_visualization()
             .expect("Falha ao abrir no navegador");
         otm.save().expect("Falha ao salvar otimizado");
-        machine::interactive_execution(&output_path, vec![]);
+        report(machine::interactive_execution(
+            &output_path,
+            vec![],
+            Arithmetic::default(),
+        ));
     } else {
         // The existing CLI logic
         let matches = Command::new("MepaC")
@@ -47,8 +78,23 @@ fn main() {
             .arg(
                 Arg::new("action")
                     .required(true)
-                    .value_parser(["compile", "run", "debug", "optimize", "evaluate"])
-                    .help("Action to perform (compile, run, debug, optimize or evaluate)"),
+                    .value_parser([
+                        "compile",
+                        "run",
+                        "debug",
+                        "optimize",
+                        "evaluate",
+                        "check",
+                        "profile",
+                        "flamegraph",
+                        "coverage",
+                        "bench",
+                        "emit-c",
+                        "emit-wat",
+                    ])
+                    .help(
+                        "Action to perform (compile, run, debug, optimize, evaluate, check, profile, flamegraph, coverage, bench, emit-c or emit-wat)",
+                    ),
             )
             .arg(
                 Arg::new("input")
@@ -66,6 +112,12 @@ fn main() {
                     .action(clap::ArgAction::SetTrue)
                     .help("Optimize the program after compilation"),
             )
+            .arg(
+                Arg::new("annotate")
+                    .long("annotate")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Write the source lines and variable names as comments in the generated MEPA"),
+            )
             .arg(
                 Arg::new("run")
                     .long("run")
@@ -81,31 +133,190 @@ fn main() {
             .arg(
                 Arg::new("input_values")
                     .long("input")
-                    .help("Comma-separated input values for execution")
+                    .help("Comma-separated input values for execution (for coverage, may be repeated: one run per --input)")
+                    .action(clap::ArgAction::Append)
                     .value_delimiter(','),
             )
+            .arg(
+                Arg::new("max_steps")
+                    .long("max-steps")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Stop execution after this many steps"),
+            )
+            .arg(
+                Arg::new("max_memory")
+                    .long("max-memory")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Maximum size of the stack M"),
+            )
+            .arg(
+                Arg::new("timeout")
+                    .long("timeout")
+                    .value_parser(parse_timeout)
+                    .help("Stop execution after this many seconds"),
+            )
+            .arg(
+                Arg::new("fast")
+                    .long("fast")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Run with the pre-decoded fast interpreter"),
+            )
+            .arg(
+                Arg::new("repeat")
+                    .long("repeat")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("How many times each machine runs the program in the benchmark (default 5)"),
+            )
+            .arg(
+                Arg::new("merge")
+                    .long("merge")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Add the coverage to an existing lcov report instead of replacing it"),
+            )
+            .arg(
+                Arg::new("arithmetic")
+                    .long("arithmetic")
+                    .value_parser(["wrapping", "checked", "saturating"])
+                    .help("What SOMA, SUBT, MULT, INVR and DIVI do on overflow (default wrapping; checked stops with an error)"),
+            )
+            .arg(
+                Arg::new("division")
+                    .long("division")
+                    .value_parser(["euclidean", "truncating", "floored"])
+                    .help("How DIVI rounds the quotient (default euclidean)"),
+            )
+            .arg(
+                Arg::new("cell")
+                    .long("cell")
+                    .value_parser(["i16", "i32", "i64", "big"])
+                    .default_value("i32")
+                    .help("Type of the memory cells and CRCT literals (big is arbitrary precision); only for run"),
+            )
+            .arg(
+                Arg::new("sanitize")
+                    .long("sanitize")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Stop at reads of memory cells never written and at ARMI writes above the stack top"),
+            )
+            .arg(
+                Arg::new("trace")
+                    .long("trace")
+                    .help("Write a trace of the execution (JSON Lines, or CSV if the file ends in .csv)"),
+            )
+            .arg(
+                Arg::new("resume")
+                    .long("resume")
+                    .help("Resume the execution saved in a snapshot file"),
+            )
+            .arg(
+                Arg::new("snapshot_at")
+                    .long("snapshot-at")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Stop after this many steps and save a snapshot (to -o, or output/<name>.json)"),
+            )
+            .arg(
+                Arg::new("trace_instructions")
+                    .long("trace-instructions")
+                    .value_delimiter(',')
+                    .help("Comma-separated instructions to include in the trace, e.g. CHPR,RTPR"),
+            )
+            .arg(
+                Arg::new("trace_range")
+                    .long("trace-range")
+                    .value_parser(parse_range)
+                    .help("Range of instruction addresses to include in the trace, e.g. 10-40"),
+            )
             .get_matches();
 
         let action = matches.get_one::<String>("action").unwrap();
 
+        let resume = matches.get_one::<String>("resume").map(PathBuf::from);
+
+        // ao retomar um snapshot, o arquivo de entrada é opcional
         let input_path = matches
             .get_one::<String>("input")
-            .map(|s| Some(PathBuf::from(s)))
-            .unwrap_or_else(|| None);
+            .map(PathBuf::from)
+            .or_else(|| resume.clone());
 
         let output_path = matches
             .get_one::<String>("output")
             .map(|s| Some(PathBuf::from(s)))
             .unwrap_or_else(|| None);
 
-        let input_values: Vec<i32> = matches
-            .get_many::<String>("input_values")
-            .map(|vals| vals.map(|s| s.parse().unwrap()).collect::<Vec<i32>>())
-            .unwrap_or_else(Vec::new);
+        let cell = matches.get_one::<String>("cell").unwrap().clone();
+        let input_words: Vec<Vec<String>> = matches
+            .get_occurrences::<String>("input_values")
+            .map(|runs| runs.map(|vals| vals.cloned().collect()).collect())
+            .unwrap_or_default();
+        // com outras células, os valores só são convertidos na execução
+        let input_runs: Vec<Vec<i32>> = if cell == "i32" {
+            input_words
+                .iter()
+                .map(|run| run.iter().map(|s| s.parse().unwrap()).collect())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let input_values: Vec<i32> = input_runs.concat();
+
+        let arithmetic_mode = matches
+            .get_one::<String>("arithmetic")
+            .map(|mode| mode.parse::<ArithmeticMode>().unwrap());
+        let division_mode = matches
+            .get_one::<String>("division")
+            .map(|mode| mode.parse::<DivisionMode>().unwrap());
+        // sem nenhuma das duas opções, um snapshot retomado mantém a sua semântica
+        let arithmetic = (arithmetic_mode.is_some() || division_mode.is_some()).then(|| {
+            Arithmetic::new(
+                arithmetic_mode.unwrap_or_default(),
+                division_mode.unwrap_or_default(),
+            )
+        });
 
-        let should_run = *matches.get_one::<bool>("run").unwrap_or(&false);
-        let should_debug = *matches.get_one::<bool>("debug").unwrap_or(&false);
-        let should_optimize = *matches.get_one::<bool>("optimize").unwrap_or(&false);
+        let options = Options {
+            should_run: *matches.get_one::<bool>("run").unwrap_or(&false),
+            should_debug: *matches.get_one::<bool>("debug").unwrap_or(&false),
+            should_optimize: *matches.get_one::<bool>("optimize").unwrap_or(&false),
+            annotate: *matches.get_one::<bool>("annotate").unwrap_or(&false),
+            input_values,
+            input_runs,
+            input_words: input_words.concat(),
+            cell,
+            merge: *matches.get_one::<bool>("merge").unwrap_or(&false),
+            fast: *matches.get_one::<bool>("fast").unwrap_or(&false),
+            sanitize: *matches.get_one::<bool>("sanitize").unwrap_or(&false),
+            repeat: *matches.get_one::<usize>("repeat").unwrap_or(&5),
+            arithmetic,
+            limits: ExecutionLimits {
+                max_steps: matches.get_one::<usize>("max_steps").copied(),
+                max_memory: matches.get_one::<usize>("max_memory").copied(),
+                timeout: matches.get_one::<Duration>("timeout").copied(),
+            },
+            trace: matches.get_one::<String>("trace").map(PathBuf::from),
+            resume,
+            snapshot_at: matches.get_one::<usize>("snapshot_at").copied(),
+            trace_filter: TraceFilter {
+                instructions: matches
+                    .get_many::<String>("trace_instructions")
+                    .map(|vals| vals.cloned().collect()),
+                addresses: matches
+                    .get_one::<RangeInclusive<usize>>("trace_range")
+                    .cloned(),
+            },
+        };
+
+        let cell_supported = action == "run" || (action == "compile" && !options.should_debug);
+        if options.cell != "i32" && !cell_supported {
+            eprintln!("Error: --cell is only supported by 'run' and 'compile --run'.");
+            std::process::exit(1);
+        }
+        if options.arithmetic.is_some() && (action == "emit-c" || action == "emit-wat") {
+            eprintln!(
+                "Error: --arithmetic and --division are not supported by '{}'; the generated code always wraps and uses euclidean division.",
+                action
+            );
+            std::process::exit(1);
+        }
 
         if let Some(input_path) = input_path {
             // Handle directory or file input
@@ -126,15 +337,7 @@ fn main() {
                                 p
                             }
                         };
-                        handle_action(
-                            action,
-                            &file_path,
-                            &p,
-                            should_run,
-                            should_debug,
-                            should_optimize,
-                            &input_values,
-                        );
+                        handle_action(action, &file_path, &p, &options);
                     }
                 }
             } else {
@@ -149,15 +352,7 @@ fn main() {
                         p
                     }
                 };
-                handle_action(
-                    action,
-                    &input_path,
-                    &p,
-                    should_run,
-                    should_debug,
-                    should_optimize,
-                    &input_values,
-                );
+                handle_action(action, &input_path, &p, &options);
             }
         } else if action != "evaluate" {
             eprintln!("Error: The 'input' argument is required for '{}'.", action);
@@ -168,15 +363,187 @@ fn main() {
     }
 }
 
-fn handle_action(
-    action: &str,
-    input_path: &PathBuf,
-    output_path: &PathBuf,
+// Opções da linha de comando repassadas para cada ação
+struct Options {
     should_run: bool,
     should_debug: bool,
     should_optimize: bool,
-    input_values: &[i32],
-) {
+    annotate: bool,
+    input_values: Vec<i32>,
+    // cada --input separadamente, para a cobertura
+    input_runs: Vec<Vec<i32>>,
+    // --input sem conversão, para células que não são i32
+    input_words: Vec<String>,
+    cell: String,
+    merge: bool,
+    fast: bool,
+    sanitize: bool,
+    repeat: usize,
+    // `None` quando nem --arithmetic nem --division foram dados
+    arithmetic: Option<Arithmetic>,
+    limits: ExecutionLimits,
+    trace: Option<PathBuf>,
+    resume: Option<PathBuf>,
+    snapshot_at: Option<usize>,
+    trace_filter: TraceFilter,
+}
+
+impl Options {
+    // `output_path` é onde o snapshot de --snapshot-at é salvo, trocando a extensão.
+    // Com as informações de depuração do compilador, os erros do sanitizador
+    // mostram o nome da variável
+    fn run(&self, path: &PathBuf, output_path: &Path) -> MepaResult<()> {
+        match self.run_program(path, output_path) {
+            Err(e) if self.sanitize => match load_with_debug_info(path) {
+                Ok((_, Some(source_map))) => Err(source_map.name_variable(e)),
+                _ => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    fn run_program(&self, path: &PathBuf, output_path: &Path) -> MepaResult<()> {
+        if self.cell != "i32" {
+            return match self.cell.as_str() {
+                "i16" => self.run_cell::<i16>(path),
+                "i64" => self.run_cell::<i64>(path),
+                _ => self.run_cell::<Big>(path),
+            };
+        }
+        if self.fast {
+            let unsupported = if self.trace.is_some() {
+                Some("--trace")
+            } else if self.sanitize {
+                Some("--sanitize")
+            } else if self.resume.is_some() {
+                Some("--resume")
+            } else if self.snapshot_at.is_some() {
+                Some("--snapshot-at")
+            } else {
+                None
+            };
+            if let Some(option) = unsupported {
+                return Err(MepaError::Other(format!(
+                    "{} não pode ser usado com --fast",
+                    option
+                )));
+            }
+            return machine::execute_fast(
+                path,
+                self.input_values.clone(),
+                self.limits,
+                self.arithmetic.unwrap_or_default(),
+            );
+        }
+        let tracer = match &self.trace {
+            Some(trace) => Some(Tracer::to_file(trace)?.with_filter(self.trace_filter.clone())),
+            None => None,
+        };
+        if self.resume.is_none() && self.snapshot_at.is_none() {
+            return machine::execute(
+                path,
+                self.input_values.clone(),
+                None,
+                self.limits,
+                tracer,
+                self.arithmetic.unwrap_or_default(),
+                self.sanitize,
+            );
+        }
+        if self.sanitize {
+            return Err(MepaError::Other(format!(
+                "--sanitize não pode ser usado com {}",
+                if self.resume.is_some() {
+                    "--resume"
+                } else {
+                    "--snapshot-at"
+                }
+            )));
+        }
+        let snapshot_path = if output_path.extension().is_some_and(|e| e == "json") {
+            output_path.to_path_buf()
+        } else {
+            output_path.with_extension("json")
+        };
+        machine::execute_with_snapshot(
+            self.resume.as_ref().unwrap_or(path),
+            self.resume.is_some(),
+            self.input_values.clone(),
+            self.limits,
+            tracer,
+            self.arithmetic,
+            self.snapshot_at.map(|step| (step, snapshot_path)),
+        )
+    }
+
+    // Execução com outro tipo de célula, só com a FullMachine e sem snapshots
+    fn run_cell<C: Cell>(&self, path: &PathBuf) -> MepaResult<()> {
+        let unsupported = if self.fast {
+            Some("--fast")
+        } else if self.resume.is_some() {
+            Some("--resume")
+        } else if self.snapshot_at.is_some() {
+            Some("--snapshot-at")
+        } else {
+            None
+        };
+        if let Some(option) = unsupported {
+            return Err(MepaError::Other(format!(
+                "{} só pode ser usado com --cell i32",
+                option
+            )));
+        }
+        let input = self
+            .input_words
+            .iter()
+            .map(|word| {
+                word.parse::<C>().map_err(|_| {
+                    MepaError::Other(format!(
+                        "valor de entrada inválido '{}' para células {}",
+                        word, self.cell
+                    ))
+                })
+            })
+            .collect::<MepaResult<Vec<C>>>()?;
+        let tracer = match &self.trace {
+            Some(trace) => Some(Tracer::to_file(trace)?.with_filter(self.trace_filter.clone())),
+            None => None,
+        };
+        machine::execute(
+            path,
+            input,
+            None,
+            self.limits,
+            tracer,
+            self.arithmetic.unwrap_or_default(),
+            self.sanitize,
+        )
+    }
+}
+
+// "10-40" ou apenas "10"
+fn parse_range(arg: &str) -> Result<RangeInclusive<usize>, String> {
+    let bound = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid address '{}'", n))
+    };
+    match arg.split_once('-') {
+        Some((start, end)) => Ok(bound(start)?..=bound(end)?),
+        None => bound(arg).map(|n| n..=n),
+    }
+}
+
+// segundos, possivelmente fracionários: "2" ou "0.5"
+fn parse_timeout(arg: &str) -> Result<Duration, String> {
+    arg.trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid number of seconds '{}'", arg))
+}
+
+fn handle_action(action: &str, input_path: &PathBuf, output_path: &PathBuf, options: &Options) {
     match action {
         "compile" => {
             let output = if output_path.is_dir() {
@@ -186,14 +553,27 @@ fn handle_action(
             } else {
                 output_path.clone()
             };
+            if options.annotate && options.should_optimize {
+                eprintln!("--annotate não pode ser usado com --optimize");
+                std::process::exit(1);
+            }
             println!("compilando {:?}", input_path.file_name().unwrap());
-            match compile(input_path, &output, should_optimize) {
+            match compile(
+                input_path,
+                &output,
+                options.should_optimize,
+                options.annotate,
+            ) {
                 Ok(r) => match r {
                     Ok(_) => {
-                        if should_debug {
-                            machine::interactive_execution(&output_path, input_values.to_vec());
-                        } else if should_run {
-                            machine::execute(&output_path, input_values.to_vec(), None);
+                        if options.should_debug {
+                            report(machine::interactive_execution(
+                                &output_path,
+                                options.input_values.clone(),
+                                options.arithmetic.unwrap_or_default(),
+                            ));
+                        } else if options.should_run {
+                            report(options.run(output_path, output_path));
                         }
                     }
                     Err(e) => println!("Erro de IO: {:?}", e),
@@ -206,18 +586,236 @@ fn handle_action(
             }
         }
         "optimize" => {
-            Otimizador::from(input_path)
-                .otimizar()
-                .expect("Não foi possível otimizar o arquivo")
-                .save()
-                .expect("Erro ao salvar arquivo otimizado");
+            let otimizador = MepaSyntax::from_file(input_path)
+                .and_then(|syntax| Otimizador::from_syntax(syntax, Some(input_path)));
+            match otimizador {
+                Ok(otimizador) => otimizador
+                    .otimizar()
+                    .expect("Não foi possível otimizar o arquivo")
+                    .save()
+                    .expect("Erro ao salvar arquivo otimizado"),
+                Err(e) => report(Err(e)),
+            }
         }
         "run" => {
-            machine::execute(input_path, input_values.to_vec(), None);
+            report(options.run(input_path, output_path));
+        }
+        "profile" => {
+            let result = load_with_debug_info(input_path).and_then(|(code, source_map)| {
+                let mut profile = profile(code, options.input_values.clone(), options.limits)?;
+                if let Some(source_map) = source_map {
+                    profile.rename_procedures(&source_map.function_names());
+                }
+                Ok(profile)
+            });
+            match result {
+                Ok(profile) => {
+                    println!("Saída: {:?}\n", profile.output);
+                    println!("{}", profile.summary());
+                    print!("{}", profile.listing());
+                    if let Some(e) = profile.error {
+                        report(Err(e));
+                    }
+                }
+                Err(e) => report(Err(e)),
+            }
+        }
+        "flamegraph" => {
+            let program = if input_path.extension().is_some_and(|e| e == "ipt") {
+                match compile_with_source_map(input_path) {
+                    Ok((code, source_map)) => (code, source_map.function_names()),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                match load_with_debug_info(input_path) {
+                    Ok((code, source_map)) => (
+                        code,
+                        source_map.map_or_else(HashMap::new, |map| map.function_names()),
+                    ),
+                    Err(e) => return report(Err(e)),
+                }
+            };
+            let (code, names) = program;
+            match profile(code, options.input_values.clone(), options.limits) {
+                Ok(mut profile) => {
+                    profile.rename_procedures(&names);
+                    let svg = output_path.extension().is_some_and(|e| e == "svg");
+                    let (path, content) = if svg {
+                        let title = input_path.file_name().unwrap().to_string_lossy();
+                        (output_path.clone(), flamegraph::svg(&profile, &title))
+                    } else {
+                        (
+                            output_path.with_extension("folded"),
+                            flamegraph::folded(&profile),
+                        )
+                    };
+                    if let Some(dir) = path.parent() {
+                        let _ = fs::create_dir_all(dir);
+                    }
+                    match fs::write(&path, content) {
+                        Ok(_) => println!("Flamegraph salvo em {}", path.display()),
+                        Err(e) => report(Err(e.into())),
+                    }
+                    if let Some(e) = profile.error {
+                        report(Err(e));
+                    }
+                }
+                Err(e) => report(Err(e)),
+            }
+        }
+        "coverage" => {
+            // código MEPA só serve com as informações de depuração gravadas pelo compile
+            let program = if input_path.extension().is_some_and(|e| e == "mepa") {
+                match load_with_debug_info(input_path) {
+                    Ok((code, Some(source_map))) => Ok((code, source_map)),
+                    Ok((_, None)) => Err(format!(
+                        "{}: faltam as informações de depuração ({}) deste código, geradas por 'compile' sem --optimize",
+                        input_path.display(),
+                        SourceMap::sidecar_path(input_path).display()
+                    )),
+                    Err(e) => Err(e.to_string()),
+                }
+            } else {
+                compile_with_source_map(input_path).map_err(|e| e.to_string())
+            };
+            let (code, source_map) = match program {
+                Ok(program) => program,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let source = source_map
+                .source
+                .clone()
+                .unwrap_or_else(|| input_path.display().to_string());
+            let lcov_path = output_path.with_extension("lcov");
+            let previous: Option<Coverage> = if options.merge && lcov_path.exists() {
+                match fs::read_to_string(&lcov_path).map(|text| Coverage::from_lcov(&text)) {
+                    Ok(Ok(previous)) => Some(previous),
+                    Ok(Err(e)) => {
+                        eprintln!("{}: {}", lcov_path.display(), e);
+                        std::process::exit(1);
+                    }
+                    Err(e) => return report(Err(e.into())),
+                }
+            } else {
+                None
+            };
+            let runs = if options.input_runs.is_empty() {
+                vec![vec![]]
+            } else {
+                options.input_runs.clone()
+            };
+            let mut total: Option<Coverage> = None;
+            for input in runs {
+                let result =
+                    coverage::run(code.clone(), &source_map, &source, input, options.limits);
+                match (result, &mut total) {
+                    (Ok(run), Some(total)) => total.merge(&run),
+                    (Ok(run), None) => total = Some(run),
+                    (Err(e), _) => report(Err(e)),
+                }
+            }
+            if let Some(mut total) = total {
+                // a execução atual vem primeiro, pois sabe onde cada função termina
+                if let Some(previous) = previous {
+                    total.merge(&previous);
+                }
+                print!("{}", total.summary());
+                if let Some(dir) = lcov_path.parent() {
+                    let _ = fs::create_dir_all(dir);
+                }
+                match fs::write(&lcov_path, total.to_lcov()) {
+                    Ok(_) => println!("Relatório lcov salvo em {}", lcov_path.display()),
+                    Err(e) => report(Err(e.into())),
+                }
+            }
         }
+        "bench" => {
+            let result = MepaCode::from_file(input_path).and_then(|code| {
+                machine::benchmark(code, options.input_values.clone(), options.repeat)
+            });
+            match result {
+                Ok(results) => {
+                    let base = results[0].time.as_secs_f64();
+                    println!(
+                        "{:<32} {:>12} {:>12} {:>9}",
+                        "máquina", "passos", "tempo (ms)", "ganho"
+                    );
+                    for r in &results {
+                        println!(
+                            "{:<32} {:>12} {:>12.3} {:>8.2}x",
+                            r.name,
+                            r.steps,
+                            r.time.as_secs_f64() * 1000.0,
+                            base / r.time.as_secs_f64()
+                        );
+                    }
+                    if results
+                        .iter()
+                        .any(|r| r.output != results[0].output || r.steps != results[0].steps)
+                    {
+                        eprintln!("As máquinas divergiram na saída ou no número de passos!");
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => report(Err(e)),
+            }
+        }
+        "emit-c" | "emit-wat" => {
+            let (extension, emit): (_, fn(MepaCode) -> MepaResult<String>) = match action {
+                "emit-c" => ("c", emit_c),
+                _ => ("wat", emit_wat),
+            };
+            let path = output_path.with_extension(extension);
+            let result = MepaCode::from_file(input_path).and_then(emit);
+            match result {
+                Ok(code) => {
+                    if let Some(dir) = path.parent() {
+                        let _ = fs::create_dir_all(dir);
+                    }
+                    match fs::write(&path, code) {
+                        Ok(_) => println!("Código salvo em {}", path.display()),
+                        Err(e) => report(Err(e.into())),
+                    }
+                }
+                Err(e) => report(Err(e)),
+            }
+        }
+        "check" => match MepaCode::from_file(input_path).and_then(verify) {
+            Ok(erros) if erros.is_empty() => {
+                println!(
+                    "{:?}: nenhum problema encontrado",
+                    input_path.file_name().unwrap()
+                )
+            }
+            Ok(erros) => {
+                for e in &erros {
+                    eprintln!("{}: {}", input_path.display(), e);
+                }
+                std::process::exit(1);
+            }
+            Err(e) => report(Err(e)),
+        },
         "debug" => {
-            machine::interactive_execution(input_path, input_values.to_vec());
+            report(machine::interactive_execution(
+                input_path,
+                options.input_values.clone(),
+                options.arithmetic.unwrap_or_default(),
+            ));
         }
         _ => unreachable!(),
     }
 }
+
+// Mostra o erro de uma execução e encerra o processo
+fn report(result: MepaResult<()>) {
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::machine::{Arithmetic, ArithmeticMode, DivisionMode};
+
+use num_integer::Integer;
+use num_traits::{Euclid, Signed, ToPrimitive, Zero};
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Tipo de uma posição de `M` e do literal de `CRCT`.
+/// Endereços, níveis e deslocamentos continuam sendo `i32`.
+pub trait Cell:
+    Clone + Default + Ord + fmt::Debug + fmt::Display + FromStr + Serialize + DeserializeOwned + 'static
+{
+    /// Nome do tipo, como em `--cell`
+    const NAME: &'static str;
+
+    /// `None` se o valor não cabe na célula
+    fn from_i32(value: i32) -> Option<Self>;
+    /// O valor como `i32`, saturando nos limites, para usá-lo como endereço
+    fn to_i32_saturating(&self) -> i32;
+    fn from_bool(value: bool) -> Self;
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // As operações devolvem `None` quando há overflow no modo `Checked`
+
+    fn add(&self, other: &Self, mode: ArithmeticMode) -> Option<Self>;
+    fn sub(&self, other: &Self, mode: ArithmeticMode) -> Option<Self>;
+    fn mul(&self, other: &Self, mode: ArithmeticMode) -> Option<Self>;
+    fn neg(&self, mode: ArithmeticMode) -> Option<Self>;
+    /// Quociente por `other`, que não pode ser zero
+    fn div(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self>;
+}
+
+macro_rules! primitive_cell {
+    ($($t:ty),*) => {$(
+        impl Cell for $t {
+            const NAME: &'static str = stringify!($t);
+
+            #[inline]
+            fn from_i32(value: i32) -> Option<Self> {
+                <$t>::try_from(value).ok()
+            }
+            #[inline]
+            fn to_i32_saturating(&self) -> i32 {
+                i32::try_from(*self).unwrap_or(if *self < 0 { i32::MIN } else { i32::MAX })
+            }
+            #[inline]
+            fn from_bool(value: bool) -> Self {
+                value as $t
+            }
+            #[inline]
+            fn add(&self, other: &Self, mode: ArithmeticMode) -> Option<Self> {
+                match mode {
+                    ArithmeticMode::Wrapping => Some(self.wrapping_add(*other)),
+                    ArithmeticMode::Checked => self.checked_add(*other),
+                    ArithmeticMode::Saturating => Some(self.saturating_add(*other)),
+                }
+            }
+            #[inline]
+            fn sub(&self, other: &Self, mode: ArithmeticMode) -> Option<Self> {
+                match mode {
+                    ArithmeticMode::Wrapping => Some(self.wrapping_sub(*other)),
+                    ArithmeticMode::Checked => self.checked_sub(*other),
+                    ArithmeticMode::Saturating => Some(self.saturating_sub(*other)),
+                }
+            }
+            #[inline]
+            fn mul(&self, other: &Self, mode: ArithmeticMode) -> Option<Self> {
+                match mode {
+                    ArithmeticMode::Wrapping => Some(self.wrapping_mul(*other)),
+                    ArithmeticMode::Checked => self.checked_mul(*other),
+                    ArithmeticMode::Saturating => Some(self.saturating_mul(*other)),
+                }
+            }
+            #[inline]
+            fn neg(&self, mode: ArithmeticMode) -> Option<Self> {
+                match mode {
+                    ArithmeticMode::Wrapping => Some(self.wrapping_neg()),
+                    ArithmeticMode::Checked => self.checked_neg(),
+                    ArithmeticMode::Saturating => Some(self.saturating_neg()),
+                }
+            }
+            #[inline]
+            fn div(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+                let (a, b) = (*self, *other);
+                // em qualquer arredondamento, o único quociente que não cabe é MIN / -1
+                if a == <$t>::MIN && b == -1 {
+                    return match arithmetic.mode {
+                        ArithmeticMode::Wrapping => Some(<$t>::MIN),
+                        ArithmeticMode::Checked => None,
+                        ArithmeticMode::Saturating => Some(<$t>::MAX),
+                    };
+                }
+                Some(match arithmetic.division {
+                    DivisionMode::Euclidean => a.div_euclid(b),
+                    DivisionMode::Truncating => a / b,
+                    DivisionMode::Floored => {
+                        let q = a / b;
+                        if a % b != 0 && (a < 0) != (b < 0) {
+                            q - 1
+                        } else {
+                            q
+                        }
+                    }
+                })
+            }
+        }
+    )*};
+}
+
+primitive_cell!(i16, i32, i64);
+
+/// Inteiro de precisão arbitrária, que nunca sofre overflow.
+/// Em JSON é escrito como string, para não perder precisão.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Big(pub num_bigint::BigInt);
+
+impl fmt::Display for Big {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Big {
+    type Err = num_bigint::ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Big)
+    }
+}
+
+impl Serialize for Big {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Big {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BigVisitor;
+
+        impl Visitor<'_> for BigVisitor {
+            type Value = Big;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("um inteiro ou uma string com um inteiro")
+            }
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Big, E> {
+                Ok(Big(v.into()))
+            }
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Big, E> {
+                Ok(Big(v.into()))
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Big, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(BigVisitor)
+    }
+}
+
+impl Cell for Big {
+    const NAME: &'static str = "big";
+
+    fn from_i32(value: i32) -> Option<Self> {
+        Some(Big(value.into()))
+    }
+    fn to_i32_saturating(&self) -> i32 {
+        self.0.to_i32().unwrap_or(if self.0.is_negative() {
+            i32::MIN
+        } else {
+            i32::MAX
+        })
+    }
+    fn from_bool(value: bool) -> Self {
+        Big((value as i32).into())
+    }
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+    // sem overflow, os modos aritméticos não fazem diferença
+    fn add(&self, other: &Self, _: ArithmeticMode) -> Option<Self> {
+        Some(Big(&self.0 + &other.0))
+    }
+    fn sub(&self, other: &Self, _: ArithmeticMode) -> Option<Self> {
+        Some(Big(&self.0 - &other.0))
+    }
+    fn mul(&self, other: &Self, _: ArithmeticMode) -> Option<Self> {
+        Some(Big(&self.0 * &other.0))
+    }
+    fn neg(&self, _: ArithmeticMode) -> Option<Self> {
+        Some(Big(-&self.0))
+    }
+    fn div(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+        Some(Big(match arithmetic.division {
+            DivisionMode::Euclidean => self.0.div_euclid(&other.0),
+            DivisionMode::Truncating => &self.0 / &other.0,
+            DivisionMode::Floored => self.0.div_floor(&other.0),
+        }))
+    }
+}
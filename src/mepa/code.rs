@@ -1,30 +1,35 @@
-use super::{instruction::Instruction, label::Label};
-use crate::utils::{matrix_to_string, write_matrix};
-use std::fs::{self, File};
-use std::io::{self, BufRead};
+use super::error::{AssemblyError, AssemblyErrorKind, MepaError, MepaResult};
+use super::syntax::{comment_start, MepaSyntax};
+use super::{cell::Cell, instruction::Instruction, label::Label};
+use crate::utils::matrix_to_string;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
+use std::str::FromStr;
 
+/// Programa MEPA com células do tipo `C` (ver `Instruction`)
 #[derive(Clone, Debug)]
-pub struct MepaCode(pub Vec<(Option<Label>, Instruction)>);
+pub struct MepaCode<C = i32>(pub Vec<(Option<Label>, Instruction<C>)>);
 
-impl Deref for MepaCode {
-    type Target = [(Option<Label>, Instruction)];
+impl<C> Deref for MepaCode<C> {
+    type Target = [(Option<Label>, Instruction<C>)];
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for MepaCode {
+impl<C> DerefMut for MepaCode<C> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl<T> From<T> for MepaCode
+impl<C, T> From<T> for MepaCode<C>
 where
-    T: IntoIterator<Item = Instruction>,
+    T: IntoIterator<Item = Instruction<C>>,
 {
     fn from(instructions: T) -> Self {
         // Map each instruction to a tuple with `None` as the label
@@ -37,77 +42,139 @@ where
     }
 }
 
-impl MepaCode {
-    pub fn with_capacity(capacity: usize) -> MepaCode {
+impl<C: Cell> FromStr for MepaCode<C> {
+    type Err = MepaError;
+
+    fn from_str(input: &str) -> MepaResult<Self> {
+        Self::parse_source(input, None)
+    }
+}
+
+impl<C: Cell> MepaCode<C> {
+    pub fn with_capacity(capacity: usize) -> Self {
         MepaCode(Vec::with_capacity(capacity))
     }
-    pub fn insert(&mut self, new: (Option<Label>, Instruction)) {
+    pub fn insert(&mut self, new: (Option<Label>, Instruction<C>)) {
         //println!("{:?}",new);
         self.0.push(new);
     }
 
-    pub fn from_file<P>(filename: P) -> io::Result<MepaCode>
+    pub fn from_file<P>(filename: P) -> MepaResult<Self>
     where
         P: AsRef<Path>,
     {
-        let delimiters = [',', ' ', '\t', ';', ':'];
-        let file = File::open(&filename)?;
-        let mut instruction_count = 0;
-        for _ in io::BufReader::new(&file).lines().flatten() {
-            instruction_count += 1;
-        }
-
-        let mut mc = MepaCode(Vec::with_capacity(instruction_count));
+        let source = fs::read_to_string(&filename)?;
+        Self::parse_source(&source, Some(filename.as_ref().display().to_string()))
+    }
 
-        let file = File::open(&filename)?;
-        for line in io::BufReader::new(file).lines().flatten() {
-            let without_comments = &line[..line
-                .find('#')
-                .unwrap_or(line.len())
-                .min(line.find("//").unwrap_or(line.len()))];
+    // Interpreta todas as linhas, acumulando os erros em vez de parar no primeiro
+    fn parse_source(source: &str, file: Option<String>) -> MepaResult<Self> {
+        MepaSyntax::parse_source(source, file).map(|syntax| syntax.code)
+    }
 
-            let line: Vec<&str> = without_comments
-                .split(|c| delimiters.contains(&c))
-                .filter(|s| !s.is_empty()) // To remove empty strings
-                .collect();
+    /// Mapeia cada rótulo simbólico para o endereço da instrução que ele marca
+    pub fn label_table(&self) -> Result<HashMap<String, usize>, Vec<AssemblyError>> {
+        let (table, errors) = self.build_label_table();
+        if errors.is_empty() {
+            Ok(table)
+        } else {
+            Err(errors)
+        }
+    }
 
-            if line.len() > 0 {
-                match Instruction::parse(&line) {
-                    Ok(value) => mc.insert(value),
-                    Err(_) => panic!("Failed to interpret line {:?}", line),
+    // Em caso de rótulo duplicado, a tabela fica com a primeira definição
+    fn build_label_table(&self) -> (HashMap<String, usize>, Vec<AssemblyError>) {
+        let mut table = HashMap::new();
+        let mut errors = Vec::new();
+        for (address, (label, _)) in self.0.iter().enumerate() {
+            if let Some(Label::Simbolic(s)) = label {
+                if let Some(first) = table.get(s) {
+                    errors.push(AssemblyError {
+                        address,
+                        kind: AssemblyErrorKind::DuplicateLabel(s.clone(), *first),
+                    });
+                } else {
+                    table.insert(s.clone(), address);
                 }
             }
         }
-
-        Ok(mc)
+        (table, errors)
     }
 
-    pub fn from_str(input: &str) -> io::Result<MepaCode> {
-        let delimiters = [',', ' ', '\t', ';', ':'];
-
-        let instruction_count = input.lines().count();
-        let mut mc = MepaCode(Vec::with_capacity(instruction_count));
-
-        for line in input.lines() {
-            let without_comments = &line[..line
-                .find('#')
-                .unwrap_or(line.len())
-                .min(line.find("//").unwrap_or(line.len()))];
-
-            let tokens: Vec<&str> = without_comments
-                .split(|c| delimiters.contains(&c))
-                .filter(|s| !s.is_empty())
-                .collect();
+    /// Resolve todos os desvios (DSVS, DSVF, CHPR) para endereços literais, validando
+    /// rótulos duplicados, indefinidos e destinos fora do programa, e recusa
+    /// `AMEM`, `DMEM` e `RTPR` com quantidade negativa.
+    /// Os rótulos das linhas são mantidos, apenas os argumentos são substituídos.
+    pub fn assemble(mut self) -> MepaResult<Self> {
+        let (table, mut errors) = self.build_label_table();
+        let len = self.0.len();
 
-            if !tokens.is_empty() {
-                match Instruction::parse(&tokens) {
-                    Ok(instr) => mc.insert(instr),
-                    Err(_) => panic!("Failed to interpret line {:?}", tokens),
+        for (address, (_, instruction)) in self.0.iter_mut().enumerate() {
+            if let Instruction::AMEM(n) | Instruction::DMEM(n) | Instruction::RTPR(_, n) =
+                *instruction
+            {
+                if n < 0 {
+                    errors.push(AssemblyError {
+                        address,
+                        kind: AssemblyErrorKind::NegativeAmount(
+                            instruction.to_string_vec().remove(0),
+                            n,
+                        ),
+                    });
                 }
             }
+            if let Instruction::DSVS(label) | Instruction::DSVF(label) | Instruction::CHPR(label) =
+                instruction
+            {
+                match label {
+                    Label::Simbolic(s) => match table.get(s) {
+                        Some(target) => *label = Label::Literal(*target),
+                        None => errors.push(AssemblyError {
+                            address,
+                            kind: AssemblyErrorKind::UndefinedLabel(s.clone()),
+                        }),
+                    },
+                    Label::Literal(target) => {
+                        if *target >= len {
+                            errors.push(AssemblyError {
+                                address,
+                                kind: AssemblyErrorKind::TargetOutOfRange(*target),
+                            })
+                        }
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            errors.sort_by_key(|e| e.address);
+            Err(MepaError::Assembly(errors))
         }
+    }
+
+    /// Se todos os desvios já apontam para endereços literais
+    pub fn is_resolved(&self) -> bool {
+        self.0.iter().all(|(_, instruction)| match instruction {
+            Instruction::DSVS(label) | Instruction::DSVF(label) | Instruction::CHPR(label) => {
+                matches!(label, Label::Literal(_))
+            }
+            _ => true,
+        })
+    }
 
-        Ok(mc)
+    /// Quantos níveis léxicos `D` precisa ter: o maior nível usado no código mais um,
+    /// e pelo menos 2 (o programa principal e seus procedimentos)
+    pub fn lexical_levels(&self) -> usize {
+        self.0
+            .iter()
+            .filter_map(|(_, instruction)| instruction.level())
+            .filter(|k| *k >= 0)
+            .map(|k| k as usize + 1)
+            .max()
+            .unwrap_or(0)
+            .max(2)
     }
 
     pub fn remove_instruction(&mut self, index: usize) {
@@ -129,6 +196,8 @@ impl MepaCode {
         }
     }
 
+    /// Grava o código na sintaxe de `to_string`. Para manter os comentários e o
+    /// alinhamento de um arquivo lido, use `MepaSyntax`.
     pub fn to_file<P>(self, filename: P) -> io::Result<()>
     where
         P: AsRef<Path>,
@@ -136,52 +205,199 @@ impl MepaCode {
         if let Some(parent) = filename.as_ref().parent() {
             fs::create_dir_all(parent)?;
         }
+        fs::write(filename, self.to_string()?)
+    }
 
-        // Create or open the file
-        let file = File::create(filename)?;
+    /// O código na sintaxe canônica: rótulos seguidos de `:` e colunas alinhadas
+    pub fn to_string(&self) -> io::Result<String> {
+        let mut text = self.canonical_lines().join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        Ok(text)
+    }
 
-        // Write each string to the file, separated by newlines
+    // Uma linha por instrução, sem espaços no fim
+    pub(super) fn canonical_lines(&self) -> Vec<String> {
         let matrix: Vec<Vec<String>> = self
             .0
-            .into_iter()
-            .map(|line| {
+            .iter()
+            .map(|(label, instruction)| {
                 let mut v = Vec::with_capacity(5);
-                v.push(if let Some(label) = line.0 {
-                    format!("{}", label)
-                } else {
-                    "   ".to_string()
+                // a coluna dos rótulos tem pelo menos 3 caracteres, para recuar as instruções
+                v.push(match label {
+                    Some(label) => format!("{}:", label),
+                    None => "   ".to_string(),
                 });
-                v.append(&mut line.1.to_string_vec());
+                v.append(&mut instruction.to_string_vec());
                 v
             })
             .collect();
 
-        write_matrix(&matrix, file)
+        matrix_to_string(&matrix)
+            .lines()
+            .map(|line| line.trim_end().to_owned())
+            .collect()
     }
+}
 
-    pub fn to_string(&self) -> io::Result<String> {
-        // First, transform the internal vector of instructions into a
-        // matrix of strings, similar to the `to_file` method.
-        let matrix: Vec<Vec<String>> = self
-            .clone()
-            .0
-            .into_iter()
-            .map(|line| {
-                let mut v = Vec::with_capacity(5);
-                // Add the label to the row, or a placeholder if it's absent.
-                v.push(if let Some(label) = line.0 {
-                    format!("{}:", label) // Using a colon for standard assembly style
-                } else {
-                    String::new() // Using an empty string for better alignment
-                });
-                // Add the string parts of the instruction itself.
-                v.append(&mut line.1.to_string_vec());
-                v
-            })
-            .collect();
+// Separa a linha em tokens, ignorando comentários.
+// Cada token vem acompanhado da sua coluna (começando em 1) na linha original
+pub(super) fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let delimiters = [',', ' ', '\t', ';', ':'];
+    let without_comments = &line[..comment_start(line).unwrap_or(line.len())];
+
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in without_comments.char_indices() {
+        if delimiters.contains(&c) {
+            if let Some(s) = start.take() {
+                tokens.push((s, &without_comments[s..i]));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &without_comments[s..]));
+    }
+
+    tokens
+        .into_iter()
+        .map(|(byte, token)| (line[..byte].chars().count() + 1, token))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mepa::error::{MepaParseError, ParseErrorKind};
+
+    fn parse_errors(source: &str) -> Vec<MepaParseError> {
+        match MepaCode::<i32>::from_str(source) {
+            Err(MepaError::Parse(errors)) => errors,
+            other => panic!("esperava erro de sintaxe, veio {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_instruction() {
+        let errors = parse_errors("    INPP\n    CRTC 1\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 5));
+        assert!(matches!(&errors[0].kind, ParseErrorKind::UnknownInstruction(s) if s == "CRTC"));
+    }
+
+    #[test]
+    fn unknown_instruction_after_label() {
+        let errors = parse_errors("L1: FOO\n");
+        assert_eq!((errors[0].line, errors[0].column), (1, 5));
+        assert!(matches!(&errors[0].kind, ParseErrorKind::UnknownInstruction(s) if s == "FOO"));
+    }
+
+    #[test]
+    fn missing_argument() {
+        let errors = parse_errors("  AMEM\n");
+        assert_eq!((errors[0].line, errors[0].column), (1, 3));
+        assert!(matches!(&errors[0].kind, ParseErrorKind::MissingArgument(s) if s == "AMEM"));
+    }
+
+    #[test]
+    fn invalid_argument() {
+        let errors = parse_errors("CRVL 1, x\n");
+        assert_eq!((errors[0].line, errors[0].column), (1, 9));
+        assert!(matches!(&errors[0].kind, ParseErrorKind::InvalidArgument(s) if s == "x"));
+    }
+
+    #[test]
+    fn argument_out_of_range() {
+        let errors = match MepaCode::<i16>::from_str("CRCT 2147483647\nCRVL 0, 3000000000\n") {
+            Err(MepaError::Parse(errors)) => errors,
+            other => panic!("esperava erro de sintaxe, veio {:?}", other),
+        };
+        assert_eq!((errors[0].line, errors[0].column), (1, 6));
+        assert!(matches!(&errors[0].kind,
+            ParseErrorKind::OutOfRange(s, t) if s == "2147483647" && t == "i16"));
+        assert_eq!((errors[1].line, errors[1].column), (2, 9));
+        assert!(matches!(&errors[1].kind,
+            ParseErrorKind::OutOfRange(s, t) if s == "3000000000" && t == "i32"));
+    }
+
+    #[test]
+    fn extra_arguments() {
+        let errors = parse_errors("# comentário\n\nSOMA 1 # outro\n");
+        assert_eq!((errors[0].line, errors[0].column), (3, 6));
+        assert!(matches!(&errors[0].kind, ParseErrorKind::ExtraArguments(s) if s == "SOMA"));
+    }
+
+    #[test]
+    fn all_errors_are_reported() {
+        let errors = parse_errors("FOO\nSOMA\nAMEM\nCRCT y\n");
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn comments_and_separators() {
+        let code =
+            MepaCode::<i32>::from_str("L1: CRVI ; 1,, 2 // comentário\n# só comentário\n").unwrap();
+        assert_eq!(code.len(), 1);
+        assert_eq!(code[0].0.as_ref().map(|l| l.to_string()), Some("L1".into()));
+        assert!(matches!(code[0].1, Instruction::CRVI(1, 2)));
+    }
+
+    fn assembly_errors(source: &str) -> Vec<AssemblyError> {
+        match MepaCode::<i32>::from_str(source).unwrap().assemble() {
+            Err(MepaError::Assembly(errors)) => errors,
+            other => panic!("esperava erro de montagem, veio {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assemble_resolves_labels() {
+        let code = MepaCode::<i32>::from_str("    DSVS L1\nL0: NADA\nL1: CHPR L0\n")
+            .unwrap()
+            .assemble()
+            .unwrap();
+        assert!(code.is_resolved());
+        assert!(matches!(code[0].1, Instruction::DSVS(Label::Literal(2))));
+        assert!(matches!(code[2].1, Instruction::CHPR(Label::Literal(1))));
+        // os rótulos das linhas continuam lá
+        assert_eq!(code[2].0.as_ref().map(|l| l.to_string()), Some("L1".into()));
+    }
+
+    #[test]
+    fn duplicate_label() {
+        let errors = assembly_errors("L1: NADA\n    INPP\nL1: PARA\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].address, 2);
+        assert!(matches!(
+            &errors[0].kind,
+            AssemblyErrorKind::DuplicateLabel(label, 0) if label == "L1"
+        ));
+    }
+
+    #[test]
+    fn undefined_label() {
+        let errors = assembly_errors("    INPP\n    DSVF L9\n    PARA\n");
+        assert_eq!(errors[0].address, 1);
+        assert!(matches!(&errors[0].kind, AssemblyErrorKind::UndefinedLabel(l) if l == "L9"));
+    }
+
+    #[test]
+    fn target_out_of_range() {
+        let errors = assembly_errors("    INPP\n    DSVS 3\n    PARA\n");
+        assert_eq!(errors[0].address, 1);
+        assert!(matches!(
+            errors[0].kind,
+            AssemblyErrorKind::TargetOutOfRange(3)
+        ));
+    }
 
-        // Use the helper function to format the matrix into a single string.
-        // The result is wrapped in `Ok` to match the function's return type.
-        Ok(matrix_to_string(&matrix))
+    #[test]
+    fn assembly_errors_are_sorted_by_address() {
+        let errors = assembly_errors("    CHPR 10\n    DSVS X\nL: NADA\nL: NADA\n");
+        let addresses: Vec<usize> = errors.iter().map(|e| e.address).collect();
+        assert_eq!(addresses, vec![0, 1, 3]);
     }
 }
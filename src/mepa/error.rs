@@ -4,11 +4,186 @@ use std::io;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::instruction::Instruction;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParseErrorKind {
+    UnknownInstruction(String),
+    MissingArgument(String),
+    InvalidArgument(String),
+    /// Inteiro que não cabe no tipo do argumento; guarda o argumento e o nome do tipo
+    OutOfRange(String, String),
+    ExtraArguments(String),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnknownInstruction(s) => write!(f, "instrução desconhecida '{}'", s),
+            ParseErrorKind::MissingArgument(s) => write!(f, "falta argumento para {}", s),
+            ParseErrorKind::InvalidArgument(s) => write!(f, "argumento não inteiro '{}'", s),
+            ParseErrorKind::OutOfRange(s, t) => {
+                write!(f, "argumento '{}' fora do intervalo de {}", s, t)
+            }
+            ParseErrorKind::ExtraArguments(s) => write!(f, "argumentos extras para {}", s),
+        }
+    }
+}
+
+/// Erro ao interpretar uma linha de código MEPA.
+/// `line` e `column` começam em 1; `column` aponta para o token problemático.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MepaParseError {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for MepaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        writeln!(f, "{}:{}: {}", self.line, self.column, self.kind)?;
+        writeln!(f, "    {}", self.source_line)?;
+        write!(f, "    {:>width$}", "^", width = self.column)
+    }
+}
+
+impl Error for MepaParseError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AssemblyErrorKind {
+    /// Rótulo definido mais de uma vez; guarda o endereço da primeira definição
+    DuplicateLabel(String, usize),
+    UndefinedLabel(String),
+    TargetOutOfRange(usize),
+    /// `AMEM`, `DMEM` ou `RTPR` com quantidade negativa; guarda o mnemônico e o operando
+    NegativeAmount(String, i32),
+}
+
+/// Erro encontrado ao resolver os rótulos de um programa.
+/// `address` é o índice da instrução que causou o erro.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyError {
+    pub address: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            AssemblyErrorKind::DuplicateLabel(label, first) => write!(
+                f,
+                "instrução {}: rótulo '{}' já definido na instrução {}",
+                self.address, label, first
+            ),
+            AssemblyErrorKind::UndefinedLabel(label) => write!(
+                f,
+                "instrução {}: rótulo '{}' não definido",
+                self.address, label
+            ),
+            AssemblyErrorKind::TargetOutOfRange(target) => write!(
+                f,
+                "instrução {}: desvio para {} fora do programa",
+                self.address, target
+            ),
+            AssemblyErrorKind::NegativeAmount(mnemonic, n) => write!(
+                f,
+                "instrução {}: {} com quantidade negativa {}",
+                self.address, mnemonic, n
+            ),
+        }
+    }
+}
+
+impl Error for AssemblyError {}
+
+/// Limite de execução configurado na máquina
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Limit {
+    Steps(usize),
+    /// Tamanho máximo de M
+    Memory(usize),
+    /// Tempo máximo, em milissegundos
+    Timeout(u64),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps(n) => write!(f, "limite de {} passos", n),
+            Limit::Memory(n) => write!(f, "limite de {} posições de memória", n),
+            Limit::Timeout(ms) => write!(f, "limite de {} ms", ms),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MepaError {
     IO(String),
+    Parse(Vec<MepaParseError>),
+    Assembly(Vec<AssemblyError>),
     Runtime(String),
     MissingInput(usize),
+    /// `LEIT` executado depois que a entrada acabou
+    EndOfInput {
+        instruction_index: usize,
+    },
+    StackUnderflow {
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    InvalidAddress {
+        addr: i32,
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    DivisionByZero {
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    /// Resultado que não cabe na célula, com a aritmética no modo `Checked`
+    ArithmeticOverflow {
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    InvalidLevel {
+        level: i32,
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    /// Retorno de procedimento para um endereço fora do programa
+    InvalidJump {
+        target: i32,
+        instruction_index: usize,
+        instruction: Instruction,
+    },
+    /// Leitura de uma posição de M ainda não escrita, encontrada pelo sanitizador.
+    /// `variable` é o nome da variável da instrução, quando o código veio de um
+    /// programa ipt; com `indirect`, a posição foi alcançada pelo ponteiro nela
+    UninitializedRead {
+        addr: usize,
+        indirect: bool,
+        instruction_index: usize,
+        instruction: Instruction,
+        variable: Option<String>,
+    },
+    /// `ARMI` escrevendo acima do topo da pilha, encontrado pelo sanitizador
+    WriteAboveStack {
+        addr: usize,
+        s: i32,
+        instruction_index: usize,
+        instruction: Instruction,
+        variable: Option<String>,
+    },
+    LimitExceeded {
+        limit: Limit,
+        steps: usize,
+        instruction_index: usize,
+    },
     Other(String),
 }
 
@@ -16,8 +191,126 @@ impl fmt::Display for MepaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             MepaError::IO(e) => write!(f, "Erro de IO: {}", e),
+            MepaError::Parse(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "Erro de sintaxe: {}", e)?;
+                }
+                Ok(())
+            }
+            MepaError::Assembly(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "Erro de montagem: {}", e)?;
+                }
+                Ok(())
+            }
             MepaError::Runtime(msg) => write!(f, "Erro de execução: {}", msg),
             MepaError::MissingInput(linha) => write!(f, "Falta de input: linha {}", linha + 1),
+            MepaError::EndOfInput { instruction_index } => write!(
+                f,
+                "Erro de execução na instrução {} (LEIT): a entrada acabou",
+                instruction_index
+            ),
+            MepaError::StackUnderflow {
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): pilha vazia",
+                instruction_index, instruction
+            ),
+            MepaError::InvalidAddress {
+                addr,
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): endereço de memória inválido {}",
+                instruction_index, instruction, addr
+            ),
+            MepaError::DivisionByZero {
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): divisão por zero",
+                instruction_index, instruction
+            ),
+            MepaError::ArithmeticOverflow {
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): overflow aritmético",
+                instruction_index, instruction
+            ),
+            MepaError::InvalidLevel {
+                level,
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): nível léxico inválido {}",
+                instruction_index, instruction, level
+            ),
+            MepaError::InvalidJump {
+                target,
+                instruction_index,
+                instruction,
+            } => write!(
+                f,
+                "Erro de execução na instrução {} ({}): retorno para endereço inválido {}",
+                instruction_index, instruction, target
+            ),
+            MepaError::UninitializedRead {
+                addr,
+                indirect,
+                instruction_index,
+                instruction,
+                variable,
+            } => {
+                write!(
+                    f,
+                    "Erro de execução na instrução {} ({}): leitura de M[{}], que não foi inicializada",
+                    instruction_index, instruction, addr
+                )?;
+                match (variable, indirect) {
+                    (Some(variable), false) => write!(f, " (variável '{}')", variable),
+                    (Some(variable), true) => write!(f, " (apontada por '{}')", variable),
+                    (None, _) => Ok(()),
+                }
+            }
+            MepaError::WriteAboveStack {
+                addr,
+                s,
+                instruction_index,
+                instruction,
+                variable,
+            } => {
+                write!(
+                    f,
+                    "Erro de execução na instrução {} ({}): escrita em M[{}], acima do topo da pilha (s = {})",
+                    instruction_index, instruction, addr, s
+                )?;
+                if let Some(variable) = variable {
+                    write!(f, " (através da variável '{}')", variable)?;
+                }
+                Ok(())
+            }
+            MepaError::LimitExceeded {
+                limit,
+                steps,
+                instruction_index,
+            } => write!(
+                f,
+                "Execução interrompida: {} excedido após {} passos, na instrução {}",
+                limit, steps, instruction_index
+            ),
             MepaError::Other(msg) => write!(f, "Erro: {}", msg),
         }
     }
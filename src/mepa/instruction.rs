@@ -1,9 +1,13 @@
+use super::cell::Cell;
+use super::error::ParseErrorKind;
 use super::label::Label;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug)]
-pub enum Instruction {
-    CRCT(i32),
+/// Instrução MEPA. `C` é o tipo das células de `M`, usado no literal de `CRCT`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Instruction<C = i32> {
+    CRCT(C),
     CRVL(i32, i32),
     CREN(i32, i32),
     ARMZ(i32, i32),
@@ -37,7 +41,7 @@ pub enum Instruction {
     RTPR(i32, i32),
 }
 
-impl Instruction {
+impl<C: Cell> Instruction<C> {
     fn valid(s: &str) -> bool {
         [
             "CRCT", "CRVL", "CREN", "ARMZ", "CRVI", "ARMI", "SOMA", "SUBT", "MULT", "DIVI", "INVR",
@@ -46,204 +50,163 @@ impl Instruction {
         ]
         .contains(&s)
     }
-    pub fn parse(line: &[&str]) -> Result<(Option<Label>, Instruction), &'static str> {
-        let label = if Instruction::valid(line[0]) {
-            None
+    /// Interpreta uma linha já separada em tokens.
+    /// Em caso de erro, retorna o índice do token problemático junto com o motivo.
+    pub fn parse(line: &[&str]) -> Result<(Option<Label>, Self), (usize, ParseErrorKind)> {
+        let i = if Self::valid(line[0]) {
+            0
         } else {
+            match line.get(1) {
+                Some(next) if Self::valid(next) => 1,
+                // "CRTC 1": o primeiro token é uma instrução errada, não um rótulo
+                Some(next) if next.parse::<C>().is_ok() => {
+                    return Err((0, ParseErrorKind::UnknownInstruction(line[0].to_owned())))
+                }
+                Some(next) => {
+                    return Err((1, ParseErrorKind::UnknownInstruction(next.to_string())))
+                }
+                None => return Err((0, ParseErrorKind::UnknownInstruction(line[0].to_owned()))),
+            }
+        };
+        let label = if i == 1 {
             Some(Label::Simbolic(line[0].to_owned()))
+        } else {
+            None
         };
-        let i = if label.is_some() { 1 } else { 0 };
 
-        let missing_arg_err = "Missing argument";
-        let parse_err = "Failed to parse argument";
+        let mnemonic = line[i];
+        let n_args = line.len() - i - 1;
 
-        let instruction = match line.get(i) {
-            Some(&"CRCT") => Instruction::CRCT(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"CRVL") => {
-                if line.len() - i == 2 {
-                    Instruction::CRVL(
-                        0,
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                } else {
-                    Instruction::CRVL(
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                        line.get(i + 2)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                }
-            }
-            Some(&"CREN") => {
-                if line.len() - i == 2 {
-                    Instruction::CREN(
-                        0,
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                } else {
-                    Instruction::CREN(
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                        line.get(i + 2)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                }
-            }
-            Some(&"ARMZ") => {
-                if line.len() - i == 2 {
-                    Instruction::ARMZ(
-                        0,
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                } else {
-                    Instruction::ARMZ(
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                        line.get(i + 2)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                }
-            }
-            Some(&"CRVI") => {
-                if line.len() - i == 2 {
-                    Instruction::CRVI(
-                        0,
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                } else {
-                    Instruction::CRVI(
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                        line.get(i + 2)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                }
-            }
-            Some(&"ARMI") => {
-                if line.len() - i == 2 {
-                    Instruction::ARMI(
-                        0,
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                } else {
-                    Instruction::ARMI(
-                        line.get(i + 1)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                        line.get(i + 2)
-                            .ok_or(missing_arg_err)?
-                            .parse::<i32>()
-                            .map_err(|_| parse_err)?,
-                    )
-                }
-            }
-            Some(&"SOMA") => Self::SOMA,
-            Some(&"SUBT") => Self::SUBT,
-            Some(&"MULT") => Self::MULT,
-            Some(&"DIVI") => Self::DIVI,
-            Some(&"INVR") => Self::INVR,
-            Some(&"CONJ") => Self::CONJ,
-            Some(&"DISJ") => Self::DISJ,
-            Some(&"NEGA") => Self::NEGA,
-            Some(&"CMME") => Self::CMME,
-            Some(&"CMMA") => Self::CMMA,
-            Some(&"CMIG") => Self::CMIG,
-            Some(&"CMDG") => Self::CMDG,
-            Some(&"CMEG") => Self::CMEG,
-            Some(&"CMAG") => Self::CMAG,
-            Some(&"DSVS") => Self::DSVS(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<Label>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"DSVF") => Self::DSVF(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<Label>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"NADA") => Self::NADA,
-            Some(&"PARA") => Self::PARA,
-            Some(&"LEIT") => Self::LEIT,
-            Some(&"IMPR") => Self::IMPR,
-            Some(&"AMEM") => Instruction::AMEM(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"DMEM") => Instruction::DMEM(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"INPP") => Self::INPP,
-            Some(&"ENPR") => Self::ENPR(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"CHPR") => Self::CHPR(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<Label>()
-                    .map_err(|_| parse_err)?,
-            ),
-            Some(&"RTPR") => Self::RTPR(
-                line.get(i + 1)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-                line.get(i + 2)
-                    .ok_or(missing_arg_err)?
-                    .parse::<i32>()
-                    .map_err(|_| parse_err)?,
-            ),
-            _ => return Err("Unknown instruction"),
+        let token = |k: usize| {
+            line.get(i + 1 + k)
+                .ok_or_else(|| (i, ParseErrorKind::MissingArgument(mnemonic.to_owned())))
+        };
+        // distingue um inteiro que não cabe no tipo de algo que nem é inteiro
+        let bad_argument = |k: usize, type_name: &str| {
+            let word = line[i + 1 + k];
+            let kind = if word.parse::<num_bigint::BigInt>().is_ok() {
+                ParseErrorKind::OutOfRange(word.to_owned(), type_name.to_owned())
+            } else {
+                ParseErrorKind::InvalidArgument(word.to_owned())
+            };
+            (i + 1 + k, kind)
+        };
+        let int = |k: usize| {
+            token(k)?
+                .parse::<i32>()
+                .map_err(|_| bad_argument(k, i32::NAME))
+        };
+        let literal = |k: usize| token(k)?.parse::<C>().map_err(|_| bad_argument(k, C::NAME));
+        let label_arg = |k: usize| {
+            token(k)?.parse::<Label>().map_err(|_| {
+                (
+                    i + 1 + k,
+                    ParseErrorKind::InvalidArgument(line[i + 1 + k].to_owned()),
+                )
+            })
+        };
+
+        // instruções de acesso a memória aceitam o nível léxico como argumento-padrão 0
+        let (instruction, expected_args) = match mnemonic {
+            "CRCT" => (Self::CRCT(literal(0)?), 1),
+            "CRVL" if n_args == 1 => (Self::CRVL(0, int(0)?), 1),
+            "CRVL" => (Self::CRVL(int(0)?, int(1)?), 2),
+            "CREN" if n_args == 1 => (Self::CREN(0, int(0)?), 1),
+            "CREN" => (Self::CREN(int(0)?, int(1)?), 2),
+            "ARMZ" if n_args == 1 => (Self::ARMZ(0, int(0)?), 1),
+            "ARMZ" => (Self::ARMZ(int(0)?, int(1)?), 2),
+            "CRVI" if n_args == 1 => (Self::CRVI(0, int(0)?), 1),
+            "CRVI" => (Self::CRVI(int(0)?, int(1)?), 2),
+            "ARMI" if n_args == 1 => (Self::ARMI(0, int(0)?), 1),
+            "ARMI" => (Self::ARMI(int(0)?, int(1)?), 2),
+            "SOMA" => (Self::SOMA, 0),
+            "SUBT" => (Self::SUBT, 0),
+            "MULT" => (Self::MULT, 0),
+            "DIVI" => (Self::DIVI, 0),
+            "INVR" => (Self::INVR, 0),
+            "CONJ" => (Self::CONJ, 0),
+            "DISJ" => (Self::DISJ, 0),
+            "NEGA" => (Self::NEGA, 0),
+            "CMME" => (Self::CMME, 0),
+            "CMMA" => (Self::CMMA, 0),
+            "CMIG" => (Self::CMIG, 0),
+            "CMDG" => (Self::CMDG, 0),
+            "CMEG" => (Self::CMEG, 0),
+            "CMAG" => (Self::CMAG, 0),
+            "DSVS" => (Self::DSVS(label_arg(0)?), 1),
+            "DSVF" => (Self::DSVF(label_arg(0)?), 1),
+            "NADA" => (Self::NADA, 0),
+            "PARA" => (Self::PARA, 0),
+            "LEIT" => (Self::LEIT, 0),
+            "IMPR" => (Self::IMPR, 0),
+            "AMEM" => (Self::AMEM(int(0)?), 1),
+            "DMEM" => (Self::DMEM(int(0)?), 1),
+            "INPP" => (Self::INPP, 0),
+            "ENPR" => (Self::ENPR(int(0)?), 1),
+            "CHPR" => (Self::CHPR(label_arg(0)?), 1),
+            "RTPR" => (Self::RTPR(int(0)?, int(1)?), 2),
+            _ => unreachable!(),
         };
 
+        if n_args > expected_args {
+            return Err((
+                i + 1 + expected_args,
+                ParseErrorKind::ExtraArguments(mnemonic.to_owned()),
+            ));
+        }
+
         Ok((label, instruction))
     }
+    /// Nível léxico usado pela instrução, para as que acessam `D`
+    pub fn level(&self) -> Option<i32> {
+        match self {
+            Instruction::CRVL(k, _)
+            | Instruction::CREN(k, _)
+            | Instruction::ARMZ(k, _)
+            | Instruction::CRVI(k, _)
+            | Instruction::ARMI(k, _)
+            | Instruction::ENPR(k)
+            | Instruction::RTPR(k, _) => Some(*k),
+            _ => None,
+        }
+    }
+    /// A mesma instrução, com o literal de `CRCT` convertido por `f`
+    pub fn map_literal<D>(self, f: impl FnOnce(C) -> D) -> Instruction<D> {
+        match self {
+            Instruction::CRCT(k) => Instruction::CRCT(f(k)),
+            Instruction::CRVL(a, b) => Instruction::CRVL(a, b),
+            Instruction::CREN(a, b) => Instruction::CREN(a, b),
+            Instruction::ARMZ(a, b) => Instruction::ARMZ(a, b),
+            Instruction::CRVI(a, b) => Instruction::CRVI(a, b),
+            Instruction::ARMI(a, b) => Instruction::ARMI(a, b),
+            Instruction::SOMA => Instruction::SOMA,
+            Instruction::SUBT => Instruction::SUBT,
+            Instruction::MULT => Instruction::MULT,
+            Instruction::DIVI => Instruction::DIVI,
+            Instruction::INVR => Instruction::INVR,
+            Instruction::CONJ => Instruction::CONJ,
+            Instruction::DISJ => Instruction::DISJ,
+            Instruction::NEGA => Instruction::NEGA,
+            Instruction::CMME => Instruction::CMME,
+            Instruction::CMMA => Instruction::CMMA,
+            Instruction::CMIG => Instruction::CMIG,
+            Instruction::CMDG => Instruction::CMDG,
+            Instruction::CMEG => Instruction::CMEG,
+            Instruction::CMAG => Instruction::CMAG,
+            Instruction::DSVS(label) => Instruction::DSVS(label),
+            Instruction::DSVF(label) => Instruction::DSVF(label),
+            Instruction::NADA => Instruction::NADA,
+            Instruction::PARA => Instruction::PARA,
+            Instruction::LEIT => Instruction::LEIT,
+            Instruction::IMPR => Instruction::IMPR,
+            Instruction::AMEM(n) => Instruction::AMEM(n),
+            Instruction::DMEM(n) => Instruction::DMEM(n),
+            Instruction::INPP => Instruction::INPP,
+            Instruction::CHPR(label) => Instruction::CHPR(label),
+            Instruction::ENPR(k) => Instruction::ENPR(k),
+            Instruction::RTPR(k, n) => Instruction::RTPR(k, n),
+        }
+    }
     pub fn to_string_vec(&self) -> Vec<String> {
         match self {
             Instruction::CRCT(val) => vec!["CRCT".to_string(), val.to_string()],
@@ -284,7 +247,7 @@ impl Instruction {
     }
 }
 
-impl fmt::Display for Instruction {
+impl<C: Cell> fmt::Display for Instruction<C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Instruction::CRCT(val) => write!(f, "CRCT {}", val),
@@ -1,30 +1,13 @@
-use super::code::MepaCode;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Label {
     Simbolic(String),
     Literal(usize),
 }
 impl Label {
-    pub fn locate(&self, mc: &MepaCode) -> Option<usize> {
-        match self {
-            Self::Simbolic(s) => {
-                for (i, (op_label, _)) in mc.0.iter().enumerate() {
-                    if let Some(label) = op_label {
-                        if let Self::Simbolic(simbol) = label {
-                            if s == simbol {
-                                return Some(i);
-                            }
-                        }
-                    }
-                }
-                None
-            }
-            Self::Literal(u) => Some(*u),
-        }
-    }
     pub fn new(id: usize) -> Label {
         Self::Simbolic(format!("L{}", id))
     }
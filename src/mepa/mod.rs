@@ -1,4 +1,6 @@
+pub mod cell;
 pub mod code;
 pub mod error;
 pub mod instruction;
 pub mod label;
+pub mod syntax;
@@ -0,0 +1,263 @@
+use super::code::{tokenize, MepaCode};
+use super::error::{MepaError, MepaParseError, MepaResult};
+use super::{cell::Cell, instruction::Instruction, label::Label};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// O que acompanha uma instrução no arquivo além dela mesma
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trivia {
+    /// Linhas antes da instrução (comentários e linhas em branco), como estão no arquivo
+    pub leading: Vec<String>,
+    /// Comentário no fim da linha, com o marcador (`#` ou `//`)
+    pub trailing: Option<String>,
+    /// A linha como foi lida, reescrita sem mudanças enquanto a instrução for a mesma
+    pub original: Option<String>,
+}
+
+/// Programa MEPA junto com os comentários, linhas em branco e o alinhamento do arquivo.
+/// `to_string` devolve exatamente o texto lido; instruções alteradas ou novas são
+/// escritas na sintaxe de `MepaCode::to_string`, mantendo os comentários.
+#[derive(Clone, Debug)]
+pub struct MepaSyntax<C = i32> {
+    pub code: MepaCode<C>,
+    /// Uma posição para cada instrução de `code`
+    pub trivia: Vec<Trivia>,
+    /// Linhas depois da última instrução
+    pub trailer: Vec<String>,
+    /// Se as linhas terminam com `\r\n` em vez de `\n`
+    pub crlf: bool,
+    /// Se a última linha termina com quebra de linha
+    pub final_newline: bool,
+}
+
+impl<C> From<MepaCode<C>> for MepaSyntax<C> {
+    fn from(code: MepaCode<C>) -> Self {
+        let trivia = vec![Trivia::default(); code.len()];
+        MepaSyntax {
+            code,
+            trivia,
+            trailer: Vec::new(),
+            crlf: false,
+            final_newline: true,
+        }
+    }
+}
+
+impl<C: Cell> FromStr for MepaSyntax<C> {
+    type Err = MepaError;
+
+    fn from_str(input: &str) -> MepaResult<Self> {
+        Self::parse_source(input, None)
+    }
+}
+
+impl<C: Cell> MepaSyntax<C> {
+    pub fn new(code: MepaCode<C>, trivia: Vec<Trivia>) -> Self {
+        assert_eq!(code.len(), trivia.len(), "uma trivia por instrução");
+        MepaSyntax {
+            code,
+            trivia,
+            trailer: Vec::new(),
+            crlf: false,
+            final_newline: true,
+        }
+    }
+
+    pub fn from_file<P>(filename: P) -> MepaResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let source = fs::read_to_string(&filename)?;
+        Self::parse_source(&source, Some(filename.as_ref().display().to_string()))
+    }
+
+    // Interpreta todas as linhas, acumulando os erros em vez de parar no primeiro
+    pub(super) fn parse_source(source: &str, file: Option<String>) -> MepaResult<Self> {
+        let mut code = MepaCode::with_capacity(source.lines().count());
+        let mut trivia = Vec::with_capacity(code.0.capacity());
+        let mut pending = Vec::new();
+        let mut errors = Vec::new();
+
+        for (line_number, line) in source.lines().enumerate() {
+            let tokens = tokenize(line);
+            if tokens.is_empty() {
+                pending.push(line.to_owned());
+                continue;
+            }
+            let words: Vec<&str> = tokens.iter().map(|(_, word)| *word).collect();
+            match Instruction::parse(&words) {
+                Ok(instr) => {
+                    code.insert(instr);
+                    trivia.push(Trivia {
+                        leading: std::mem::take(&mut pending),
+                        trailing: comment_start(line).map(|i| line[i..].trim_end().to_owned()),
+                        original: Some(line.to_owned()),
+                    });
+                }
+                Err((token, kind)) => errors.push(MepaParseError {
+                    file: file.clone(),
+                    line: line_number + 1,
+                    column: tokens[token].0,
+                    source_line: line.to_owned(),
+                    kind,
+                }),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(MepaSyntax {
+                code,
+                trivia,
+                trailer: pending,
+                crlf: source.contains("\r\n"),
+                final_newline: source.ends_with('\n'),
+            })
+        } else {
+            Err(MepaError::Parse(errors))
+        }
+    }
+
+    /// Descarta o alinhamento original: todas as instruções passam a ser escritas
+    /// na sintaxe canônica, com os comentários alinhados depois delas
+    pub fn format(&mut self) {
+        for trivia in &mut self.trivia {
+            trivia.original = None;
+        }
+    }
+
+    /// Remove a instrução como `MepaCode::remove_instruction`, junto com os
+    /// comentários ligados a ela
+    pub fn remove_instruction(&mut self, index: usize) {
+        self.code.remove_instruction(index);
+        self.trivia.remove(index);
+    }
+
+    /// Troca o código por `code`, um resultado de transformar o atual em que a instrução
+    /// `i` veio da instrução `origins[i]`. As instruções que sobreviveram mantêm os
+    /// comentários e, se não mudaram, o alinhamento; os comentários das removidas
+    /// são descartados.
+    pub fn rebuild(self, code: MepaCode<C>, origins: &[usize]) -> Self {
+        assert_eq!(code.len(), origins.len(), "uma origem por instrução");
+        let trivia = origins
+            .iter()
+            .map(|&origin| self.trivia.get(origin).cloned().unwrap_or_default())
+            .collect();
+        MepaSyntax {
+            code,
+            trivia,
+            ..self
+        }
+    }
+
+    pub fn to_file<P>(&self, filename: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        if let Some(parent) = filename.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(filename, self.to_string())
+    }
+
+    // Se a linha lida ainda corresponde à instrução
+    fn unchanged(original: &str, (label, instruction): &(Option<Label>, Instruction<C>)) -> bool {
+        let words: Vec<&str> = tokenize(original).into_iter().map(|(_, w)| w).collect();
+        match Instruction::<C>::parse(&words) {
+            Ok((l, i)) => {
+                l.map(|l| l.to_string()) == label.as_ref().map(|l| l.to_string())
+                    && i.to_string_vec() == instruction.to_string_vec()
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl<C: Cell> fmt::Display for MepaSyntax<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let canonical = self.code.canonical_lines();
+        let width = canonical.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut lines = Vec::with_capacity(self.code.len() + self.trailer.len());
+        for ((line, trivia), text) in self.code.iter().zip(&self.trivia).zip(&canonical) {
+            lines.extend(trivia.leading.iter().cloned());
+            lines.push(match (&trivia.original, &trivia.trailing) {
+                (Some(original), _) if Self::unchanged(original, line) => original.clone(),
+                (_, Some(comment)) => format!("{:width$}  {}", text, comment),
+                (_, None) => text.clone(),
+            });
+        }
+        lines.extend(self.trailer.iter().cloned());
+
+        let ending = if self.crlf { "\r\n" } else { "\n" };
+        f.write_str(&lines.join(ending))?;
+        if self.final_newline && !lines.is_empty() {
+            f.write_str(ending)?;
+        }
+        Ok(())
+    }
+}
+
+/// Onde começa o comentário da linha (`#` ou `//`), se houver
+pub(super) fn comment_start(line: &str) -> Option<usize> {
+    match (line.find('#'), line.find("//")) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::otimizador::Otimizador;
+    use std::env;
+
+    fn parse(source: &str) -> MepaSyntax {
+        MepaSyntax::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn parse_then_display_is_lossless() {
+        for source in [
+            "# cabeçalho\n\n    INPP\n    AMEM 1   # x\n\n  // outro comentário\nL1:   CRCT 5\n    ARMZ 0,0\n    PARA\n\n# fim\n",
+            "INPP\r\n# crlf\r\nCRCT 1 // um\r\nIMPR\r\nPARA",
+            "",
+            "# só comentários\n\n",
+        ] {
+            assert_eq!(parse(source).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn optimized_instructions_keep_their_comments() {
+        let source = "# desvio para outro desvio\n    INPP\n    DSVS L1          # pula para L1\nL2: CRCT 7\n    IMPR\n    PARA\nL1: DSVS L2          # que pula para L2\n";
+        let optimized = Otimizador::from_syntax(parse(source), None::<&str>)
+            .unwrap()
+            .otimizar()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            optimized,
+            "# desvio para outro desvio\n    INPP\n    DSVS 2  # pula para L1\n    CRCT 7\n    IMPR\n    PARA\n"
+        );
+    }
+
+    #[test]
+    fn labels_are_written_the_same_way() {
+        let code: MepaCode = MepaCode::from_str("INPP\nDSVS L1\nL1: NADA\nPARA\n").unwrap();
+        let text = code.to_string().unwrap();
+        assert!(text.contains("L1: NADA"), "{}", text);
+        assert_eq!(MepaSyntax::from(code.clone()).to_string(), text);
+
+        let dir = env::temp_dir().join(format!("mepa-rs-syntax-{}", std::process::id()));
+        let (plain, syntax) = (dir.join("code.mepa"), dir.join("syntax.mepa"));
+        MepaSyntax::from(code.clone()).to_file(&syntax).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        code.to_file(&plain).unwrap();
+        assert_eq!(fs::read_to_string(&plain).unwrap(), text);
+        assert_eq!(fs::read_to_string(&syntax).unwrap(), text);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
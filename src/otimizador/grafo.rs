@@ -1,11 +1,11 @@
 use crate::mepa::code::MepaCode;
+use crate::mepa::error::MepaResult;
 use crate::mepa::instruction::Instruction;
 use crate::mepa::label::Label;
 use petgraph::dot::{Config, Dot};
 use petgraph::graph::NodeIndex;
 use petgraph::visit::{Dfs, EdgeRef};
 use petgraph::Graph;
-use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{self, Write};
@@ -94,10 +94,21 @@ pub struct CodeGraph {
 }
 
 impl CodeGraph {
-    pub fn new(mut code: MepaCode) -> CodeGraph {
+    pub fn new(code: MepaCode) -> MepaResult<CodeGraph> {
         // este pré-processamento deve ser feito para que a montagem do grafo funcione normalmente
-        code = remover_rotulos_simbolicos(code);
+        let code = remover_rotulos_simbolicos(code.assemble()?);
 
+        let mut grafo = CodeGraph::blocos(&code);
+
+        // mapeia o uso de memoria
+        grafo.mapear_memoria();
+
+        Ok(grafo)
+    }
+
+    /// Divide o código em blocos básicos ligados pelos desvios, sem mapear a memória.
+    /// O código precisa estar montado (todos os desvios literais)
+    pub fn blocos(code: &MepaCode) -> CodeGraph {
         let mut grafo = CodeGraph {
             grafo: Graph::new(),
             funcoes: Vec::new(),
@@ -121,11 +132,13 @@ impl CodeGraph {
                             }
                         }
                         Instruction::RTPR(_, _) => Some(vec![i + 1]), // Add the next instruction
+                        Instruction::CHPR(label) => Some(vec![label.unwrap()]), // Procedure entry
                         _ => None,
                     }
                 }
             })
             .flatten()
+            .filter(|&addr| addr < code.len())
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
@@ -199,9 +212,6 @@ impl CodeGraph {
 
         grafo.grafo.extend_with_edges(&arestas);
 
-        // mapeia o uso de memoria
-        grafo.mapear_memoria();
-
         grafo
     }
 
@@ -1143,7 +1153,8 @@ impl CodeGraph {
         }
     }
 
-    pub fn to_mepa_code(self) -> MepaCode {
+    /// O código de volta em ordem, junto com o endereço original de cada instrução
+    pub fn to_mepa_code_with_addresses(self) -> (MepaCode, Vec<usize>) {
         // Collect all instructions from all nodes into a single Vec
         let mut instructions: Vec<InstructionAndMetadata> = self
             .grafo
@@ -1189,7 +1200,11 @@ impl CodeGraph {
         }
 
         // Return the sorted Vec
-        MepaCode::from(instructions.into_iter().map(|line| line.instruction))
+        let addresses = instructions.iter().map(|line| line.address).collect();
+        (
+            MepaCode::from(instructions.into_iter().map(|line| line.instruction)),
+            addresses,
+        )
     }
 
     pub fn allocations(&self) -> impl Iterator<Item = &Allocation> {
@@ -1575,50 +1590,11 @@ impl CodeGraph {
     // }
 }
 
+// Recebe código já montado (todos os desvios literais); remove os rótulos das linhas e os NADA
 pub fn remover_rotulos_simbolicos(mc: MepaCode) -> MepaCode {
-    let mut labels = HashMap::new();
-
-    // Localiza todas labels
-    mc.0.iter()
-        .enumerate()
-        .for_each(|(line, (label_current_line, _))| {
-            if let Some(Label::Simbolic(s)) = label_current_line {
-                labels.insert(s.clone(), line);
-            }
-        });
-
-    // Transforma instruções usando mapa de labels
     let mut mc = MepaCode(
         mc.0.into_iter()
-            .map(|(_, instruction)| {
-                (
-                    None,
-                    match instruction {
-                        Instruction::DSVS(label) => {
-                            if let Label::Simbolic(s) = label {
-                                Instruction::DSVS(Label::Literal(*labels.get(&s).unwrap()))
-                            } else {
-                                Instruction::DSVS(label)
-                            }
-                        }
-                        Instruction::DSVF(label) => {
-                            if let Label::Simbolic(s) = label {
-                                Instruction::DSVF(Label::Literal(*labels.get(&s).unwrap()))
-                            } else {
-                                Instruction::DSVF(label)
-                            }
-                        }
-                        Instruction::CHPR(label) => {
-                            if let Label::Simbolic(s) = label {
-                                Instruction::CHPR(Label::Literal(*labels.get(&s).unwrap()))
-                            } else {
-                                Instruction::CHPR(label)
-                            }
-                        }
-                        _ => instruction,
-                    },
-                )
-            })
+            .map(|(_, instruction)| (None, instruction))
             .collect(),
     );
 
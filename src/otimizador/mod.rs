@@ -1,4 +1,4 @@
-mod grafo;
+pub(crate) mod grafo;
 mod otimizador;
 
 pub use otimizador::Otimizador;
@@ -1,7 +1,9 @@
 use super::grafo::{CodeGraph, InstructionAndMetadata};
 use crate::mepa::code::MepaCode;
+use crate::mepa::error::MepaResult;
 use crate::mepa::instruction::Instruction;
 use crate::mepa::label::Label;
+use crate::mepa::syntax::MepaSyntax;
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
 use std::error::Error;
@@ -15,19 +17,38 @@ where
     code: CodeGraph,
     verbose_level: usize,
     file_path: Option<P>,
+    // comentários do arquivo lido e, para cada endereço do grafo, a instrução
+    // correspondente no arquivo (o grafo é montado sem os NADA)
+    syntax: Option<(MepaSyntax, Vec<usize>)>,
 }
 
 impl<P> Otimizador<P>
 where
     P: AsRef<Path>,
 {
-    pub fn new(code: MepaCode, file_path: Option<P>) -> Self {
-        let code = CodeGraph::new(code);
-        Otimizador {
+    pub fn new(code: MepaCode, file_path: Option<P>) -> MepaResult<Self> {
+        let code = CodeGraph::new(code)?;
+        Ok(Otimizador {
             code,
             verbose_level: 0,
             file_path,
-        }
+            syntax: None,
+        })
+    }
+
+    /// Como `new`, mantendo em `save` os comentários e o alinhamento das instruções
+    /// que sobreviverem à otimização
+    pub fn from_syntax(syntax: MepaSyntax, file_path: Option<P>) -> MepaResult<Self> {
+        let origins = syntax
+            .code
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, instruction))| !matches!(instruction, Instruction::NADA))
+            .map(|(i, _)| i)
+            .collect();
+        let mut otimizador = Self::new(syntax.code.clone(), file_path)?;
+        otimizador.syntax = Some((syntax, origins));
+        Ok(otimizador)
     }
 
     pub fn verbose(mut self) -> Self {
@@ -73,9 +94,8 @@ where
         // code.to_mepa_code()
     }
     pub fn save(self) -> io::Result<()> {
-        if let Some(file_path) = self.file_path {
-            let code = self.code.to_mepa_code();
-            code.to_file(&file_path)
+        if let Some(file_path) = &self.file_path {
+            self.output().to_file(file_path)
         } else {
             Err(io::Error::new(
                 io::ErrorKind::Other,
@@ -90,7 +110,19 @@ where
     }
 
     pub fn to_string(&self) -> String {
-        self.code.clone().to_mepa_code().to_string().unwrap()
+        self.output().to_string()
+    }
+
+    // O código otimizado, com os comentários do arquivo lido, se houver
+    fn output(&self) -> MepaSyntax {
+        let (code, addresses) = self.code.clone().to_mepa_code_with_addresses();
+        match &self.syntax {
+            Some((syntax, origins)) => {
+                let origins: Vec<usize> = addresses.iter().map(|&a| origins[a]).collect();
+                syntax.clone().rebuild(code, &origins)
+            }
+            None => MepaSyntax::from(code),
+        }
     }
 }
 
@@ -99,9 +131,10 @@ where
     P: AsRef<Path>,
 {
     fn from(filename: P) -> Self {
-        let raw_code =
-            MepaCode::from_file(&filename).expect("Falha ao abrir arquivo para otimizar");
-        Otimizador::new(raw_code, Some(filename))
+        let syntax =
+            MepaSyntax::from_file(&filename).expect("Falha ao abrir arquivo para otimizar");
+        Otimizador::from_syntax(syntax, Some(filename))
+            .expect("Falha ao montar código para otimizar")
     }
 }
 
@@ -111,9 +144,10 @@ where
 {
     fn from(code: MepaCode) -> Self {
         Otimizador {
-            code: CodeGraph::new(code),
+            code: CodeGraph::new(code).expect("Falha ao montar código para otimizar"),
             verbose_level: 0,
             file_path: None,
+            syntax: None,
         }
     }
 }
@@ -0,0 +1,159 @@
+use super::Profile;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Nome da base de todas as pilhas, onde fica o programa principal
+const ROOT: &str = "programa";
+
+const WIDTH: f64 = 1200.0;
+const FRAME_HEIGHT: f64 = 16.0;
+const MARGIN: f64 = 10.0;
+const TITLE_HEIGHT: f64 = 30.0;
+// largura aproximada de um caractere a 12px, em fonte monoespaçada
+const CHAR_WIDTH: f64 = 7.2;
+
+/// Pilhas no formato "dobrado" de Brendan Gregg: uma linha por pilha de chamadas,
+/// com os nomes separados por `;` e o número de instruções executadas nela,
+/// ex.: `programa;main;fib;fib 42`
+pub fn folded(profile: &Profile) -> String {
+    let mut lines: Vec<(String, u64)> = profile
+        .stacks
+        .iter()
+        .map(|(stack, count)| {
+            let names: Vec<String> = std::iter::once(ROOT.to_owned())
+                .chain(stack.iter().map(|entry| profile.procedure_name(*entry)))
+                .collect();
+            (names.join(";"), *count)
+        })
+        .collect();
+    lines.sort();
+
+    let mut folded = String::new();
+    for (stack, count) in lines {
+        let _ = writeln!(folded, "{} {}", stack, count);
+    }
+    folded
+}
+
+// Nó da árvore de chamadas, com o total de instruções incluindo os filhos
+#[derive(Default)]
+struct Node {
+    total: u64,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn depth(&self) -> usize {
+        1 + self.children.values().map(Node::depth).max().unwrap_or(0)
+    }
+}
+
+/// Flamegraph em SVG, sem dependências externas, a partir das mesmas pilhas de `folded`
+pub fn svg(profile: &Profile, title: &str) -> String {
+    let mut root = Node::default();
+    for (stack, count) in &profile.stacks {
+        root.total += count;
+        let mut node = &mut root;
+        for entry in stack {
+            node = node
+                .children
+                .entry(profile.procedure_name(*entry))
+                .or_default();
+            node.total += count;
+        }
+    }
+
+    let depth = root.depth();
+    let height = TITLE_HEIGHT + depth as f64 * FRAME_HEIGHT + 2.0 * MARGIN;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="12">"#,
+        w = WIDTH,
+        h = height
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect x="0" y="0" width="{}" height="{}" fill="#f8f8f8"/>"##,
+        WIDTH, height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{}</text>"#,
+        WIDTH / 2.0,
+        MARGIN + 14.0,
+        escape(title)
+    );
+
+    if root.total > 0 {
+        let scale = (WIDTH - 2.0 * MARGIN) / root.total as f64;
+        let bottom = height - MARGIN - FRAME_HEIGHT;
+        render(&mut svg, ROOT, &root, MARGIN, bottom, scale, root.total);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Desenha o nó e, acima dele, os filhos
+fn render(svg: &mut String, name: &str, node: &Node, x: f64, y: f64, scale: f64, total: u64) {
+    let width = node.total as f64 * scale;
+    if width < 0.5 {
+        return;
+    }
+    let percent = 100.0 * node.total as f64 / total as f64;
+    let chars = ((width - 6.0) / CHAR_WIDTH).max(0.0) as usize;
+    let label: String = if name.chars().count() <= chars {
+        name.to_owned()
+    } else if chars > 2 {
+        name.chars().take(chars - 2).collect::<String>() + ".."
+    } else {
+        String::new()
+    };
+    let _ = writeln!(
+        svg,
+        r#"<g><title>{} ({} instruções, {:.2}%)</title><rect x="{:.2}" y="{:.2}" width="{:.2}" height="{}" rx="2" fill="{}"/><text x="{:.2}" y="{:.2}">{}</text></g>"#,
+        escape(name),
+        node.total,
+        percent,
+        x,
+        y,
+        width,
+        FRAME_HEIGHT - 1.0,
+        color(name),
+        x + 3.0,
+        y + 11.5,
+        escape(&label)
+    );
+
+    let mut child_x = x;
+    for (child_name, child) in &node.children {
+        render(
+            svg,
+            child_name,
+            child,
+            child_x,
+            y - FRAME_HEIGHT,
+            scale,
+            total,
+        );
+        child_x += child.total as f64 * scale;
+    }
+}
+
+// Tons quentes, estáveis para o mesmo nome
+fn color(name: &str) -> String {
+    let hash = name
+        .bytes()
+        .fold(5381u32, |h, b| h.wrapping_mul(33) ^ b as u32);
+    let r = 205 + hash % 50;
+    let g = (hash / 50) % 180;
+    let b = (hash / 9000) % 55;
+    format!("rgb({},{},{})", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,336 @@
+use crate::machine::{BasicMachine, ExecutionLimits, FullMachine, MachineObserver};
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::{MepaError, MepaResult};
+use crate::mepa::instruction::Instruction;
+use crate::mepa::label::Label;
+use crate::otimizador::grafo::CodeGraph;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+pub mod flamegraph;
+
+/// Execuções de um bloco básico (`start..=end`)
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockProfile {
+    pub start: usize,
+    pub end: usize,
+    /// Quantas vezes o bloco foi iniciado
+    pub count: u64,
+    /// Instruções executadas dentro do bloco
+    pub instructions: u64,
+}
+
+/// Custo de um procedimento, identificado pelo endereço do seu ENPR
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcedureProfile {
+    pub entry: usize,
+    pub name: String,
+    pub calls: u64,
+    /// Instruções executadas no próprio procedimento
+    pub self_steps: u64,
+    /// Instruções executadas no procedimento e nos que ele chamou
+    pub total_steps: u64,
+}
+
+/// Resultado de uma execução com contagem de instruções
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    #[serde(skip)]
+    pub code: MepaCode,
+    /// Execuções de cada endereço
+    pub counts: Vec<u64>,
+    pub blocks: Vec<BlockProfile>,
+    /// Ordenados pelo endereço de entrada
+    pub procedures: Vec<ProcedureProfile>,
+    /// Chamadas por destino de CHPR
+    pub calls: BTreeMap<usize, u64>,
+    pub total_steps: u64,
+    /// Instruções executadas com cada pilha de chamadas ativa, da mais externa para a
+    /// mais interna (endereços de entrada; vazia no programa principal)
+    #[serde(skip)]
+    pub stacks: HashMap<Vec<usize>, u64>,
+    pub output: Vec<i32>,
+    /// Erro que interrompeu a execução (um limite, por exemplo); as contagens vão
+    /// até o passo em que ele aconteceu
+    pub error: Option<MepaError>,
+}
+
+// Um procedimento em execução
+struct Frame {
+    entry: usize,
+    started_at: u64,
+}
+
+// Contagens feitas durante a execução
+#[derive(Default)]
+struct Counter {
+    counts: Vec<u64>,
+    calls: BTreeMap<usize, u64>,
+    self_steps: BTreeMap<usize, u64>,
+    total_steps_of: BTreeMap<usize, u64>,
+    frames: Vec<Frame>,
+    // entradas dos procedimentos em `frames`, para indexar `stacks` sem alocar
+    stack: Vec<usize>,
+    stacks: HashMap<Vec<usize>, u64>,
+    total_steps: u64,
+}
+
+impl MachineObserver for Counter {
+    fn on_step(&mut self, before: &BasicMachine, _: &Instruction) {
+        self.counts[before.i] += 1;
+        self.total_steps += 1;
+        if let Some(frame) = self.frames.last() {
+            *self.self_steps.entry(frame.entry).or_default() += 1;
+        }
+        match self.stacks.get_mut(self.stack.as_slice()) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.stack.clone(), 1);
+            }
+        }
+    }
+
+    fn on_call(&mut self, _: usize, entry: usize) {
+        *self.calls.entry(entry).or_default() += 1;
+        self.frames.push(Frame {
+            entry,
+            started_at: self.total_steps,
+        });
+        self.stack.push(entry);
+    }
+
+    fn on_return(&mut self, _: usize, _: usize) {
+        self.stack.pop();
+        if let Some(frame) = self.frames.pop() {
+            // em recursão, só a chamada mais externa conta no total
+            if !self.frames.iter().any(|f| f.entry == frame.entry) {
+                *self.total_steps_of.entry(frame.entry).or_default() +=
+                    self.total_steps - frame.started_at;
+            }
+        }
+    }
+
+    // procedimentos ainda ativos quando a execução foi interrompida
+    fn on_finish(&mut self) -> MepaResult<()> {
+        while !self.frames.is_empty() {
+            self.on_return(0, 0);
+        }
+        Ok(())
+    }
+}
+
+/// Executa o programa contando quantas vezes cada instrução roda.
+/// As saídas são coletadas em `Profile::output` em vez de impressas. Um erro durante
+/// a execução não descarta as contagens: fica em `Profile::error`.
+pub fn profile(code: MepaCode, input: Vec<i32>, limits: ExecutionLimits) -> MepaResult<Profile> {
+    let code = code.assemble()?;
+    let mut counter = Counter {
+        counts: vec![0; code.len()],
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+
+    let mut machine = FullMachine::new(code.clone())?
+        .with_limits(limits)
+        .add_output(&mut output)
+        .with_observer(&mut counter);
+    if !input.is_empty() {
+        machine = machine.add_input_vec(input);
+    }
+    let error = machine.execute().err();
+    drop(machine);
+    let Counter {
+        counts,
+        calls,
+        self_steps,
+        total_steps_of,
+        stacks,
+        total_steps,
+        ..
+    } = counter;
+
+    let grafo = CodeGraph::blocos(&code);
+    let mut blocks: Vec<BlockProfile> = grafo
+        .grafo
+        .node_weights()
+        .filter(|linhas| !linhas.is_empty())
+        .map(|linhas| {
+            let (start, end) = (linhas[0].address, linhas[linhas.len() - 1].address);
+            BlockProfile {
+                start,
+                end,
+                count: counts[start],
+                instructions: counts[start..=end].iter().sum(),
+            }
+        })
+        .collect();
+    blocks.sort_by_key(|b| b.start);
+
+    let procedures = calls
+        .iter()
+        .map(|(&entry, &n)| ProcedureProfile {
+            entry,
+            name: procedure_name(&code, entry),
+            calls: n,
+            self_steps: self_steps.get(&entry).copied().unwrap_or(0),
+            total_steps: total_steps_of.get(&entry).copied().unwrap_or(0),
+        })
+        .collect();
+
+    Ok(Profile {
+        code,
+        counts,
+        blocks,
+        procedures,
+        calls,
+        total_steps,
+        stacks,
+        output,
+        error,
+    })
+}
+
+// nome do rótulo na entrada do procedimento, se houver
+fn procedure_name(code: &MepaCode, entry: usize) -> String {
+    match code.get(entry) {
+        Some((Some(Label::Simbolic(name)), _)) => name.clone(),
+        _ => format!("proc_{}", entry),
+    }
+}
+
+impl Profile {
+    /// Troca os nomes dos procedimentos cujo rótulo de entrada aparece em `names`
+    /// (rótulo -> nome), por exemplo com os nomes das funções do código ipt
+    pub fn rename_procedures(&mut self, names: &HashMap<String, String>) {
+        for procedure in &mut self.procedures {
+            if let Some(name) = names.get(&procedure.name) {
+                procedure.name = name.clone();
+            }
+        }
+    }
+
+    /// Nome do procedimento que começa em `entry`
+    pub fn procedure_name(&self, entry: usize) -> String {
+        self.procedures
+            .iter()
+            .find(|p| p.entry == entry)
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| procedure_name(&self.code, entry))
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        if self.total_steps == 0 {
+            0.0
+        } else {
+            100.0 * count as f64 / self.total_steps as f64
+        }
+    }
+
+    /// O programa com as contagens e porcentagens de cada instrução na margem
+    pub fn listing(&self) -> String {
+        let mut listing = String::new();
+        for (addr, ((label, instruction), count)) in
+            self.code.iter().zip(self.counts.iter()).enumerate()
+        {
+            let label = label
+                .as_ref()
+                .map(|l| format!("{}:", l))
+                .unwrap_or_default();
+            let margin = if *count == 0 {
+                format!("{:>10} {:>7}", "-", "")
+            } else {
+                format!("{:>10} {:>6.2}%", count, self.percent(*count))
+            };
+            let _ = writeln!(
+                listing,
+                "{} | {:>4}  {:<6}{}",
+                margin, addr, label, instruction
+            );
+        }
+        listing
+    }
+
+    /// Tabelas de blocos, procedimentos e chamadas, das mais custosas para as menos
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        let _ = writeln!(
+            summary,
+            "Total de instruções executadas: {}",
+            self.total_steps
+        );
+
+        let _ = writeln!(summary, "\nProcedimentos:");
+        let _ = writeln!(
+            summary,
+            "{:<16} {:>8} {:>10} {:>8} {:>10} {:>8}",
+            "nome", "chamadas", "próprio", "%", "total", "%"
+        );
+        let mut procedures: Vec<&ProcedureProfile> = self.procedures.iter().collect();
+        procedures.sort_by_key(|p| std::cmp::Reverse(p.self_steps));
+        for p in procedures {
+            let _ = writeln!(
+                summary,
+                "{:<16} {:>8} {:>10} {:>7.2}% {:>10} {:>7.2}%",
+                format!("{} ({})", p.name, p.entry),
+                p.calls,
+                p.self_steps,
+                self.percent(p.self_steps),
+                p.total_steps,
+                self.percent(p.total_steps)
+            );
+        }
+
+        let _ = writeln!(summary, "\nBlocos:");
+        let _ = writeln!(
+            summary,
+            "{:<12} {:>10} {:>12} {:>8}",
+            "endereços", "execuções", "instruções", "%"
+        );
+        let mut blocks: Vec<&BlockProfile> = self.blocks.iter().filter(|b| b.count > 0).collect();
+        blocks.sort_by_key(|b| std::cmp::Reverse(b.instructions));
+        for b in blocks {
+            let _ = writeln!(
+                summary,
+                "{:<12} {:>10} {:>12} {:>7.2}%",
+                format!("{}-{}", b.start, b.end),
+                b.count,
+                b.instructions,
+                self.percent(b.instructions)
+            );
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mepa::error::Limit;
+
+    #[test]
+    fn interrupted_run_keeps_the_counts() {
+        let code = MepaCode::from_file("samples/mepa/recursao.mepa").unwrap();
+        let complete = profile(code.clone(), vec![], ExecutionLimits::default()).unwrap();
+        assert!(complete.error.is_none());
+
+        let limits = ExecutionLimits {
+            max_steps: Some(40),
+            ..Default::default()
+        };
+        let partial = profile(code, vec![], limits).unwrap();
+        assert!(matches!(
+            partial.error,
+            Some(MepaError::LimitExceeded {
+                limit: Limit::Steps(40),
+                ..
+            })
+        ));
+        assert_eq!(partial.total_steps, 40);
+        assert_eq!(partial.counts.iter().sum::<u64>(), 40);
+        assert!(partial.total_steps < complete.total_steps);
+        // o procedimento ainda ativo entra com os passos executados até o erro
+        let procedure = &partial.procedures[0];
+        assert!(procedure.calls > 0 && procedure.total_steps > 0);
+    }
+}
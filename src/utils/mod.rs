@@ -1,5 +1,9 @@
 use std::fs::File;
 use std::io::{self, Write};
+use std::str::FromStr;
+
+#[cfg(test)]
+pub(crate) mod samples;
 
 pub fn print_matrix(matrix: &Vec<Vec<String>>) {
     // Calculate the maximum width for each column
@@ -88,16 +92,18 @@ pub fn write_matrix(matrix: &Vec<Vec<String>>, file: File) -> io::Result<()> {
     Ok(())
 }
 
-pub fn input_i32() -> i32 {
+/// Pede um inteiro até receber um válido; `None` se o stdin acabar
+pub fn input_value<T: FromStr>() -> io::Result<Option<T>> {
     let mut input_line = String::new();
     let mut retry = false;
     loop {
         println!("Type an {}integer: ", if retry { "valid " } else { "" });
-        io::stdin()
-            .read_line(&mut input_line)
-            .expect("Failed to read line");
+        input_line.clear();
+        if io::stdin().read_line(&mut input_line)? == 0 {
+            return Ok(None);
+        }
         if let Ok(v) = input_line.trim().parse() {
-            return v;
+            return Ok(Some(v));
         }
         retry = true;
     }
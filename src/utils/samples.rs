@@ -0,0 +1,45 @@
+// Programas de exemplo usados pelos testes que comparam as implementações da máquina
+use crate::compiler::compile_from_str;
+use crate::machine::{ExecutionLimits, FullMachine};
+use crate::mepa::code::MepaCode;
+use std::fs;
+
+/// Entrada dada a todos os programas
+pub const INPUT: [i32; 10] = [5, 3, 8, 1, 9, 2, 7, 4, 6, 10];
+
+/// Os programas de samples/mepa e os algoritmos ipt compilados, com o caminho de cada um
+pub fn programs() -> Vec<(String, MepaCode)> {
+    let mut programs = Vec::new();
+    for (dir, ext) in [("samples/mepa", "mepa"), ("samples/ipt/algoritmos", "ipt")] {
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|e| e == ext))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let code = if ext == "mepa" {
+                MepaCode::from_file(&path).unwrap()
+            } else {
+                compile_from_str(&fs::read_to_string(&path).unwrap()).unwrap()
+            };
+            programs.push((path.display().to_string(), code));
+        }
+    }
+    programs
+}
+
+/// Executa na `FullMachine` com `INPUT`: o resultado (erros como texto), as saídas
+/// e os passos executados
+pub fn run_full(code: MepaCode, limits: ExecutionLimits) -> (Result<(), String>, Vec<i32>, usize) {
+    let mut output = Vec::new();
+    let mut machine = FullMachine::new(code)
+        .unwrap()
+        .add_input_vec(INPUT.to_vec())
+        .add_output(&mut output)
+        .with_limits(limits);
+    let result = machine.execute().map_err(|e| e.to_string());
+    let steps = machine.basic_machine().steps;
+    drop(machine);
+    (result, output, steps)
+}
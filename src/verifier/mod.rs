@@ -0,0 +1,465 @@
+use crate::mepa::code::MepaCode;
+use crate::mepa::error::MepaResult;
+use crate::mepa::instruction::Instruction;
+use crate::otimizador::grafo::CodeGraph;
+use petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyErrorKind {
+    /// A instrução desempilha mais valores do que existem no frame atual
+    StackUnderflow {
+        height: i32,
+        needed: i32,
+    },
+    /// Dois caminhos chegam na mesma instrução com alturas de pilha diferentes
+    HeightMismatch {
+        expected: i32,
+        found: i32,
+    },
+    /// CHPR com menos valores na pilha do que o procedimento chamado libera no RTPR
+    ArgumentMismatch {
+        procedure: usize,
+        expected: i32,
+        available: i32,
+    },
+    /// RTPRs de um mesmo procedimento liberam quantidades diferentes de argumentos
+    InconsistentReturn {
+        expected: i32,
+        found: i32,
+    },
+    /// RTPR executado com valores ainda na pilha do procedimento
+    UnbalancedReturn {
+        height: i32,
+    },
+    /// RTPR com nível léxico diferente do ENPR do procedimento
+    ReturnLevelMismatch {
+        expected: i32,
+        found: i32,
+    },
+    CallToNonProcedure(usize),
+    ReturnOutsideProcedure,
+    /// Desvio (ou continuação) para dentro de outro procedimento sem CHPR
+    JumpIntoProcedure {
+        procedure: usize,
+        target: usize,
+    },
+    /// A execução pode passar da última instrução sem encontrar PARA
+    FallsOffEnd,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    pub address: usize,
+    pub kind: VerifyErrorKind,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instrução {}: ", self.address)?;
+        match &self.kind {
+            VerifyErrorKind::StackUnderflow { height, needed } => write!(
+                f,
+                "desempilha {} valores, mas a pilha do frame tem {}",
+                needed, height
+            ),
+            VerifyErrorKind::HeightMismatch { expected, found } => write!(
+                f,
+                "caminhos chegam com alturas de pilha diferentes ({} e {})",
+                expected, found
+            ),
+            VerifyErrorKind::ArgumentMismatch {
+                procedure,
+                expected,
+                available,
+            } => write!(
+                f,
+                "procedimento em {} libera {} argumentos, mas só há {} valores na pilha",
+                procedure, expected, available
+            ),
+            VerifyErrorKind::InconsistentReturn { expected, found } => write!(
+                f,
+                "RTPR libera {} argumentos, mas outro RTPR do procedimento libera {}",
+                found, expected
+            ),
+            VerifyErrorKind::UnbalancedReturn { height } => {
+                write!(
+                    f,
+                    "RTPR com {} valores ainda na pilha do procedimento",
+                    height
+                )
+            }
+            VerifyErrorKind::ReturnLevelMismatch { expected, found } => write!(
+                f,
+                "RTPR no nível {}, mas o procedimento entrou com ENPR {}",
+                found, expected
+            ),
+            VerifyErrorKind::CallToNonProcedure(target) => {
+                write!(f, "CHPR para {}, que não é um ENPR", target)
+            }
+            VerifyErrorKind::ReturnOutsideProcedure => write!(f, "RTPR fora de procedimento"),
+            VerifyErrorKind::JumpIntoProcedure { procedure, target } => write!(
+                f,
+                "desvio para {}, dentro do procedimento em {}",
+                target, procedure
+            ),
+            VerifyErrorKind::FallsOffEnd => {
+                write!(f, "execução pode passar do fim do programa sem PARA")
+            }
+        }
+    }
+}
+
+// Uma região é o programa principal ou um procedimento (iniciado por ENPR)
+struct Region {
+    entry: NodeIndex,
+    // endereço do ENPR e seu nível léxico; None para o programa principal
+    procedure: Option<(usize, i32)>,
+    blocks: HashSet<NodeIndex>,
+    // endereço do maior RTPR alcançável, delimita o procedimento
+    end: usize,
+}
+
+/// Verifica estaticamente a disciplina de pilha e o fluxo de controle do programa.
+/// Retorna erro apenas se o código não puder ser montado; os problemas encontrados
+/// são devolvidos na lista, ordenados por endereço.
+pub fn verify(code: MepaCode) -> MepaResult<Vec<VerifyError>> {
+    let code = code.assemble()?;
+    let grafo = CodeGraph::blocos(&code);
+    let mut erros = Vec::new();
+
+    let block_of: HashMap<usize, NodeIndex> = grafo
+        .grafo
+        .node_indices()
+        .map(|node| (grafo.grafo[node][0].address, node))
+        .collect();
+
+    let entry_of = |node: NodeIndex| -> Option<i32> {
+        match grafo.grafo[node][0].instruction {
+            Instruction::ENPR(k) => Some(k),
+            _ => None,
+        }
+    };
+
+    // descobre as regiões, sem atravessar a entrada de outros procedimentos
+    let mut regions: Vec<Region> = grafo
+        .grafo
+        .node_indices()
+        .filter_map(|node| entry_of(node).map(|k| (node, k)))
+        .map(|(node, k)| (node, Some((grafo.grafo[node][0].address, k))))
+        .chain(block_of.get(&0).map(|node| (*node, None)))
+        .map(|(entry, procedure)| {
+            let mut blocks = HashSet::new();
+            let mut stack = vec![entry];
+            let mut end = procedure.map(|(addr, _)| addr).unwrap_or(0);
+            while let Some(node) = stack.pop() {
+                if !blocks.insert(node) {
+                    continue;
+                }
+                for line in &grafo.grafo[node] {
+                    if let Instruction::RTPR(_, _) = line.instruction {
+                        end = end.max(line.address);
+                    }
+                }
+                for next in grafo.grafo.neighbors(node) {
+                    if entry_of(next).is_none() {
+                        stack.push(next);
+                    }
+                }
+            }
+            Region {
+                entry,
+                procedure,
+                blocks,
+                end,
+            }
+        })
+        .collect();
+    // programa principal por último
+    regions.sort_by_key(|r| r.procedure.is_none());
+
+    // procedimento mais interno que contém o endereço
+    let owner = |addr: usize| -> Option<usize> {
+        regions
+            .iter()
+            .filter_map(|r| r.procedure.map(|(start, _)| (start, r.end)))
+            .filter(|(start, end)| addr >= *start && addr <= *end)
+            .max_by_key(|(start, _)| *start)
+            .map(|(start, _)| start)
+    };
+
+    // quantos argumentos cada procedimento libera no retorno
+    let mut args_of: HashMap<usize, i32> = HashMap::new();
+    for region in &regions {
+        let mut returns: Vec<(usize, i32, i32)> = region
+            .blocks
+            .iter()
+            .flat_map(|node| grafo.grafo[*node].iter())
+            .filter_map(|line| match line.instruction {
+                Instruction::RTPR(k, n) => Some((line.address, k, n)),
+                _ => None,
+            })
+            .collect();
+        returns.sort_unstable();
+        match region.procedure {
+            Some((start, level)) => {
+                for (address, k, n) in &returns {
+                    if *k != level {
+                        erros.push(VerifyError {
+                            address: *address,
+                            kind: VerifyErrorKind::ReturnLevelMismatch {
+                                expected: level,
+                                found: *k,
+                            },
+                        });
+                    }
+                    match args_of.get(&start) {
+                        Some(expected) if expected != n => erros.push(VerifyError {
+                            address: *address,
+                            kind: VerifyErrorKind::InconsistentReturn {
+                                expected: *expected,
+                                found: *n,
+                            },
+                        }),
+                        Some(_) => (),
+                        None => {
+                            args_of.insert(start, *n);
+                        }
+                    }
+                }
+            }
+            None => {
+                for (address, _, _) in returns {
+                    erros.push(VerifyError {
+                        address,
+                        kind: VerifyErrorKind::ReturnOutsideProcedure,
+                    });
+                }
+            }
+        }
+    }
+
+    // desvios e continuações para dentro de outros procedimentos
+    for region in &regions {
+        let current = region.procedure.map(|(start, _)| start);
+        for node in &region.blocks {
+            let last = grafo.grafo[*node].last().unwrap();
+            for next in grafo.grafo.neighbors(*node) {
+                let target = grafo.grafo[next][0].address;
+                // entrar num ENPR sem CHPR é sempre inválido, mesmo que seja o próprio
+                let target_owner = match entry_of(next) {
+                    Some(_) => Some(target),
+                    None => owner(target),
+                };
+                if let Some(procedure) = target_owner {
+                    if entry_of(next).is_some() || target_owner != current {
+                        erros.push(VerifyError {
+                            address: last.address,
+                            kind: VerifyErrorKind::JumpIntoProcedure { procedure, target },
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // interpretação abstrata: altura da pilha relativa ao frame de cada região
+    for region in &regions {
+        let mut heights: HashMap<NodeIndex, i32> = HashMap::new();
+        let mut mismatched = HashSet::new();
+        let mut queue = VecDeque::from([region.entry]);
+        heights.insert(region.entry, 0);
+
+        while let Some(node) = queue.pop_front() {
+            let mut height = heights[&node];
+            let mut continues = true;
+            let mut halted = false;
+
+            for line in &grafo.grafo[node] {
+                let mut underflow = |height: i32, needed: i32| {
+                    if height < needed {
+                        erros.push(VerifyError {
+                            address: line.address,
+                            kind: VerifyErrorKind::StackUnderflow { height, needed },
+                        });
+                    }
+                };
+                let mut chpr = None;
+                match &line.instruction {
+                    Instruction::CRCT(_)
+                    | Instruction::CRVL(_, _)
+                    | Instruction::CREN(_, _)
+                    | Instruction::CRVI(_, _)
+                    | Instruction::LEIT => height += 1,
+                    Instruction::ARMZ(_, _)
+                    | Instruction::ARMI(_, _)
+                    | Instruction::IMPR
+                    | Instruction::DSVF(_) => {
+                        underflow(height, 1);
+                        height -= 1;
+                    }
+                    Instruction::INVR | Instruction::NEGA => underflow(height, 1),
+                    Instruction::SOMA
+                    | Instruction::SUBT
+                    | Instruction::MULT
+                    | Instruction::DIVI
+                    | Instruction::CONJ
+                    | Instruction::DISJ
+                    | Instruction::CMME
+                    | Instruction::CMMA
+                    | Instruction::CMIG
+                    | Instruction::CMDG
+                    | Instruction::CMEG
+                    | Instruction::CMAG => {
+                        underflow(height, 2);
+                        height -= 1;
+                    }
+                    Instruction::AMEM(n) => height += n,
+                    Instruction::DMEM(n) => {
+                        underflow(height, *n);
+                        height -= n;
+                    }
+                    Instruction::INPP | Instruction::ENPR(_) => height = 0,
+                    Instruction::CHPR(label) => chpr = Some(label.unwrap()),
+                    Instruction::RTPR(_, _) => {
+                        if region.procedure.is_some() && height != 0 {
+                            erros.push(VerifyError {
+                                address: line.address,
+                                kind: VerifyErrorKind::UnbalancedReturn { height },
+                            });
+                        }
+                        continues = false;
+                    }
+                    Instruction::PARA => {
+                        halted = true;
+                        break;
+                    }
+                    Instruction::DSVS(_) => continues = false,
+                    Instruction::NADA => (),
+                }
+                if let Some(target) = chpr {
+                    if block_of.get(&target).and_then(|b| entry_of(*b)).is_none() {
+                        erros.push(VerifyError {
+                            address: line.address,
+                            kind: VerifyErrorKind::CallToNonProcedure(target),
+                        });
+                    } else if let Some(n) = args_of.get(&target) {
+                        if height < *n {
+                            erros.push(VerifyError {
+                                address: line.address,
+                                kind: VerifyErrorKind::ArgumentMismatch {
+                                    procedure: target,
+                                    expected: *n,
+                                    available: height,
+                                },
+                            });
+                        }
+                        height -= n;
+                    }
+                }
+                // evita que um erro se propague em cascata pelo resto do caminho
+                height = height.max(0);
+            }
+
+            if halted {
+                continue;
+            }
+            let last = grafo.grafo[node].last().unwrap();
+            if continues && last.address + 1 == code.len() {
+                erros.push(VerifyError {
+                    address: last.address,
+                    kind: VerifyErrorKind::FallsOffEnd,
+                });
+            }
+
+            for next in grafo.grafo.neighbors(node) {
+                if !region.blocks.contains(&next) {
+                    continue;
+                }
+                match heights.get(&next) {
+                    None => {
+                        heights.insert(next, height);
+                        queue.push_back(next);
+                    }
+                    Some(expected) => {
+                        if *expected != height && mismatched.insert(next) {
+                            erros.push(VerifyError {
+                                address: grafo.grafo[next][0].address,
+                                kind: VerifyErrorKind::HeightMismatch {
+                                    expected: *expected,
+                                    found: height,
+                                },
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    erros.sort_by_key(|e| e.address);
+    erros.dedup();
+    Ok(erros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn kinds(source: &str) -> Vec<(usize, VerifyErrorKind)> {
+        verify(MepaCode::from_str(source).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.address, e.kind))
+            .collect()
+    }
+
+    #[test]
+    fn samples_have_no_problems() {
+        for sample in ["c1", "ex5", "recursao", "sort"] {
+            let code = MepaCode::from_file(format!("samples/mepa/{}.mepa", sample)).unwrap();
+            assert_eq!(verify(code).unwrap(), vec![], "{}", sample);
+        }
+    }
+
+    #[test]
+    fn stack_underflow() {
+        let errors = kinds("    INPP\n    CRCT 1\n    SOMA\n    PARA\n");
+        assert_eq!(
+            errors,
+            vec![(
+                2,
+                VerifyErrorKind::StackUnderflow {
+                    height: 1,
+                    needed: 2
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn height_mismatch() {
+        let errors = kinds(
+            "    INPP\n    CRCT 0\n    DSVF L1\n    CRCT 7\nL1: NADA\n    DMEM 0\n    PARA\n",
+        );
+        // DSVF desvia com a pilha vazia; o caminho que empilha 7 chega com um valor
+        assert_eq!(
+            errors,
+            vec![(
+                4,
+                VerifyErrorKind::HeightMismatch {
+                    expected: 0,
+                    found: 1
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn falls_off_end() {
+        let errors = kinds("    INPP\n    CRCT 1\n    IMPR\n");
+        assert_eq!(errors, vec![(2, VerifyErrorKind::FallsOffEnd)]);
+    }
+}